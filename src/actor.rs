@@ -0,0 +1,363 @@
+// One task per running regular process: it owns the child, takes commands over a channel and handles the exit,
+// so handlers only ever send it a message and never wait on a process themselves
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "server")]
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio::sync::watch;
+
+use crate::events::EventKind;
+use crate::hooks::{self, Hook, HookContext};
+use crate::log_rules::Matcher;
+use crate::{ProcessConfig, ProcessStatus, RestartRequest, Supervisor};
+
+pub enum ProcessCommand {
+    // A line for the process's stdin
+    Input(String),
+    Kill,
+}
+
+// What a ManagedProcess keeps of its actor
+#[derive(Debug)]
+pub struct ActorHandle {
+    commands: UnboundedSender<ProcessCommand>,
+    interactive: bool,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ActorHandle {
+    pub fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    // Fails once the actor has finished, i.e. the process has exited
+    pub fn send(&self, command: ProcessCommand) -> Result<(), ProcessCommand> {
+        self.commands.send(command).map_err(|e| e.0)
+    }
+
+    // Asks for the process to be killed; the returned task finishes once it has been reaped
+    pub fn kill(self) -> tokio::task::JoinHandle<()> {
+        let _ = self.commands.send(ProcessCommand::Kill);
+        self.task
+    }
+}
+
+// The process an actor waits on
+pub enum Watched {
+    Child(tokio::process::Child),
+    // Started by a previous supervisor; only its pid and /proc start time are known
+    #[cfg(all(unix, feature = "server"))]
+    Adopted { pid: u32, start_time: u64 },
+}
+
+impl Watched {
+    // None when the exit status can't be known
+    async fn wait(&mut self) -> Option<ExitStatus> {
+        match self {
+            Watched::Child(child) => child.wait().await.ok(),
+            // Polls until the pid is gone. After an upgrade the process is still our child and is reaped
+            #[cfg(all(unix, feature = "server"))]
+            Watched::Adopted { pid, start_time } => loop {
+                let mut status = 0;
+                if unsafe { libc::waitpid(*pid as libc::pid_t, &mut status, libc::WNOHANG) } == *pid as libc::pid_t {
+                    return Some(std::os::unix::process::ExitStatusExt::from_raw(status));
+                }
+                if crate::runtime::start_time(*pid) != Some(*start_time) {
+                    return None;
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            },
+        }
+    }
+
+    fn kill(&mut self) {
+        match self {
+            Watched::Child(child) => {
+                let _ = child.start_kill();
+            }
+            #[cfg(all(unix, feature = "server"))]
+            Watched::Adopted { pid, .. } => unsafe {
+                libc::kill(*pid as libc::pid_t, libc::SIGKILL);
+            },
+        }
+    }
+}
+
+pub fn spawn(
+    supervisor: Supervisor,
+    config: ProcessConfig,
+    pid: u32,
+    run_count: u64,
+    watched: Watched,
+    stdin: Option<Box<dyn AsyncWrite + Unpin + Send>>,
+) -> ActorHandle {
+    let (commands, receiver) = unbounded_channel();
+    let interactive = stdin.is_some();
+    let stdin_sender = stdin.map(relay_input);
+    let task = tokio::spawn(run(supervisor, config, pid, run_count, watched, stdin_sender, receiver));
+    ActorHandle { commands, interactive, task }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run(
+    supervisor: Supervisor,
+    config: ProcessConfig,
+    pid: u32,
+    run_count: u64,
+    mut watched: Watched,
+    stdin_sender: Option<UnboundedSender<String>>,
+    mut commands: UnboundedReceiver<ProcessCommand>,
+) {
+    let mut listening = true;
+    let mut stop_requested = false;
+    let exit_status = loop {
+        tokio::select! {
+            exit_status = watched.wait() => break exit_status,
+            command = commands.recv(), if listening => match command {
+                Some(ProcessCommand::Input(input)) => {
+                    if let Some(sender) = &stdin_sender {
+                        let _ = sender.send(input);
+                    }
+                }
+                Some(ProcessCommand::Kill) => {
+                    // Stop, restart and removal wait for this task, and so for the hook
+                    if !stop_requested {
+                        stop_requested = true;
+                        let _ = hooks::run(&supervisor, Hook::PreStop, &config, &HookContext { pid: Some(pid), ..HookContext::run(run_count) }).await;
+                    }
+                    watched.kill();
+                }
+                // The handle was dropped without a kill, e.g. by an upgrade; keep watching the process
+                None => listening = false,
+            },
+        }
+    };
+    on_exit(supervisor, config, pid, run_count, exit_status, stop_requested);
+}
+
+// Runs the exit hooks, updates the process entry and requests an auto-restart if it is still supposed to be running
+fn on_exit(
+    supervisor: Supervisor,
+    config: ProcessConfig,
+    pid: u32,
+    run_count: u64,
+    exit_status: Option<ExitStatus>,
+    stop_requested: bool,
+) {
+    let now = supervisor.now();
+    supervisor.log_system(&config.log_file, &format!("Process '{}' (PID: {}) has exited{}", config.id, pid,
+        match exit_status {
+            Some(status) => format!(" with status: {}", status),
+            None => String::new(),
+        }));
+    supervisor.emit(&config.id, EventKind::Exited { exit_code: exit_status.and_then(|status| status.code()) });
+
+    let context = HookContext::exited(pid, run_count, exit_status);
+    let exit_hooks = if stop_requested {
+        vec![Hook::OnExit, Hook::PostStop]
+    } else if context.crashed() {
+        vec![Hook::OnExit, Hook::OnCrash]
+    } else {
+        vec![Hook::OnExit]
+    };
+    hooks::spawn(&supervisor, exit_hooks, config.clone(), context.clone());
+
+    let restart_delay = 'entry: {
+        let mut processes_lock = supervisor.process_map().lock().unwrap();
+        // A stop, restart or removal has already moved the entry on from this pid
+        let Some(managed_process) = processes_lock.get_mut(&config.id).filter(|managed_process| managed_process.pid == Some(pid)) else {
+            break 'entry None;
+        };
+        managed_process.last_exit_time = Some(now);
+        managed_process.pid = None;
+        managed_process.actor = None;
+        // The live config, so auto_restart changes made with PATCH apply=on_next_restart count
+        if matches!(managed_process.status, ProcessStatus::Running) && managed_process.config.auto_restart {
+            managed_process.status = ProcessStatus::Failed;
+            // Calculate restart delay with exponential backoff to prevent restart loops
+            let delay = crate::calculate_restart_delay(now, managed_process.run_count, managed_process.last_exit_time);
+            managed_process.run_count += 1;
+            Some(delay)
+        } else {
+            if !matches!(managed_process.status, ProcessStatus::ManuallyStopped) {
+                managed_process.status = ProcessStatus::Stopped;
+            }
+            None
+        }
+    };
+    // Exits, stops and removals all end here, so the state file never lists a process that is gone
+    #[cfg(unix)]
+    crate::runtime::save(&supervisor);
+    let Some(delay_seconds) = restart_delay else {
+        return;
+    };
+
+    supervisor.log_system(&config.log_file,
+        &format!("Initiating auto-restart for process '{}' (PID: {}) in {}s", config.id, pid, delay_seconds));
+
+    let reason = match exit_status.map(|status| status.code().unwrap_or(-1)) {
+        Some(0) => format!("Auto-restarting process '{}' after normal exit (PID: {})", config.id, pid),
+        Some(exit_code @ (9 | 15)) => format!("Auto-restarting process '{}' after external kill signal {} (PID: {})", config.id, exit_code, pid),
+        Some(exit_code) => format!("Auto-restarting process '{}' after crash with exit code {} (PID: {})", config.id, exit_code, pid),
+        None => format!("Auto-restarting process '{}' after PID {} exited", config.id, pid),
+    };
+    crate::schedule_restart(&supervisor, &config, context, RestartRequest { process_id: config.id.clone(), delay_seconds, reason });
+}
+
+// Writes each line sent on the returned channel to a child's stdin
+fn relay_input<W: AsyncWrite + Unpin + Send + 'static>(mut stdin: W) -> UnboundedSender<String> {
+    let (stdin_sender, mut stdin_receiver) = unbounded_channel::<String>();
+    tokio::spawn(async move {
+        while let Some(input) = stdin_receiver.recv().await {
+            let written = async {
+                stdin.write_all(format!("{}\n", input).as_bytes()).await?;
+                stdin.flush().await
+            };
+            if let Err(e) = written.await {
+                eprintln!("Failed to write to process stdin: {}", e);
+                break;
+            }
+        }
+    });
+    stdin_sender
+}
+
+// The output relays of one supervisor's processes
+pub struct Relays {
+    // Set while an upgrade is about to exec, see pause_relays
+    paused: watch::Sender<bool>,
+    // Relays still reading, i.e. not paused and not finished
+    active: AtomicUsize,
+}
+
+impl Relays {
+    pub fn new() -> Self {
+        Self { paused: watch::channel(false).0, active: AtomicUsize::new(0) }
+    }
+}
+
+// Copies a child's stdout or stderr to its log and the console, line by line, checking each against the log rules
+pub fn relay_output<R: AsyncRead + Unpin + Send + 'static>(
+    supervisor: Supervisor,
+    output: R,
+    log_file_path: String,
+    process_id: String,
+    stderr: bool,
+    rules: Option<Arc<Matcher>>,
+) {
+    let relays = supervisor.clone();
+    let relay_line = move |line: &[u8]| {
+        let line = String::from_utf8_lossy(line);
+        let line = line.strip_suffix('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).unwrap_or(&line);
+        let log_entry = format!("[{}] {}: {}\n",
+            supervisor.now().format("%Y-%m-%d %H:%M:%S"), if stderr { "STDERR" } else { "STDOUT" }, line);
+        let _ = supervisor.logger().append_process_log(&log_file_path, &log_entry);
+        if let Some(rules) = &rules {
+            rules.check(line, stderr);
+        }
+        if stderr {
+            eprintln!("[{}] ERROR: {}", process_id, line);
+        } else {
+            println!("[{}] {}", process_id, line);
+        }
+    };
+
+    relays.relays().active.fetch_add(1, Ordering::SeqCst);
+    tokio::spawn(async move {
+        let active = &relays.relays().active;
+        let mut paused = relays.relays().paused.subscribe();
+        let mut reader = BufReader::new(output);
+        // read_until keeps what it has read so far when cancelled, so a partial line survives a pause
+        let mut line = Vec::new();
+        loop {
+            if *paused.borrow_and_update() {
+                // Whatever is left in the pipe stays there for the next binary, only the partial line is ours
+                if !line.is_empty() {
+                    relay_line(&line);
+                    line.clear();
+                }
+                active.fetch_sub(1, Ordering::SeqCst);
+                while *paused.borrow_and_update() {
+                    let _ = paused.changed().await;
+                }
+                active.fetch_add(1, Ordering::SeqCst);
+                continue;
+            }
+            tokio::select! {
+                _ = paused.changed() => {}
+                read = reader.read_until(b'\n', &mut line) => match read {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        relay_line(&line);
+                        line.clear();
+                    }
+                    Err(_) => {
+                        if !line.is_empty() {
+                            relay_line(&line);
+                        }
+                        break;
+                    }
+                },
+            }
+        }
+        active.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+// Stops the relays reading before an upgrade execs, writing out the line each was in the middle of. Waits until
+// they all have, or up to a second. The new binary reads the rest from the pipes
+#[cfg(feature = "server")]
+pub async fn pause_relays(supervisor: &Supervisor) {
+    let relays = supervisor.relays();
+    relays.paused.send_replace(true);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(1);
+    while relays.active.load(Ordering::SeqCst) > 0 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+// For when the exec failed
+#[cfg(feature = "server")]
+pub fn resume_relays(supervisor: &Supervisor) {
+    supervisor.relays().paused.send_replace(false);
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pausing_relays_writes_out_the_partial_line() {
+        let log_file = std::env::temp_dir().join(format!("exeio-relay-test-{}.log", std::process::id()));
+        let log_file = log_file.to_string_lossy().to_string();
+        let _ = std::fs::remove_file(&log_file);
+        let supervisor = Supervisor::builder().config_store(crate::MemoryConfigStore::default()).build();
+        supervisor.logger().open_process_log(&log_file).unwrap();
+        let read_log = || {
+            supervisor.logger().flush_process_logs();
+            std::fs::read_to_string(&log_file).unwrap_or_default()
+        };
+
+        let (mut child_end, relay_end) = tokio::io::duplex(64);
+        relay_output(supervisor.clone(), relay_end, log_file.clone(), "relay-test".to_string(), false, None);
+        child_end.write_all(b"first\r\nsecond li").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(read_log().contains("STDOUT: first\n"));
+
+        pause_relays(&supervisor).await;
+        assert!(read_log().ends_with("STDOUT: second li\n"));
+        // Nothing is read while paused
+        child_end.write_all(b"ne\n").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!read_log().contains("STDOUT: ne"));
+
+        resume_relays(&supervisor);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(read_log().ends_with("STDOUT: ne\n"));
+        let _ = std::fs::remove_file(&log_file);
+    }
+}
@@ -0,0 +1,328 @@
+// Append-only JSON lines audit log of every mutating API call
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::Filter;
+
+use crate::error::ApiError;
+use crate::keys::{Caller, KeyStore, Scope};
+use crate::{ApiResponse, FileLogSink, LocalPeer, LogSink};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEntry {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub identity: String,
+    pub source: String,
+    pub method: String,
+    pub route: String,
+    pub process_id: Option<String>,
+    pub details: Option<serde_json::Value>,
+    pub success: bool,
+    pub message: String,
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    hash_input: bool,
+    files: FileLogSink,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, hash_input: bool) -> Self {
+        // Create the file up front so it is only readable by the exeio user
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        if let Err(e) = options.open(&path) {
+            eprintln!("Failed to open audit log {}: {}", path.display(), e);
+        }
+
+        Self { path, hash_input, files: FileLogSink::default() }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn append(&self, entry: &AuditEntry) {
+        match serde_json::to_string(entry) {
+            Ok(line) => {
+                if let Err(e) = self.files.append(&self.path.to_string_lossy(), &format!("{}\n", line)) {
+                    eprintln!("Failed to write audit log: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to serialize audit entry: {}", e),
+        }
+    }
+
+    // Newest first, only reading as far back as the caller iterates
+    fn lines_from_end(&self) -> Result<LinesFromEnd, std::io::Error> {
+        let file = File::open(&self.path)?;
+        let pos = file.metadata()?.len();
+        Ok(LinesFromEnd { file, pos, partial: Vec::new(), lines: Vec::new() })
+    }
+}
+
+const CHUNK_SIZE: u64 = 32768;
+
+// Reads a file backwards in chunks and yields its lines from the last one
+struct LinesFromEnd {
+    file: File,
+    // Start of what has been read so far
+    pos: u64,
+    // The first line of what has been read, which may continue in the previous chunk
+    partial: Vec<u8>,
+    // Complete lines not yet yielded, the last one first
+    lines: Vec<String>,
+}
+
+impl Iterator for LinesFromEnd {
+    type Item = Result<String, std::io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(line) = self.lines.pop() {
+                return Some(Ok(line));
+            }
+            if self.pos == 0 {
+                if self.partial.is_empty() {
+                    return None;
+                }
+                let line = std::mem::take(&mut self.partial);
+                return Some(Ok(String::from_utf8_lossy(&line).into_owned()));
+            }
+
+            let start = self.pos.saturating_sub(CHUNK_SIZE);
+            let mut chunk = vec![0u8; (self.pos - start) as usize];
+            if let Err(e) = self.file.seek(SeekFrom::Start(start)).and_then(|_| self.file.read_exact(&mut chunk)) {
+                self.pos = 0;
+                self.partial.clear();
+                return Some(Err(e));
+            }
+            self.pos = start;
+            chunk.append(&mut self.partial);
+            self.partial = chunk;
+
+            if let Some(newline) = self.partial.iter().position(|&byte| byte == b'\n') {
+                let complete = self.partial.split_off(newline + 1);
+                self.partial.truncate(newline);
+                self.lines = complete
+                    .split(|&byte| byte == b'\n')
+                    .filter(|line| !line.is_empty())
+                    .map(|line| String::from_utf8_lossy(line).into_owned())
+                    .collect();
+            }
+        }
+    }
+}
+
+// Who is calling and from where, extracted alongside authentication for mutating routes
+#[derive(Clone)]
+pub struct AuditContext {
+    log: Arc<AuditLog>,
+    pub caller: Caller,
+    source: String,
+    method: String,
+    route: String,
+}
+
+impl AuditContext {
+    pub fn record(&self, process_id: Option<&str>, details: Option<serde_json::Value>, response: &ApiResponse) {
+        self.log.append(&AuditEntry {
+            timestamp: chrono::Utc::now(),
+            identity: self.caller.name.clone(),
+            source: self.source.clone(),
+            method: self.method.clone(),
+            route: self.route.clone(),
+            process_id: process_id.map(String::from),
+            details,
+            success: response.success,
+            message: response.message.clone(),
+        });
+    }
+
+    pub fn record_error(&self, process_id: Option<&str>, details: Option<serde_json::Value>, error: &ApiError) {
+        self.record(process_id, details, &ApiResponse { success: false, message: error.message() });
+    }
+
+    // Input sent to a process is stored as-is unless --audit-hash-input is set
+    pub fn input_details(&self, input: &str) -> serde_json::Value {
+        if self.log.hash_input {
+            serde_json::json!({ "input_sha256": crate::keys::hash_key(input) })
+        } else {
+            serde_json::json!({ "input": input })
+        }
+    }
+}
+
+pub fn with_audit(
+    keys: Arc<KeyStore>,
+    log: Arc<AuditLog>,
+    scope: Scope,
+) -> impl Filter<Extract = (AuditContext,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("exeio-api-key")
+        .and(warp::ext::optional::<LocalPeer>())
+        .and(crate::network::with_client_ip())
+        .and(warp::method())
+        .and(warp::path::full())
+        .and_then(move |provided_key: Option<String>, peer: Option<LocalPeer>, client: Option<std::net::IpAddr>, method: warp::http::Method, path: warp::path::FullPath| {
+            let keys = keys.clone();
+            let log = log.clone();
+            async move {
+                let source = crate::ratelimit::client_source(client, peer.as_ref());
+                let had_key = provided_key.is_some();
+
+                match crate::validate_api_key(provided_key, peer, client, keys, scope).await {
+                    Ok(caller) => Ok(AuditContext {
+                        log,
+                        caller,
+                        source,
+                        method: method.to_string(),
+                        route: path.as_str().to_string(),
+                    }),
+                    Err(rejection) => {
+                        // Failed authentication and missing scopes are audited too
+                        let message = if let Some(error) = rejection.find::<ApiError>() {
+                            error.message()
+                        } else if let Some(limited) = rejection.find::<crate::ratelimit::TooManyRequests>() {
+                            limited.message.clone()
+                        } else {
+                            "Invalid or missing API key".to_string()
+                        };
+                        log.append(&AuditEntry {
+                            timestamp: chrono::Utc::now(),
+                            identity: if had_key { "invalid-key".to_string() } else { "anonymous".to_string() },
+                            source,
+                            method: method.to_string(),
+                            route: path.as_str().to_string(),
+                            process_id: None,
+                            details: None,
+                            success: false,
+                            message,
+                        });
+                        Err(rejection)
+                    }
+                }
+            }
+        })
+}
+
+// Same as with_audit, for routes that take a process id and must check the key may access it
+pub fn with_process_audit(
+    keys: Arc<KeyStore>,
+    log: Arc<AuditLog>,
+    scope: Scope,
+) -> impl Filter<Extract = (String, AuditContext), Error = warp::Rejection> + Clone {
+    warp::path::param::<String>()
+        .and(with_audit(keys, log, scope))
+        .and_then(authorize_process)
+        .untuple_one()
+}
+
+// For routes that matched the process id themselves, e.g. /v1/processes/:id
+pub async fn authorize_process(id: String, audit: AuditContext) -> Result<(String, AuditContext), warp::Rejection> {
+    if audit.caller.can_access(&id) {
+        Ok((id, audit))
+    } else {
+        let error = ApiError::Forbidden(format!("Key '{}' is not allowed to access process '{}'", audit.caller.name, id));
+        audit.record_error(Some(&id), None, &error);
+        Err(warp::reject::custom(error))
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AuditQuery {
+    page: Option<usize>,
+    page_size: Option<usize>,
+    process_id: Option<String>,
+    identity: Option<String>,
+    route: Option<String>,
+    success: Option<bool>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct AuditPage {
+    success: bool,
+    page: usize,
+    page_size: usize,
+    /// Whether older entries match too, on the next page
+    has_more: bool,
+    entries: Vec<AuditEntry>,
+}
+
+pub async fn handle_audit_log(query: AuditQuery, log: Arc<AuditLog>) -> Result<impl warp::Reply, warp::Rejection> {
+    let read_error = |e: std::io::Error| warp::reject::custom(ApiError::Io(format!("Failed to read audit log: {}", e)));
+    let lines = log.lines_from_end().map_err(read_error)?;
+
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(50).clamp(1, 1000);
+    let to_skip = (page - 1) * page_size;
+
+    // Newest first, like /logs. Reading stops once the page is full, or at `since`, as entries are appended in order
+    let mut entries = Vec::new();
+    let mut matching = 0;
+    let mut has_more = false;
+    for line in lines {
+        let Ok(entry) = serde_json::from_str::<AuditEntry>(&line.map_err(read_error)?) else {
+            continue;
+        };
+        if query.since.is_some_and(|since| entry.timestamp < since) {
+            break;
+        }
+        let matches = query.process_id.as_ref().is_none_or(|id| entry.process_id.as_ref() == Some(id))
+            && query.identity.as_ref().is_none_or(|identity| &entry.identity == identity)
+            && query.route.as_ref().is_none_or(|route| entry.route.starts_with(route.as_str()))
+            && query.success.is_none_or(|success| entry.success == success)
+            && query.until.is_none_or(|until| entry.timestamp <= until);
+        if !matches {
+            continue;
+        }
+        matching += 1;
+        if matching <= to_skip {
+            continue;
+        }
+        if entries.len() == page_size {
+            has_more = true;
+            break;
+        }
+        entries.push(entry);
+    }
+
+    let response = AuditPage {
+        success: true,
+        page,
+        page_size,
+        has_more,
+        entries,
+    };
+    Ok(warp::reply::json(&response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_read_newest_first_across_chunks() {
+        let path = std::env::temp_dir().join(format!("exeio-audit-test-{}.log", std::process::id()));
+        // Long enough lines that some straddle chunk boundaries
+        let lines: Vec<String> = (0..2000).map(|i| format!("{}:{}", i, "x".repeat(i % 97))).collect();
+        std::fs::write(&path, format!("{}\n", lines.join("\n"))).unwrap();
+
+        let log = AuditLog { path: path.clone(), hash_input: false, files: FileLogSink::default() };
+        let read: Vec<String> = log.lines_from_end().unwrap().map(Result::unwrap).collect();
+        let expected: Vec<String> = lines.into_iter().rev().collect();
+        assert_eq!(read, expected);
+        let _ = std::fs::remove_file(&path);
+    }
+}
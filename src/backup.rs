@@ -0,0 +1,289 @@
+// Versioned export archive of the supervisor state (GET /export) and restoring one (POST /restore)
+use std::collections::{BTreeMap, HashSet};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::AuditContext;
+use crate::config_file::{FileSettings, Settings};
+use crate::error::{ApiError, FieldError};
+use crate::{ApiResponse, ProcessConfig, SETTINGS, Supervisor};
+
+const ARCHIVE_FORMAT: &str = "exeio-export";
+// Bumped whenever the archive layout changes in a way older versions can't read
+const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Archive {
+    /// Always "exeio-export"
+    format: String,
+    /// Archive layout version, currently 1
+    version: u32,
+    exeio_version: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    /// Rate limit and network settings in effect, in config file syntax
+    settings: FileSettings,
+    processes: Vec<ArchivedProcess>,
+    /// Recent log lines per process id, oldest first; for reference, they are not restored
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    logs: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ArchivedProcess {
+    #[serde(flatten)]
+    config: ProcessConfig,
+    /// In the saved config, so it starts with the supervisor
+    saved: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ExportParams {
+    /// Include the end of each process log
+    logs: Option<bool>,
+    /// Log lines per process when logs=true (default 200, at most 10000)
+    log_lines: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    // Keep the existing process
+    #[default]
+    Skip,
+    // Replace the existing process with the archived one
+    Overwrite,
+    // Restore the archived process under a new id
+    Rename,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RestoreRequest {
+    archive: Archive,
+    /// What to do with archived processes whose id already exists: skip (default), overwrite or rename
+    #[serde(default)]
+    on_conflict: OnConflict,
+    /// Keep the current rate limit and network settings instead of the archived ones
+    #[serde(default)]
+    skip_settings: bool,
+    /// Only validate the archive and report what would happen
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreStatus {
+    Created,
+    Skipped,
+    Overwritten,
+    Renamed,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RestoredProcess {
+    /// Id in the archive
+    id: String,
+    status: RestoreStatus,
+    /// The new id when renamed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restored_as: Option<String>,
+    /// Why its config couldn't be saved or unsaved; it was restored for this run only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    save_failed: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RestoreResponse {
+    success: bool,
+    message: String,
+    dry_run: bool,
+    settings_restored: bool,
+    processes: Vec<RestoredProcess>,
+}
+
+pub async fn handle_export(params: ExportParams, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    let saved = supervisor.saved().load_configs();
+    let saved_ids: HashSet<&str> = saved.iter().map(|config| config.id.as_str()).collect();
+
+    // The live config of every supervised process, plus saved ones that aren't loaded (e.g. failed to start)
+    let mut configs: BTreeMap<String, ProcessConfig> = supervisor
+        .live_configs()
+        .into_iter()
+        .map(|(id, (config, _))| (id, config))
+        .collect();
+    for config in &saved {
+        configs.entry(config.id.clone()).or_insert_with(|| config.clone());
+    }
+
+    let mut logs = BTreeMap::new();
+    if params.logs.unwrap_or(false) {
+        let log_lines = params.log_lines.unwrap_or(200).clamp(1, 10_000);
+        for config in configs.values() {
+            if let Ok((mut lines, _)) = supervisor.logger().process_sink().read(&config.log_file, 1, log_lines) {
+                lines.reverse();
+                logs.insert(config.id.clone(), lines);
+            }
+        }
+    }
+
+    let created_at = supervisor.now();
+    let archive = Archive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exeio_version: crate::EXEIO_VERSION.to_string(),
+        created_at,
+        settings: SETTINGS.read().unwrap().snapshot(),
+        processes: configs
+            .into_values()
+            .map(|config| ArchivedProcess { saved: saved_ids.contains(config.id.as_str()), config })
+            .collect(),
+        logs,
+    };
+
+    Ok(warp::reply::with_header(
+        warp::reply::json(&archive),
+        "content-disposition",
+        format!("attachment; filename=\"exeio-export-{}.json\"", created_at.format("%Y%m%d-%H%M%S")),
+    ))
+}
+
+// Checks everything before anything is applied, so a bad archive changes nothing
+fn validate(archive: &Archive, errors: &mut Vec<FieldError>) -> Settings {
+    if archive.format != ARCHIVE_FORMAT {
+        errors.push(FieldError::new("archive.format", "unsupported", format!("Not an exeio export, expected format '{}'", ARCHIVE_FORMAT)));
+    }
+    if archive.version != ARCHIVE_VERSION {
+        errors.push(FieldError::new("archive.version", "unsupported",
+            format!("Archive version {} is not supported, expected {}", archive.version, ARCHIVE_VERSION)));
+    }
+
+    let mut seen = HashSet::new();
+    for archived in &archive.processes {
+        let config = &archived.config;
+        if !seen.insert(config.id.as_str()) {
+            errors.push(FieldError::new(&format!("archive.processes.{}", config.id), "duplicate",
+                format!("Process '{}' is in the archive more than once", config.id)));
+        }
+        for mut error in crate::validate_process_fields(&config.id, &config.command, config.periodic, config.period_seconds, &config.hooks, &config.log_rules) {
+            error.field = format!("archive.processes.{}.{}", config.id, error.field);
+            errors.push(error);
+        }
+    }
+
+    // The archived settings replace the current ones rather than adding to them
+    let mut settings_errors = Vec::new();
+    let settings = Settings::default().with_file(&archive.settings, &mut settings_errors);
+    errors.extend(settings_errors.into_iter().map(|mut error| {
+        error.field = format!("archive.{}", error.field);
+        error
+    }));
+    settings
+}
+
+// `id-restored`, then `id-restored-2` and so on, skipping ids that are taken
+fn free_id(id: &str, taken: &HashSet<String>) -> String {
+    let mut candidate = format!("{}-restored", id);
+    let mut n = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{}-restored-{}", id, n);
+        n += 1;
+    }
+    candidate
+}
+
+pub async fn handle_restore(
+    audit: AuditContext,
+    req: RestoreRequest,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut audit_details = serde_json::json!({
+        "on_conflict": req.on_conflict,
+        "skip_settings": req.skip_settings,
+        "dry_run": req.dry_run,
+        "archive_created_at": req.archive.created_at,
+    });
+
+    let mut errors = Vec::new();
+    let settings = validate(&req.archive, &mut errors);
+    if !errors.is_empty() {
+        let error = ApiError::Validation(errors);
+        audit.record_error(None, Some(audit_details), &error);
+        return Err(warp::reject::custom(error));
+    }
+
+    let mut taken: HashSet<String> = supervisor.live_configs().into_keys().collect();
+    taken.extend(req.archive.processes.iter().map(|archived| archived.config.id.clone()));
+
+    let mut restored = Vec::new();
+    for archived in req.archive.processes {
+        let mut config = archived.config;
+        let id = config.id.clone();
+        let exists = supervisor.contains(&id);
+
+        let status = match (exists, req.on_conflict) {
+            (false, _) => RestoreStatus::Created,
+            (true, OnConflict::Skip) => RestoreStatus::Skipped,
+            (true, OnConflict::Overwrite) => RestoreStatus::Overwritten,
+            (true, OnConflict::Rename) => {
+                config.id = free_id(&id, &taken);
+                config.log_file = crate::get_process_log_path(&config.id).to_string_lossy().into_owned();
+                taken.insert(config.id.clone());
+                RestoreStatus::Renamed
+            }
+        };
+        let restored_as = (status == RestoreStatus::Renamed).then(|| config.id.clone());
+        restored.push(RestoredProcess { id, status, restored_as, save_failed: None });
+        if req.dry_run || status == RestoreStatus::Skipped {
+            continue;
+        }
+
+        let saved = if archived.saved {
+            supervisor.saved().save_process_config(&config, &audit.caller.name).map_err(|e| e.to_string())
+        } else if supervisor.saved().is_saved(&config.id) {
+            supervisor.saved().remove_process_config(&config.id, &audit.caller.name).map_err(|e| e.to_string())
+        } else {
+            Ok(())
+        };
+        if let Err(e) = saved {
+            eprintln!("Failed to update process config: {}", e);
+            if let Some(process) = restored.last_mut() {
+                process.save_failed = Some(e);
+            }
+        }
+
+        if status == RestoreStatus::Overwritten {
+            supervisor.replace(config, "Process configuration replaced from a restored archive").await;
+        } else {
+            supervisor.launch(config).await;
+        }
+    }
+
+    let settings_restored = !req.skip_settings && !req.dry_run;
+    if settings_restored {
+        settings.apply();
+    }
+
+    let count = |status: RestoreStatus| restored.iter().filter(|process| process.status == status).count();
+    let save_failed = restored.iter().filter(|process| process.save_failed.is_some()).count();
+    let message = format!("{}{} created, {} overwritten, {} renamed, {} skipped{}{}",
+        if req.dry_run { "Dry run: " } else { "Restored archive: " },
+        count(RestoreStatus::Created),
+        count(RestoreStatus::Overwritten),
+        count(RestoreStatus::Renamed),
+        count(RestoreStatus::Skipped),
+        if save_failed > 0 { format!(" ({} failed to save, see save_failed)", save_failed) } else { String::new() },
+        if req.skip_settings { ", settings kept" } else if req.dry_run { "" } else { ", settings restored" });
+
+    let response = ApiResponse { success: true, message };
+    audit_details["processes"] = serde_json::json!(restored);
+    audit.record(None, Some(audit_details), &response);
+
+    Ok(warp::reply::json(&RestoreResponse {
+        success: true,
+        message: response.message,
+        dry_run: req.dry_run,
+        settings_restored,
+        processes: restored,
+    }))
+}
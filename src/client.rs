@@ -0,0 +1,572 @@
+// The client subcommands (`exeio list`, `exeio logs -f`, ...): each one is a call to the v1 API of a running
+// supervisor, printed as a table or, with --json, as the JSON the API answered
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+// How often `logs -f` asks for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+// Lines fetched per poll while following, more than a process writes in FOLLOW_INTERVAL
+const FOLLOW_PAGE_SIZE: usize = 1000;
+// For every call but the event stream, which stays open
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(clap::Args)]
+pub struct Connection {
+    /// Base URL of the supervisor, instead of --host and --port (e.g. https://exeio.example.com)
+    #[arg(long, env = "EXEIO_URL")]
+    url: Option<String>,
+
+    /// Host the supervisor listens on [default: 127.0.0.1]
+    #[arg(short = 'H', long, env = "EXEIO_HOST")]
+    host: Option<String>,
+
+    /// Port the supervisor listens on [default: 8080]
+    #[arg(short = 'P', long, env = "EXEIO_PORT")]
+    port: Option<u16>,
+
+    /// API key to authenticate with [default: the key the supervisor persisted for this user]
+    #[arg(short = 'k', long = "api-key", env = "EXEIO_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Client config file with url, host, port, api_key or api_key_file [default: ~/.config/exeio/client.toml]
+    #[arg(long = "client-config", value_name = "PATH", env = "EXEIO_CLIENT_CONFIG")]
+    client_config: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct ClientArgs {
+    #[command(flatten)]
+    connection: Connection,
+
+    /// Print the API's JSON response instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Subcommand)]
+pub enum ClientCommand {
+    /// List the processes of a running supervisor
+    List {
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Show the status and config of a process
+    Status {
+        /// Process id
+        id: String,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Add and start a process, put `--` before a command that takes options (exeio add web -- python -m http.server)
+    Add {
+        /// Process id
+        id: String,
+
+        /// Command to run
+        command: String,
+
+        /// Arguments of the command
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+
+        /// Working directory of the process
+        #[arg(long, value_name = "DIR")]
+        working_dir: Option<String>,
+
+        /// Restart the process when it exits
+        #[arg(long)]
+        auto_restart: bool,
+
+        /// Run the command every SECONDS instead of keeping it running
+        #[arg(long, value_name = "SECONDS")]
+        every: Option<u64>,
+
+        /// Keep the process in the saved config, so it starts with the supervisor
+        #[arg(long)]
+        save: bool,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Stop a process
+    Stop {
+        /// Process id
+        id: String,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Restart a process, or start a stopped one
+    Restart {
+        /// Process id
+        id: String,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Stop a process and remove it from the supervisor
+    Remove {
+        /// Process id
+        id: String,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Print the last lines of a process log
+    Logs {
+        /// Process id
+        id: String,
+
+        /// Number of lines to print
+        #[arg(short = 'n', long, default_value_t = 50)]
+        lines: usize,
+
+        /// Keep printing new lines as the process writes them
+        #[arg(short = 'f', long)]
+        follow: bool,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Send a line to the stdin of a process
+    Input {
+        /// Process id
+        id: String,
+
+        /// Text to send, the words are joined with spaces and a newline is added
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        text: Vec<String>,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Stop all processes and shut the supervisor down
+    Shutdown {
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+}
+
+impl ClientCommand {
+    fn args(&self) -> &ClientArgs {
+        match self {
+            ClientCommand::List { client }
+            | ClientCommand::Status { client, .. }
+            | ClientCommand::Add { client, .. }
+            | ClientCommand::Stop { client, .. }
+            | ClientCommand::Restart { client, .. }
+            | ClientCommand::Remove { client, .. }
+            | ClientCommand::Logs { client, .. }
+            | ClientCommand::Input { client, .. }
+            | ClientCommand::Shutdown { client } => client,
+        }
+    }
+}
+
+// ~/.config/exeio/client.toml, for talking to a supervisor that isn't on the default address
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClientConfig {
+    url: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+    api_key: Option<String>,
+    api_key_file: Option<PathBuf>,
+}
+
+fn get_client_config_path() -> PathBuf {
+    crate::get_config_path().with_file_name("client.toml")
+}
+
+fn load_client_config(path: Option<&Path>) -> Result<ClientConfig, String> {
+    // Only a file named with --client-config or EXEIO_CLIENT_CONFIG has to exist
+    let (path, required) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => (get_client_config_path(), false),
+    };
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => return Ok(ClientConfig::default()),
+        Err(e) => return Err(format!("Failed to read client config {}: {}", path.display(), e)),
+    };
+    toml::from_str(&content).map_err(|e| format!("Invalid client config {}: {}", path.display(), e))
+}
+
+// Shared with `exeio tui`, which clones it into its background requests
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl Client {
+    // Flags and environment variables first, then the client config file, then the defaults of `exeio serve`
+    pub fn new(connection: &Connection) -> Result<Self, String> {
+        let config = load_client_config(connection.client_config.as_deref())?;
+
+        let base_url = match connection.url.clone().or(config.url) {
+            Some(url) if connection.host.is_none() && connection.port.is_none() => url,
+            _ => {
+                let host = connection.host.clone().or(config.host).unwrap_or_else(|| "127.0.0.1".to_string());
+                let port = connection.port.or(config.port).unwrap_or(8080);
+                format!("http://{}:{}", host, port)
+            }
+        };
+        reqwest::Url::parse(&base_url).map_err(|e| format!("Invalid supervisor URL {}: {}", base_url, e))?;
+
+        let api_key = match connection.api_key.clone().filter(|key| !key.trim().is_empty()).or(config.api_key) {
+            Some(key) => Some(key),
+            None => match config.api_key_file {
+                Some(path) => Some(crate::read_api_key_file(&path)?),
+                // The key `exeio serve` generated, readable when the client runs as the same user
+                None => crate::read_api_key_file(&crate::get_api_key_path()).ok(),
+            },
+        };
+
+        let http = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+        Ok(Self { http, base_url: base_url.trim_end_matches('/').to_string(), api_key })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn build(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(api_key) => request.header("exeio-api-key", api_key),
+            None => request,
+        }
+    }
+
+    pub async fn request(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value, String> {
+        let mut request = self.build(method, path).timeout(REQUEST_TIMEOUT);
+        if let Some(body) = body {
+            request = request.header("content-type", "application/json").body(body.to_string());
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Could not reach the supervisor at {}: {}", self.base_url, e))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read the response of {}: {}", self.base_url, e))?;
+        let value: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+
+        if status.is_success() {
+            return Ok(value);
+        }
+        Err(error_message(status, &value, self.api_key.is_some()))
+    }
+
+    // A response that is read as it arrives, for GET /v1/events
+    pub async fn stream(&self, path: &str) -> Result<reqwest::Response, String> {
+        let response = self
+            .build(Method::GET, path)
+            .header("accept", "text/event-stream")
+            .send()
+            .await
+            .map_err(|e| format!("Could not reach the supervisor at {}: {}", self.base_url, e))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.ok().and_then(|text| serde_json::from_str(&text).ok()).unwrap_or(Value::Null);
+        Err(error_message(status, &body, self.api_key.is_some()))
+    }
+
+    pub async fn get(&self, path: &str) -> Result<Value, String> {
+        self.request(Method::GET, path, None).await
+    }
+
+    pub async fn post(&self, path: &str, body: Option<Value>) -> Result<Value, String> {
+        self.request(Method::POST, path, body).await
+    }
+}
+
+// The API's error body as one line per problem
+fn error_message(status: reqwest::StatusCode, body: &Value, has_key: bool) -> String {
+    let mut message = match body.get("message").and_then(Value::as_str) {
+        Some(message) => message.to_string(),
+        None => format!("The supervisor answered {}", status),
+    };
+    if let Some(errors) = body.get("errors").and_then(Value::as_array) {
+        let field_lines: Vec<String> = errors
+            .iter()
+            .map(|error| format!("  {}: {}", text(error, "field"), text(error, "message")))
+            .collect();
+        // A single invalid field is already the message
+        if field_lines.len() > 1 {
+            message = format!("{}\n{}", message, field_lines.join("\n"));
+        }
+    }
+    if status == reqwest::StatusCode::UNAUTHORIZED && !has_key {
+        message.push_str("\nNo API key found, pass --api-key or set EXEIO_API_KEY");
+    }
+    message
+}
+
+pub async fn run(command: ClientCommand) -> Result<(), String> {
+    let client = Client::new(&command.args().connection)?;
+    let json_output = command.args().json;
+
+    match command {
+        ClientCommand::List { .. } => {
+            let processes = client.get("/v1/processes").await?;
+            if json_output {
+                return print_json(&processes);
+            }
+            let mut processes = processes.as_array().cloned().unwrap_or_default();
+            if processes.is_empty() {
+                output("No processes");
+                return Ok(());
+            }
+            processes.sort_by_key(|process| text(process, "id"));
+            let rows: Vec<Vec<String>> = processes
+                .iter()
+                .map(|process| {
+                    vec![
+                        text(process, "id"),
+                        status_text(process),
+                        process.get("run_count").map(Value::to_string).unwrap_or_default(),
+                        last_run(process),
+                        command_line(process),
+                    ]
+                })
+                .collect();
+            print_table(&["ID", "STATUS", "RUNS", "LAST RUN", "COMMAND"], &rows);
+        }
+        ClientCommand::Status { id, .. } => {
+            let process = client.get(&format!("/v1/processes/{}", id)).await?;
+            if json_output {
+                return print_json(&process);
+            }
+            print_status(&process);
+        }
+        ClientCommand::Add { id, command, args, working_dir, auto_restart, every, save, .. } => {
+            let body = json!({
+                "id": id,
+                "command": command,
+                "args": args,
+                "working_dir": working_dir,
+                "auto_restart": auto_restart,
+                "save_for_next_run": save,
+                "periodic": every.is_some(),
+                "period_seconds": every,
+            });
+            print_result(&client.post("/v1/processes", Some(body)).await?, json_output)?;
+        }
+        ClientCommand::Stop { id, .. } => {
+            print_result(&client.post(&format!("/v1/processes/{}/actions/stop", id), None).await?, json_output)?;
+        }
+        ClientCommand::Restart { id, .. } => {
+            print_result(&client.post(&format!("/v1/processes/{}/actions/restart", id), None).await?, json_output)?;
+        }
+        ClientCommand::Remove { id, .. } => {
+            let response = client.request(Method::DELETE, &format!("/v1/processes/{}", id), None).await?;
+            print_result(&response, json_output)?;
+        }
+        ClientCommand::Logs { id, lines, follow, .. } => {
+            print_logs(&client, &id, lines, follow, json_output).await?;
+        }
+        ClientCommand::Input { id, text, .. } => {
+            let body = json!({ "input": text.join(" ") });
+            print_result(&client.post(&format!("/v1/processes/{}/input", id), Some(body)).await?, json_output)?;
+        }
+        ClientCommand::Shutdown { .. } => {
+            print_result(&client.post("/v1/actions/shutdown", None).await?, json_output)?;
+        }
+    }
+    Ok(())
+}
+
+async fn print_logs(client: &Client, id: &str, lines: usize, follow: bool, json_output: bool) -> Result<(), String> {
+    // Pages are newest first, so the first page holds the tail of the log
+    let path = |page_size: usize| format!("/v1/processes/{}/logs?page=1&page_size={}", id, page_size.max(1));
+    let page = client.get(&path(lines)).await?;
+    if json_output && !follow {
+        return print_json(&page);
+    }
+
+    let tail = log_lines(&page);
+    print_log_lines(tail.iter().rev().take(lines.min(tail.len())), json_output);
+    if !follow {
+        return Ok(());
+    }
+
+    let mut seen = page.get("total_lines").and_then(Value::as_u64).unwrap_or(0);
+    loop {
+        tokio::time::sleep(FOLLOW_INTERVAL).await;
+        let page = client.get(&path(FOLLOW_PAGE_SIZE)).await?;
+        let total = page.get("total_lines").and_then(Value::as_u64).unwrap_or(0);
+        // A cleared log starts over from its first line
+        if total < seen {
+            seen = 0;
+        }
+        let new_lines = (total - seen) as usize;
+        if new_lines > 0 {
+            let newest = log_lines(&page);
+            print_log_lines(newest.iter().take(new_lines.min(newest.len())).rev(), json_output);
+        }
+        seen = total;
+    }
+}
+
+fn log_lines(page: &Value) -> Vec<String> {
+    page.get("logs")
+        .and_then(Value::as_array)
+        .map(|logs| logs.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+// Oldest first, like the log file; with --json each line is a JSON string of its own
+fn print_log_lines<'a>(lines: impl Iterator<Item = &'a String>, json_output: bool) {
+    for line in lines {
+        if json_output {
+            output(&Value::String(line.clone()).to_string());
+        } else {
+            output(line);
+        }
+    }
+}
+
+fn print_result(response: &Value, json_output: bool) -> Result<(), String> {
+    if json_output {
+        return print_json(response);
+    }
+    match response.get("message").and_then(Value::as_str) {
+        Some(message) => output(message),
+        None => output("Done"),
+    }
+    Ok(())
+}
+
+fn print_json(value: &Value) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("Failed to format the response: {}", e))?;
+    output(&json);
+    Ok(())
+}
+
+fn print_status(process: &Value) {
+    let mut fields = vec![
+        ("Id", text(process, "id")),
+        ("Status", status_text(process)),
+        ("Command", command_line(process)),
+        ("Working dir", text(process, "working_dir")),
+        ("Auto-restart", yes_no(process, "auto_restart")),
+        ("Periodic", match process.get("period_seconds").and_then(Value::as_u64) {
+            Some(seconds) if process.get("periodic") == Some(&Value::Bool(true)) => format!("every {}s", seconds),
+            _ => "no".to_string(),
+        }),
+        ("Runs", process.get("run_count").map(Value::to_string).unwrap_or_default()),
+        ("Last run", last_run(process)),
+        ("Log file", text(process, "log_file")),
+    ];
+    if let Some(rules) = process.get("log_rules").and_then(Value::as_array) {
+        for rule in rules {
+            fields.push(("Log rule", format!(
+                "{}: {} matches, {} triggered",
+                text(rule, "name"),
+                rule.get("matches").map(Value::to_string).unwrap_or_default(),
+                rule.get("triggered").map(Value::to_string).unwrap_or_default()
+            )));
+        }
+    }
+    let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, value) in fields {
+        let value = if value.is_empty() { "-".to_string() } else { value };
+        output(&format!("{:<width$}  {}", format!("{}:", name), value, width = width + 1));
+    }
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_row = |cells: Vec<&str>| {
+        let last = cells.len() - 1;
+        cells
+            .iter()
+            .enumerate()
+            // The last column isn't padded, so long commands don't leave trailing spaces
+            .map(|(i, cell)| if i == last { cell.to_string() } else { format!("{:<width$}", cell, width = widths[i]) })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    output(&format_row(headers.to_vec()));
+    for row in rows {
+        output(&format_row(row.iter().map(String::as_str).collect()));
+    }
+}
+
+// A closed pipe (`exeio logs -f | head`) ends the command instead of panicking in println!
+fn output(line: &str) {
+    let mut stdout = std::io::stdout().lock();
+    if writeln!(stdout, "{}", line).and_then(|_| stdout.flush()).is_err() {
+        std::process::exit(0);
+    }
+}
+
+fn text(value: &Value, field: &str) -> String {
+    value.get(field).and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+fn yes_no(value: &Value, field: &str) -> String {
+    if value.get(field) == Some(&Value::Bool(true)) { "yes" } else { "no" }.to_string()
+}
+
+fn status_text(process: &Value) -> String {
+    let mut status = text(process, "status");
+    if process.get("pending_restart") == Some(&Value::Bool(true)) {
+        status.push_str(" (restart pending)");
+    }
+    status
+}
+
+fn last_run(process: &Value) -> String {
+    process
+        .get("last_run")
+        .and_then(Value::as_str)
+        .and_then(|last_run| chrono::DateTime::parse_from_rfc3339(last_run).ok())
+        .map(|last_run| last_run.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn command_line(process: &Value) -> String {
+    let args: Vec<String> = process
+        .get("args")
+        .and_then(Value::as_array)
+        .map(|args| args.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default();
+    let mut line = text(process, "command");
+    for arg in crate::redact::redact_args(&args) {
+        line.push(' ');
+        line.push_str(&arg);
+    }
+    line
+}
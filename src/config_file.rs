@@ -0,0 +1,514 @@
+// Declarative config file (--config): processes and global settings kept under version control,
+// reconciled against the running state at startup and on reload (SIGHUP or POST /reload)
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::AuditContext;
+use crate::email::{self, EmailConfig};
+use crate::error::{ApiError, FieldError};
+use crate::hooks::{self, Hooks};
+use crate::network::{self, AccessRule, Cidr, NetworkPolicy};
+use crate::ratelimit::{self, LockoutPolicy, RateLimit, RouteGroup};
+use crate::webhooks::WebhookConfig;
+use crate::{
+    ApiResponse, ConfigChange, LogRule, ProcessConfig, ProcessStatus, Supervisor, NETWORK_POLICY, RATE_LIMITER,
+    SETTINGS,
+};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    settings: FileSettings,
+    // Keyed by process id
+    #[serde(default)]
+    processes: BTreeMap<String, ProcessEntry>,
+    // Keyed by webhook name
+    #[serde(default)]
+    webhooks: BTreeMap<String, WebhookConfig>,
+    email: Option<EmailConfig>,
+    // Run for every process, before its own hooks
+    #[serde(default)]
+    hooks: Hooks,
+}
+
+// Same syntax as the matching command line options; lists add to them, numbers replace them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileSettings {
+    #[serde(default)]
+    pub rate_limits: Vec<String>,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    pub auth_max_failures: Option<u32>,
+    pub auth_lockout_seconds: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProcessEntry {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    working_dir: Option<String>,
+    #[serde(default)]
+    auto_restart: bool,
+    #[serde(default)]
+    periodic: bool,
+    period_seconds: Option<u64>,
+    #[serde(default)]
+    hooks: Hooks,
+    #[serde(default)]
+    log_rules: Vec<LogRule>,
+}
+
+// The rate limit and network settings in effect
+#[derive(Clone)]
+pub struct Settings {
+    pub rate_limits: Vec<(RouteGroup, Option<RateLimit>)>,
+    pub allow: Vec<AccessRule>,
+    pub deny: Vec<AccessRule>,
+    pub trusted_proxies: Vec<Cidr>,
+    pub auth_max_failures: u32,
+    pub auth_lockout_seconds: u64,
+}
+
+// Matches the command line defaults
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            rate_limits: Vec::new(),
+            allow: Vec::new(),
+            deny: Vec::new(),
+            trusted_proxies: Vec::new(),
+            auth_max_failures: 10,
+            auth_lockout_seconds: 300,
+        }
+    }
+}
+
+impl Settings {
+    pub fn apply(&self) {
+        RATE_LIMITER.configure(&self.rate_limits, LockoutPolicy {
+            max_failures: self.auth_max_failures,
+            duration: Duration::from_secs(self.auth_lockout_seconds),
+        });
+        *NETWORK_POLICY.write().unwrap() = NetworkPolicy::new(&self.allow, &self.deny, &self.trusted_proxies);
+        *SETTINGS.write().unwrap() = self.clone();
+    }
+
+    // These settings extended by a settings section, collecting every invalid entry
+    pub fn with_file(&self, file: &FileSettings, errors: &mut Vec<FieldError>) -> Settings {
+        let mut settings = self.clone();
+        settings.rate_limits.extend(parse_list("settings.rate_limits", &file.rate_limits, ratelimit::parse_rate_limit, errors));
+        settings.allow.extend(parse_list("settings.allow", &file.allow, network::parse_access_rule, errors));
+        settings.deny.extend(parse_list("settings.deny", &file.deny, network::parse_access_rule, errors));
+        settings.trusted_proxies.extend(parse_list("settings.trusted_proxies", &file.trusted_proxies, str::parse::<Cidr>, errors));
+        if let Some(max_failures) = file.auth_max_failures {
+            settings.auth_max_failures = max_failures;
+        }
+        if let Some(lockout_seconds) = file.auth_lockout_seconds {
+            settings.auth_lockout_seconds = lockout_seconds;
+        }
+        settings
+    }
+
+    // A settings section that with_file turns back into these settings
+    pub fn snapshot(&self) -> FileSettings {
+        FileSettings {
+            rate_limits: self.rate_limits.iter().map(|(group, limit)| ratelimit::format_rate_limit(*group, *limit)).collect(),
+            allow: self.allow.iter().map(AccessRule::to_string).collect(),
+            deny: self.deny.iter().map(AccessRule::to_string).collect(),
+            trusted_proxies: self.trusted_proxies.iter().map(Cidr::to_string).collect(),
+            auth_max_failures: Some(self.auth_max_failures),
+            auth_lockout_seconds: Some(self.auth_lockout_seconds),
+        }
+    }
+}
+
+// A config file that parsed and passed validation
+pub struct DesiredState {
+    file_settings: FileSettings,
+    settings: Settings,
+    processes: Vec<ProcessConfig>,
+    webhooks: BTreeMap<String, WebhookConfig>,
+    email: Option<EmailConfig>,
+    hooks: Hooks,
+}
+
+impl DesiredState {
+    pub fn declares(&self, id: &str) -> bool {
+        self.processes.iter().any(|config| config.id == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanAction {
+    // Declared but not supervised yet
+    Add,
+    // Declared by the previous load but no longer in the file
+    Remove,
+    // Config changed while the process is running
+    Restart,
+    // Config changed while the process is stopped or failed, it is used on the next start
+    Update,
+    Unchanged,
+}
+
+impl PlanAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            PlanAction::Add => "add",
+            PlanAction::Remove => "remove",
+            PlanAction::Restart => "restart",
+            PlanAction::Update => "update",
+            PlanAction::Unchanged => "unchanged",
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PlannedChange {
+    pub id: String,
+    pub action: PlanAction,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<ConfigChange>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ReloadResponse {
+    pub success: bool,
+    pub message: String,
+    pub dry_run: bool,
+    /// The settings section differs from the one in effect
+    pub settings_changed: bool,
+    /// The webhooks differ from the ones in effect
+    pub webhooks_changed: bool,
+    /// The email section differs from the one in effect
+    pub email_changed: bool,
+    /// The global hooks differ from the ones in effect
+    pub hooks_changed: bool,
+    pub plan: Vec<PlannedChange>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ReloadParams {
+    /// Only return the plan, change nothing
+    pub dry_run: Option<bool>,
+}
+
+// What the last applied load declared
+#[derive(Default)]
+struct Applied {
+    declared: HashSet<String>,
+    settings: Option<FileSettings>,
+    webhooks: Option<BTreeMap<String, WebhookConfig>>,
+    email: Option<Option<EmailConfig>>,
+    hooks: Option<Hooks>,
+}
+
+pub struct ConfigFileManager {
+    path: PathBuf,
+    // The command line settings the file's settings extend
+    base: Settings,
+    // Also serializes reloads
+    applied: tokio::sync::Mutex<Applied>,
+}
+
+impl ConfigFileManager {
+    pub fn new(path: PathBuf, base: Settings) -> Self {
+        Self { path, base, applied: tokio::sync::Mutex::new(Applied::default()) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Parses and validates the file, collecting every problem instead of stopping at the first
+    pub fn load(&self) -> Result<DesiredState, Vec<FieldError>> {
+        let file = parse_file(&self.path).map_err(|error| vec![error])?;
+        let mut errors = Vec::new();
+
+        let settings = self.base.with_file(&file.settings, &mut errors);
+
+        let mut processes = Vec::new();
+        for (id, entry) in file.processes {
+            for mut error in crate::validate_process_fields(&id, &entry.command, entry.periodic, entry.period_seconds, &entry.hooks, &entry.log_rules) {
+                error.field = format!("processes.{}.{}", id, error.field);
+                errors.push(error);
+            }
+            processes.push(ProcessConfig {
+                log_file: crate::get_process_log_path(&id).to_string_lossy().into_owned(),
+                id,
+                command: entry.command,
+                args: entry.args,
+                working_dir: entry.working_dir,
+                auto_restart: entry.auto_restart,
+                periodic: entry.periodic,
+                period_seconds: entry.period_seconds,
+                hooks: entry.hooks,
+                log_rules: entry.log_rules,
+            });
+        }
+
+        for (name, webhook) in &file.webhooks {
+            errors.extend(webhook.validate(name));
+        }
+        if let Some(email) = &file.email {
+            errors.extend(email.validate());
+        }
+        errors.extend(file.hooks.validate("hooks"));
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(DesiredState { file_settings: file.settings, settings, processes, webhooks: file.webhooks, email: file.email, hooks: file.hooks })
+    }
+
+    // Loads the file and applies it; an invalid file changes nothing
+    pub async fn reload(
+        &self,
+        supervisor: &Supervisor,
+        dry_run: bool,
+        trigger: &str,
+    ) -> Result<ReloadResponse, Vec<FieldError>> {
+        let mut applied = self.applied.lock().await;
+        let desired = self.load()?;
+        Ok(self.reconcile(&mut applied, desired, supervisor, dry_run, trigger).await)
+    }
+
+    // Applies an already loaded file, used at startup
+    pub async fn apply(&self, desired: DesiredState, supervisor: &Supervisor) -> ReloadResponse {
+        let mut applied = self.applied.lock().await;
+        self.reconcile(&mut applied, desired, supervisor, false, "at startup").await
+    }
+
+    async fn reconcile(
+        &self,
+        applied: &mut Applied,
+        desired: DesiredState,
+        supervisor: &Supervisor,
+        dry_run: bool,
+        trigger: &str,
+    ) -> ReloadResponse {
+        let plan = plan(&applied.declared, &desired.processes, supervisor);
+        let settings_changed = applied.settings.as_ref() != Some(&desired.file_settings);
+        let webhooks_changed = applied.webhooks.as_ref() != Some(&desired.webhooks);
+        let email_changed = applied.email.as_ref() != Some(&desired.email);
+        let hooks_changed = applied.hooks.as_ref() != Some(&desired.hooks);
+        let message = format!("{} {} {}: {}{}{}{}{}",
+            if dry_run { "Dry run of" } else { "Reloaded" },
+            self.path.display(),
+            trigger,
+            plan_summary(&plan),
+            if settings_changed { ", settings changed" } else { "" },
+            if webhooks_changed { ", webhooks changed" } else { "" },
+            if email_changed { ", email changed" } else { "" },
+            if hooks_changed { ", hooks changed" } else { "" });
+
+        if dry_run {
+            return ReloadResponse { success: true, message, dry_run, settings_changed, webhooks_changed, email_changed, hooks_changed, plan };
+        }
+
+        if settings_changed {
+            desired.settings.apply();
+        }
+        if webhooks_changed {
+            supervisor.webhooks().configure(desired.webhooks.clone());
+        }
+        if email_changed {
+            email::configure(supervisor, desired.email.clone());
+        }
+        if hooks_changed {
+            hooks::configure(supervisor, desired.hooks.clone());
+        }
+
+        let reason = format!("Config file {} reloaded {}", self.path.display(), trigger);
+        apply_plan(&plan, &desired.processes, supervisor, &reason, "config file").await;
+
+        applied.declared = desired.processes.iter().map(|config| config.id.clone()).collect();
+        applied.settings = Some(desired.file_settings);
+        applied.webhooks = Some(desired.webhooks);
+        applied.email = Some(desired.email);
+        applied.hooks = Some(desired.hooks);
+        crate::log_exeio_event(supervisor, &format!("{}\n", message));
+
+        ReloadResponse { success: true, message, dry_run, settings_changed, webhooks_changed, email_changed, hooks_changed, plan }
+    }
+}
+
+fn parse_file(path: &Path) -> Result<ConfigFile, FieldError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| FieldError::new("config", "unreadable", format!("Failed to read {}: {}", path.display(), e)))?;
+    let parsed = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => toml::from_str(&content).map_err(|e| e.to_string()),
+        Some("yaml" | "yml") => serde_yaml::from_str(&content).map_err(|e| e.to_string()),
+        _ => return Err(FieldError::new("config", "unsupported_format", "Config file must end in .toml, .yaml or .yml")),
+    };
+    parsed.map_err(|e| FieldError::new("config", "parse_error", format!("{}: {}", path.display(), e.trim_end())))
+}
+
+fn parse_list<T>(
+    field: &str,
+    values: &[String],
+    parse: impl Fn(&str) -> Result<T, String>,
+    errors: &mut Vec<FieldError>,
+) -> Vec<T> {
+    values
+        .iter()
+        .enumerate()
+        .filter_map(|(index, value)| match parse(value) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                errors.push(FieldError::new(&format!("{}[{}]", field, index), "invalid", e));
+                None
+            }
+        })
+        .collect()
+}
+
+// What it takes to bring the supervised processes in line with `desired`. Processes missing from it
+// are only removed if they are in `previous`, so e.g. processes added through the API are left alone
+pub fn plan(previous: &HashSet<String>, desired: &[ProcessConfig], supervisor: &Supervisor) -> Vec<PlannedChange> {
+    let processes_lock = supervisor.process_map().lock().unwrap();
+
+    let mut plan: Vec<PlannedChange> = desired
+        .iter()
+        .map(|config| match processes_lock.get(&config.id) {
+            None => PlannedChange { id: config.id.clone(), action: PlanAction::Add, changes: Vec::new() },
+            Some(managed_process) => {
+                let changes = crate::config_diff(&managed_process.config, config);
+                let is_active = matches!(managed_process.status, ProcessStatus::Running | ProcessStatus::WaitingForPeriod);
+                let action = match (changes.is_empty(), is_active) {
+                    (true, _) => PlanAction::Unchanged,
+                    (false, true) => PlanAction::Restart,
+                    (false, false) => PlanAction::Update,
+                };
+                PlannedChange { id: config.id.clone(), action, changes }
+            }
+        })
+        .collect();
+
+    let mut removed: Vec<&String> = previous
+        .iter()
+        .filter(|id| !desired.iter().any(|config| &config.id == *id) && processes_lock.contains_key(*id))
+        .collect();
+    removed.sort();
+    plan.extend(removed.into_iter().map(|id| PlannedChange {
+        id: id.clone(),
+        action: PlanAction::Remove,
+        changes: Vec::new(),
+    }));
+    plan
+}
+
+pub fn plan_summary(plan: &[PlannedChange]) -> String {
+    let count = |action: PlanAction| plan.iter().filter(|change| change.action == action).count();
+    format!("{} added, {} removed, {} restarted, {} updated, {} unchanged",
+        count(PlanAction::Add), count(PlanAction::Remove), count(PlanAction::Restart),
+        count(PlanAction::Update), count(PlanAction::Unchanged))
+}
+
+// Carries out a plan, noting `reason` in the log of every process it touches
+pub async fn apply_plan(
+    plan: &[PlannedChange],
+    desired: &[ProcessConfig],
+    supervisor: &Supervisor,
+    reason: &str,
+    actor: &str,
+) {
+    let processes = supervisor.process_map();
+    for change in plan {
+        let note = format!("{} (action={}){}", reason, change.action.as_str(),
+            if change.changes.is_empty() { String::new() } else { format!(": {}", crate::change_summary(&change.changes)) });
+        let config = desired.iter().find(|config| config.id == change.id);
+
+        match (change.action, config) {
+            (PlanAction::Unchanged, _) => {}
+            (PlanAction::Add, Some(config)) => {
+                supervisor.log_system(&config.log_file, &note);
+                crate::start_process(supervisor, config.clone()).await;
+            }
+            (PlanAction::Restart, Some(config)) => {
+                let reaped = {
+                    let mut processes_lock = processes.lock().unwrap();
+                    processes_lock.get_mut(&change.id).and_then(|managed_process| {
+                        supervisor.log_system(&managed_process.config.log_file, &note);
+                        let reaped = crate::halt_process(supervisor, managed_process, ProcessStatus::Stopped);
+                        managed_process.config = config.clone();
+                        managed_process.run_count += 1;
+                        reaped
+                    })
+                };
+                if let Some(reaped) = reaped {
+                    let _ = reaped.await;
+                }
+                crate::start_process(supervisor, config.clone()).await;
+            }
+            (PlanAction::Update, Some(config)) => {
+                let mut processes_lock = processes.lock().unwrap();
+                if let Some(managed_process) = processes_lock.get_mut(&change.id) {
+                    supervisor.log_system(&managed_process.config.log_file, &note);
+                    managed_process.config = config.clone();
+                    managed_process.pending_restart = false;
+                }
+            }
+            (PlanAction::Remove, _) => {
+                let removed = processes.lock().unwrap().remove(&change.id);
+                if let Some(mut managed_process) = removed {
+                    let reaped = crate::halt_process(supervisor, &mut managed_process, ProcessStatus::Stopped);
+                    supervisor.log_system(&managed_process.config.log_file, &note);
+                    // A later reload may add the id back, so the old child has to be gone first
+                    if let Some(reaped) = reaped {
+                        let _ = reaped.await;
+                    }
+                }
+                if supervisor.saved().is_saved(&change.id)
+                    && let Err(e) = supervisor.saved().remove_process_config(&change.id, actor) {
+                        eprintln!("Failed to remove process config: {}", e);
+                }
+            }
+            (_, None) => {}
+        }
+    }
+}
+
+pub async fn handle_reload(
+    audit: AuditContext,
+    params: ReloadParams,
+    config_file: Option<Arc<ConfigFileManager>>,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let dry_run = params.dry_run.unwrap_or(false);
+    let Some(config_file) = config_file else {
+        let error = ApiError::NoConfigFile;
+        audit.record_error(None, Some(serde_json::json!({ "dry_run": dry_run })), &error);
+        return Err(warp::reject::custom(error));
+    };
+
+    let trigger = format!("via API by '{}'", audit.caller.name);
+    match config_file.reload(&supervisor, dry_run, &trigger).await {
+        Ok(response) => {
+            audit.record(None, Some(serde_json::json!({ "dry_run": dry_run, "plan": response.plan })), &ApiResponse {
+                success: true,
+                message: response.message.clone(),
+            });
+            Ok(warp::reply::json(&response))
+        }
+        Err(errors) => {
+            let error = ApiError::Validation(errors);
+            audit.record_error(None, Some(serde_json::json!({ "dry_run": dry_run })), &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
@@ -0,0 +1,550 @@
+// Email notifications over SMTP: events matching the config file's [email] section are mailed with the end of
+// the process's log, at once or as a digest, and held back per process so a crash loop doesn't flood inboxes
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use lettre::message::header::ContentType;
+use lettre::message::{Mailbox, Message};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::audit::AuditContext;
+use crate::error::{ApiError, FieldError};
+use crate::events::{Event, TRIGGER_NAMES};
+use crate::{ApiResponse, Supervisor};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    // Plain SMTP, for relays on localhost
+    None,
+    #[default]
+    Starttls,
+    // TLS from the start, "SMTPS"
+    Tls,
+}
+
+// The `[email]` section of the config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    pub host: String,
+    // 25, 587 or 465 depending on `security`
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: Security,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+    // Process id globs, empty means all processes
+    #[serde(default)]
+    pub processes: Vec<String>,
+    // Collect events into one email this often instead of mailing them as they happen
+    pub digest_seconds: Option<u64>,
+    // Without a digest, further events of a process within this long after an email wait for the next one
+    #[serde(default = "default_min_interval_seconds")]
+    pub min_interval_seconds: u64,
+    #[serde(default = "default_log_lines")]
+    pub log_lines: usize,
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+fn default_events() -> Vec<String> {
+    vec!["failed".to_string(), "crash_loop".to_string(), "periodic_failed".to_string(), "log_alert".to_string()]
+}
+
+fn default_min_interval_seconds() -> u64 {
+    600
+}
+
+fn default_log_lines() -> usize {
+    20
+}
+
+fn default_timeout_seconds() -> u64 {
+    30
+}
+
+impl EmailConfig {
+    // Every invalid field, named `email.<field>`
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.host.trim().is_empty() {
+            errors.push(FieldError::new("email.host", "empty", "SMTP host cannot be empty"));
+        }
+        if let Err(e) = self.from.parse::<Mailbox>() {
+            errors.push(FieldError::new("email.from", "invalid", format!("Invalid address '{}': {}", self.from, e)));
+        }
+        if self.to.is_empty() {
+            errors.push(FieldError::new("email.to", "empty", "List at least one recipient"));
+        }
+        for (index, to) in self.to.iter().enumerate() {
+            if let Err(e) = to.parse::<Mailbox>() {
+                errors.push(FieldError::new(&format!("email.to[{}]", index), "invalid", format!("Invalid address '{}': {}", to, e)));
+            }
+        }
+        for (index, event) in self.events.iter().enumerate() {
+            if !TRIGGER_NAMES.contains(&event.as_str()) {
+                errors.push(FieldError::new(&format!("email.events[{}]", index), "unknown",
+                    format!("Unknown event '{}', expected one of: {}", event, TRIGGER_NAMES.join(", "))));
+            }
+        }
+        if self.username.is_some() != self.password.is_some() {
+            errors.push(FieldError::new("email.password", "required", "username and password must be given together"));
+        }
+        if self.digest_seconds == Some(0) {
+            errors.push(FieldError::new("email.digest_seconds", "out_of_range", "digest_seconds must be greater than zero"));
+        }
+        if self.log_lines > 1000 {
+            errors.push(FieldError::new("email.log_lines", "out_of_range", "log_lines can be at most 1000"));
+        }
+        if self.timeout_seconds == 0 {
+            errors.push(FieldError::new("email.timeout_seconds", "out_of_range", "timeout_seconds must be greater than zero"));
+        }
+        errors
+    }
+
+    fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.security {
+            Security::None => 25,
+            Security::Starttls => 587,
+            Security::Tls => 465,
+        })
+    }
+
+    fn trigger(&self, event: &Event) -> Option<&'static str> {
+        if !self.processes.is_empty() && !self.processes.iter().any(|pattern| crate::keys::glob_match(pattern, &event.process_id)) {
+            return None;
+        }
+        event.triggers().into_iter().find(|trigger| self.events.iter().any(|name| name == trigger))
+    }
+
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, String> {
+        let builder = match self.security {
+            Security::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.host),
+            Security::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.host).map_err(|e| e.to_string())?,
+            Security::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host).map_err(|e| e.to_string())?,
+        };
+        let mut builder = builder.port(self.port()).timeout(Some(Duration::from_secs(self.timeout_seconds)));
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        Ok(builder.build())
+    }
+
+    async fn send(&self, subject: &str, body: String) -> Result<(), String> {
+        let mut message = Message::builder()
+            .from(self.from.parse().map_err(|e| format!("Invalid from address: {}", e))?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            message = message.to(to.parse().map_err(|e| format!("Invalid recipient {}: {}", to, e))?);
+        }
+        let message = message.body(body).map_err(|e| e.to_string())?;
+        self.transport()?.send(message).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+pub fn configure(supervisor: &Supervisor, config: Option<EmailConfig>) {
+    *supervisor.email().write().unwrap() = config.map(Arc::new);
+}
+
+fn current(supervisor: &Supervisor) -> Option<Arc<EmailConfig>> {
+    supervisor.email().read().unwrap().clone()
+}
+
+// An event waiting to be mailed, with the log as it was when it happened
+struct Notification {
+    event: Event,
+    trigger: &'static str,
+    log_tail: Vec<String>,
+}
+
+impl Notification {
+    // The log is read on the blocking pool, after the lines still queued for it are written. There is none once
+    // the process has been removed
+    async fn new(event: Event, trigger: &'static str, log_lines: usize, supervisor: &Supervisor) -> Self {
+        let Ok(log_file) = supervisor.log_file(&event.process_id) else {
+            return Self { event, trigger, log_tail: Vec::new() };
+        };
+        let supervisor = supervisor.clone();
+        let log_tail = tokio::task::spawn_blocking(move || {
+            supervisor.logger().flush_process_logs();
+            match supervisor.logger().process_sink().read(&log_file, 1, log_lines) {
+                // Newest first
+                Ok((lines, _)) => lines.into_iter().rev().collect(),
+                Err(_) => Vec::new(),
+            }
+        }).await.unwrap_or_default();
+        Self { event, trigger, log_tail }
+    }
+}
+
+// Where composed emails go; SMTP outside of tests
+trait Mailer: Send + Sync {
+    // Sends in the background, failures are only logged
+    fn mail(&self, config: Arc<EmailConfig>, subject: String, body: String);
+}
+
+struct SmtpMailer;
+
+impl Mailer for SmtpMailer {
+    fn mail(&self, config: Arc<EmailConfig>, subject: String, body: String) {
+        tokio::spawn(async move {
+            if let Err(e) = config.send(&subject, body).await {
+                eprintln!("Failed to send email notification '{}' to {}: {}", subject, config.to.join(", "), e);
+            }
+        });
+    }
+}
+
+struct Outbox {
+    mailer: Arc<dyn Mailer>,
+    // Whose logs the notifications quote
+    supervisor: Supervisor,
+    // Digest mode: everything since the last digest
+    digest: Vec<Notification>,
+    last_digest: Option<Instant>,
+    // Immediate mode: per process, what arrived too soon after its last email
+    held: BTreeMap<String, Vec<Notification>>,
+    last_sent: HashMap<String, Instant>,
+}
+
+impl Outbox {
+    fn new(mailer: Arc<dyn Mailer>, supervisor: Supervisor) -> Self {
+        Self {
+            mailer,
+            supervisor,
+            digest: Vec::new(),
+            last_digest: None,
+            held: BTreeMap::new(),
+            last_sent: HashMap::new(),
+        }
+    }
+
+    // `config` is the [email] section as it is now, None when email is off
+    async fn add(&mut self, config: Option<Arc<EmailConfig>>, event: Event) {
+        let Some(config) = config else {
+            return;
+        };
+        let Some(trigger) = config.trigger(&event) else {
+            return;
+        };
+        let notification = Notification::new(event, trigger, config.log_lines, &self.supervisor).await;
+        if config.digest_seconds.is_some() {
+            self.digest.push(notification);
+            return;
+        }
+
+        let process_id = notification.event.process_id.clone();
+        let recently_mailed = self.last_sent.get(&process_id)
+            .is_some_and(|sent| sent.elapsed() < Duration::from_secs(config.min_interval_seconds));
+        if recently_mailed {
+            self.held.entry(process_id).or_default().push(notification);
+        } else {
+            self.last_sent.insert(process_id, Instant::now());
+            self.send_batch(config, vec![notification]);
+        }
+    }
+
+    fn flush_due(&mut self, config: Option<Arc<EmailConfig>>) {
+        let Some(config) = config else {
+            return;
+        };
+        match config.digest_seconds {
+            Some(digest_seconds) => {
+                // Switched to digests: what was held goes into the next one
+                self.digest.extend(std::mem::take(&mut self.held).into_values().flatten());
+                let last_digest = *self.last_digest.get_or_insert_with(Instant::now);
+                if !self.digest.is_empty() && last_digest.elapsed() >= Duration::from_secs(digest_seconds) {
+                    self.last_digest = Some(Instant::now());
+                    let digest = std::mem::take(&mut self.digest);
+                    self.send_batch(config, digest);
+                }
+            }
+            None => {
+                if !self.digest.is_empty() {
+                    let digest = std::mem::take(&mut self.digest);
+                    self.send_batch(config.clone(), digest);
+                }
+                let min_interval = Duration::from_secs(config.min_interval_seconds);
+                let due: Vec<String> = self.held.keys()
+                    .filter(|process_id| self.last_sent.get(*process_id).is_none_or(|sent| sent.elapsed() >= min_interval))
+                    .cloned()
+                    .collect();
+                for process_id in due {
+                    if let Some(notifications) = self.held.remove(&process_id) {
+                        self.last_sent.insert(process_id, Instant::now());
+                        self.send_batch(config.clone(), notifications);
+                    }
+                }
+            }
+        }
+    }
+
+    // Mails the notifications as one email
+    fn send_batch(&self, config: Arc<EmailConfig>, notifications: Vec<Notification>) {
+        let (subject, body) = compose(&notifications);
+        self.mailer.mail(config, subject, body);
+    }
+}
+
+// The subject and plain text body of one email about the notifications
+fn compose(notifications: &[Notification]) -> (String, String) {
+    let mut process_ids: Vec<&str> = notifications.iter().map(|notification| notification.event.process_id.as_str()).collect();
+    process_ids.sort_unstable();
+    process_ids.dedup();
+    let subject = match notifications {
+        [notification] => format!("[exeio] {}: {}", notification.trigger, notification.event.describe()),
+        _ => format!("[exeio] {} events for {}", notifications.len(), process_ids.join(", ")),
+    };
+
+    let mut body = String::new();
+    for notification in notifications {
+        body.push_str(&format!("{}  {}\n", notification.event.timestamp.format("%Y-%m-%d %H:%M:%S UTC"), notification.event.describe()));
+        if !notification.log_tail.is_empty() {
+            body.push_str(&format!("Last {} log lines of {}:\n", notification.log_tail.len(), notification.event.process_id));
+            for line in &notification.log_tail {
+                body.push_str(&format!("    {}\n", line));
+            }
+        }
+        body.push('\n');
+    }
+    (subject, body)
+}
+
+// Collects matching events into emails for as long as exeio runs
+pub fn start(supervisor: &Supervisor) {
+    let mut receiver = supervisor.subscribe();
+    let supervisor = supervisor.clone();
+    tokio::spawn(async move {
+        let mut outbox = Outbox::new(Arc::new(SmtpMailer), supervisor.clone());
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                received = receiver.recv() => match received {
+                    Ok(event) => outbox.add(current(&supervisor), event).await,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => eprintln!("Email notifications missed {} events", missed),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = tick.tick() => outbox.flush_due(current(&supervisor)),
+            }
+        }
+    });
+}
+
+// Sends a test email right away and reports whether the server took it
+pub async fn handle_test_email(audit: AuditContext, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(config) = current(&supervisor) else {
+        let error = ApiError::EmailNotConfigured;
+        audit.record_error(None, None, &error);
+        return Err(warp::reject::custom(error));
+    };
+
+    let body = format!("Email notifications from exeio reach {}.\n", config.to.join(", "));
+    match config.send("[exeio] Test email", body).await {
+        Ok(()) => {
+            let response = ApiResponse {
+                success: true,
+                message: format!("Test email sent to {} via {}:{}", config.to.join(", "), config.host, config.port()),
+            };
+            audit.record(None, None, &response);
+            Ok(warp::reply::json(&response))
+        }
+        Err(e) => {
+            let error = ApiError::EmailFailed(format!("Failed to send test email via {}:{}: {}", config.host, config.port(), e));
+            audit.record_error(None, None, &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::events::EventKind;
+
+    // Records what would have been mailed
+    #[derive(Default)]
+    struct StubMailer {
+        sent: Mutex<Vec<(String, String)>>,
+    }
+
+    impl Mailer for StubMailer {
+        fn mail(&self, _config: Arc<EmailConfig>, subject: String, body: String) {
+            self.sent.lock().unwrap().push((subject, body));
+        }
+    }
+
+    impl StubMailer {
+        fn subjects(&self) -> Vec<String> {
+            self.sent.lock().unwrap().iter().map(|(subject, _)| subject.clone()).collect()
+        }
+    }
+
+    fn config(digest_seconds: Option<u64>, min_interval_seconds: u64) -> Option<Arc<EmailConfig>> {
+        Some(Arc::new(EmailConfig {
+            host: "localhost".to_string(),
+            port: None,
+            security: Security::None,
+            username: None,
+            password: None,
+            from: "exeio@example.com".to_string(),
+            to: vec!["ops@example.com".to_string()],
+            events: default_events(),
+            processes: Vec::new(),
+            digest_seconds,
+            min_interval_seconds,
+            log_lines: 0,
+            timeout_seconds: 5,
+        }))
+    }
+
+    fn failed(process_id: &str) -> Event {
+        Event {
+            id: 1,
+            timestamp: chrono::Utc::now(),
+            process_id: process_id.to_string(),
+            kind: EventKind::Failed { error: "No such file or directory".to_string() },
+        }
+    }
+
+    fn outbox() -> (Outbox, Arc<StubMailer>) {
+        let mailer = Arc::new(StubMailer::default());
+        let supervisor = Supervisor::builder().config_store(crate::MemoryConfigStore::default()).build();
+        (Outbox::new(mailer.clone(), supervisor), mailer)
+    }
+
+    // Moves an instant back, as if that much time had passed since
+    fn ago(seconds: u64) -> Instant {
+        Instant::now() - Duration::from_secs(seconds)
+    }
+
+    #[tokio::test]
+    async fn ignores_events_it_is_not_configured_for() {
+        let (mut outbox, mailer) = outbox();
+        let stopped = Event { kind: EventKind::Stopped, ..failed("web") };
+        outbox.add(config(None, 600), stopped).await;
+        outbox.add(None, failed("web")).await;
+        outbox.flush_due(config(None, 600));
+        assert!(mailer.subjects().is_empty());
+    }
+
+    #[tokio::test]
+    async fn holds_events_within_min_interval_then_flushes_them_together() {
+        let config = config(None, 600);
+        let (mut outbox, mailer) = outbox();
+
+        outbox.add(config.clone(), failed("web")).await;
+        assert_eq!(mailer.subjects(), ["[exeio] failed: Process 'web' failed to start: No such file or directory"]);
+
+        // Within min_interval of the first email: held, while other processes still mail at once
+        outbox.add(config.clone(), failed("web")).await;
+        outbox.add(config.clone(), failed("web")).await;
+        outbox.add(config.clone(), failed("worker")).await;
+        outbox.flush_due(config.clone());
+        assert_eq!(mailer.subjects().len(), 2);
+        assert_eq!(outbox.held["web"].len(), 2);
+
+        outbox.last_sent.insert("web".to_string(), ago(600));
+        outbox.flush_due(config.clone());
+        let subjects = mailer.subjects();
+        assert_eq!(subjects.len(), 3);
+        assert_eq!(subjects[2], "[exeio] 2 events for web");
+        assert!(outbox.held.is_empty());
+
+        // The flush counts as an email, so the interval starts again
+        outbox.add(config.clone(), failed("web")).await;
+        assert_eq!(mailer.subjects().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn batches_events_into_digests() {
+        let config = config(Some(300), 600);
+        let (mut outbox, mailer) = outbox();
+
+        outbox.add(config.clone(), failed("web")).await;
+        outbox.add(config.clone(), failed("worker")).await;
+        outbox.add(config.clone(), failed("web")).await;
+        outbox.flush_due(config.clone());
+        assert!(mailer.subjects().is_empty());
+
+        outbox.last_digest = Some(ago(300));
+        outbox.flush_due(config.clone());
+        assert_eq!(mailer.subjects(), ["[exeio] 3 events for web, worker"]);
+        let body = mailer.sent.lock().unwrap()[0].1.clone();
+        assert_eq!(body.matches("failed to start").count(), 3);
+
+        // Nothing new, no empty digest
+        outbox.last_digest = Some(ago(300));
+        outbox.flush_due(config);
+        assert_eq!(mailer.subjects().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn quotes_the_end_of_the_process_log_file() {
+        let (mut outbox, mailer) = outbox();
+        let log_file = std::env::temp_dir().join(format!("exeio-email-tail-{}.log", std::process::id()));
+        let log_file = log_file.to_string_lossy().into_owned();
+        let _ = std::fs::remove_file(&log_file);
+        let process_config = crate::ProcessConfig {
+            id: "web".to_string(),
+            command: "true".to_string(),
+            args: Vec::new(),
+            working_dir: None,
+            auto_restart: false,
+            log_file: log_file.clone(),
+            periodic: false,
+            period_seconds: None,
+            hooks: Default::default(),
+            log_rules: Vec::new(),
+        };
+        outbox.supervisor.logger().open_process_log(&log_file).unwrap();
+        for line in ["first", "second", "third"] {
+            outbox.supervisor.logger().append_process_log(&log_file, &format!("{}\n", line)).unwrap();
+        }
+        outbox.supervisor.process_map().lock().unwrap().insert("web".to_string(), crate::ManagedProcess {
+            config: process_config,
+            pid: None,
+            actor: None,
+            run_count: 0,
+            last_run: None,
+            periodic_handle: None,
+            status: crate::ProcessStatus::Failed,
+            last_exit_time: None,
+            pending_restart: false,
+        });
+
+        let mut email = (*config(None, 600).unwrap()).clone();
+        email.log_lines = 2;
+        outbox.add(Some(Arc::new(email)), failed("web")).await;
+        let body = mailer.sent.lock().unwrap()[0].1.clone();
+        assert!(body.contains("Last 2 log lines of web:"));
+        assert!(body.contains("second") && body.contains("third") && !body.contains("first"));
+        let _ = std::fs::remove_file(&log_file);
+    }
+
+    #[tokio::test]
+    async fn held_events_join_the_digest_when_digests_are_switched_on() {
+        let (mut outbox, mailer) = outbox();
+        outbox.add(config(None, 600), failed("web")).await;
+        outbox.add(config(None, 600), failed("web")).await;
+        assert_eq!(outbox.held["web"].len(), 1);
+
+        let digest = config(Some(60), 600);
+        outbox.flush_due(digest.clone());
+        assert!(outbox.held.is_empty());
+        outbox.last_digest = Some(ago(60));
+        outbox.flush_due(digest);
+        assert_eq!(mailer.subjects()[1], "[exeio] failed: Process 'web' failed to start: No such file or directory");
+    }
+}
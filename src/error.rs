@@ -0,0 +1,234 @@
+// Typed API errors with stable machine-readable codes, and the rejection handler that renders them
+#[cfg(feature = "server")]
+use std::convert::Infallible;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+#[cfg(feature = "server")]
+use warp::http::StatusCode;
+#[cfg(feature = "server")]
+use warp::Reply;
+
+#[cfg(feature = "server")]
+use crate::ratelimit::TooManyRequests;
+
+// One invalid field in a request body
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &'static str, message: impl Into<String>) -> Self {
+        Self { field: field.to_string(), code, message: message.into() }
+    }
+}
+
+// The body of every error response
+#[cfg(feature = "server")]
+#[derive(Serialize, JsonSchema)]
+pub struct ErrorBody {
+    pub success: bool,
+    /// Stable machine-readable error code
+    pub code: String,
+    pub message: String,
+    /// Present for `validation_failed`, one entry per invalid field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<FieldError>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    // 400: the request could not be parsed
+    InvalidJson(String),
+    InvalidQuery,
+    // 401 / 403
+    Unauthorized,
+    Forbidden(String),
+    // 404
+    ProcessNotFound(String),
+    KeyNotFound(String),
+    RevisionNotFound(u64),
+    WebhookNotFound(String),
+    RouteNotFound,
+    // 405 / 413 / 415
+    MethodNotAllowed,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    // 409: the request is valid but conflicts with the current state
+    ProcessExists(String),
+    KeyExists(String),
+    ProcessNotInteractive(String),
+    ProcessNotRunning(String),
+    NoConfigFile,
+    EmailNotConfigured,
+    UpgradeInProgress,
+    // 422: the request parsed but has invalid fields
+    Validation(Vec<FieldError>),
+    // 500
+    Io(String),
+    Internal(String),
+    // 502: a webhook or the SMTP server didn't take a test delivery
+    WebhookFailed(String),
+    EmailFailed(String),
+}
+
+#[cfg(feature = "server")]
+impl warp::reject::Reject for ApiError {}
+
+// So library callers can use `?` with their own error types
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl ApiError {
+    // Stable identifiers clients can match on, unlike the messages
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidJson(_) => "invalid_json",
+            ApiError::InvalidQuery => "invalid_query",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::ProcessNotFound(_) => "process_not_found",
+            ApiError::KeyNotFound(_) => "key_not_found",
+            ApiError::RevisionNotFound(_) => "revision_not_found",
+            ApiError::WebhookNotFound(_) => "webhook_not_found",
+            ApiError::RouteNotFound => "route_not_found",
+            ApiError::MethodNotAllowed => "method_not_allowed",
+            ApiError::PayloadTooLarge => "payload_too_large",
+            ApiError::UnsupportedMediaType => "unsupported_media_type",
+            ApiError::ProcessExists(_) => "process_exists",
+            ApiError::KeyExists(_) => "key_exists",
+            ApiError::ProcessNotInteractive(_) => "process_not_interactive",
+            ApiError::ProcessNotRunning(_) => "process_not_running",
+            ApiError::NoConfigFile => "no_config_file",
+            ApiError::EmailNotConfigured => "email_not_configured",
+            ApiError::UpgradeInProgress => "upgrade_in_progress",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::Io(_) => "io_error",
+            ApiError::Internal(_) => "internal_error",
+            ApiError::WebhookFailed(_) => "webhook_failed",
+            ApiError::EmailFailed(_) => "email_failed",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::InvalidJson(e) => format!("Invalid JSON body: {}", e),
+            ApiError::InvalidQuery => "Invalid query string, check parameter names and types".to_string(),
+            ApiError::Unauthorized => "Invalid or missing API key. Provide a valid key in the 'exeio-api-key' header.".to_string(),
+            ApiError::Forbidden(message) => message.clone(),
+            ApiError::ProcessNotFound(id) => format!("Process {} not found", id),
+            ApiError::KeyNotFound(name) => format!("Key '{}' not found", name),
+            ApiError::RevisionNotFound(rev) => format!("Config revision {} not found, see GET /config/history", rev),
+            ApiError::WebhookNotFound(name) => format!("Webhook '{}' not found, webhooks are declared in the --config file", name),
+            ApiError::RouteNotFound => "No such endpoint, see GET /info for the list".to_string(),
+            ApiError::MethodNotAllowed => "Method not allowed for this endpoint".to_string(),
+            ApiError::PayloadTooLarge => "Request body is too large".to_string(),
+            ApiError::UnsupportedMediaType => "Request body must be JSON (Content-Type: application/json)".to_string(),
+            ApiError::ProcessExists(id) => format!("Process with ID '{}' already exists. Please use a unique ID.", id),
+            ApiError::KeyExists(name) => format!("Key with name '{}' already exists", name),
+            ApiError::ProcessNotInteractive(id) => format!("Process {} has no stdin channel or is periodic", id),
+            ApiError::ProcessNotRunning(id) => format!("Process {} is not running", id),
+            ApiError::NoConfigFile => "exeio was started without --config, there is no file to reload".to_string(),
+            ApiError::EmailNotConfigured => "Email notifications are not configured, add an [email] section to the --config file".to_string(),
+            ApiError::UpgradeInProgress => "An upgrade is already in progress".to_string(),
+            ApiError::Validation(fields) => match fields.as_slice() {
+                [field] => field.message.clone(),
+                _ => format!("{} fields are invalid", fields.len()),
+            },
+            ApiError::Io(message)
+            | ApiError::Internal(message)
+            | ApiError::WebhookFailed(message)
+            | ApiError::EmailFailed(message) => message.clone(),
+        }
+    }
+}
+
+// How the HTTP API renders them
+#[cfg(feature = "server")]
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidJson(_) | ApiError::InvalidQuery => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::ProcessNotFound(_)
+            | ApiError::KeyNotFound(_)
+            | ApiError::RevisionNotFound(_)
+            | ApiError::WebhookNotFound(_)
+            | ApiError::RouteNotFound => StatusCode::NOT_FOUND,
+            ApiError::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ApiError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::ProcessExists(_)
+            | ApiError::KeyExists(_)
+            | ApiError::ProcessNotInteractive(_)
+            | ApiError::ProcessNotRunning(_)
+            | ApiError::NoConfigFile
+            | ApiError::EmailNotConfigured
+            | ApiError::UpgradeInProgress => StatusCode::CONFLICT,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Io(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::WebhookFailed(_) | ApiError::EmailFailed(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    pub fn into_response(self) -> warp::reply::Response {
+        let body = ErrorBody {
+            success: false,
+            code: self.code().to_string(),
+            message: self.message(),
+            errors: match &self {
+                ApiError::Validation(fields) => Some(fields.clone()),
+                _ => None,
+            },
+        };
+        warp::reply::with_status(warp::reply::json(&body), self.status()).into_response()
+    }
+}
+
+// Renders every rejection as a JSON error, keeping warp's own rejections on their proper status
+#[cfg(feature = "server")]
+pub async fn handle_rejection(err: warp::Rejection) -> Result<warp::reply::Response, Infallible> {
+    if let Some(limited) = err.find::<TooManyRequests>() {
+        let body = ErrorBody {
+            success: false,
+            code: "rate_limited".to_string(),
+            message: limited.message.clone(),
+            errors: None,
+        };
+        return Ok(warp::reply::with_header(
+            warp::reply::with_status(warp::reply::json(&body), StatusCode::TOO_MANY_REQUESTS),
+            "Retry-After",
+            limited.retry_after_secs().to_string(),
+        ).into_response());
+    }
+
+    let error = if let Some(error) = err.find::<ApiError>() {
+        error.clone()
+    } else if let Some(e) = err.find::<warp::body::BodyDeserializeError>() {
+        ApiError::InvalidJson(std::error::Error::source(e).map(|s| s.to_string()).unwrap_or_else(|| e.to_string()))
+    } else if err.find::<warp::reject::InvalidQuery>().is_some() {
+        ApiError::InvalidQuery
+    } else if err.find::<warp::reject::PayloadTooLarge>().is_some() {
+        ApiError::PayloadTooLarge
+    } else if err.find::<warp::reject::UnsupportedMediaType>().is_some() {
+        ApiError::UnsupportedMediaType
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        ApiError::MethodNotAllowed
+    } else if err.is_not_found() {
+        ApiError::RouteNotFound
+    } else {
+        eprintln!("Unhandled rejection: {:?}", err);
+        ApiError::Internal("Internal server error".to_string())
+    };
+
+    Ok(error.into_response())
+}
@@ -0,0 +1,420 @@
+// Process lifecycle events: every state transition is published here, kept in a bounded replay buffer and
+// broadcast to library subscribers and GET /events streams
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "server")]
+use std::convert::Infallible;
+use std::sync::Mutex;
+
+#[cfg(feature = "server")]
+use futures_util::{SinkExt, StreamExt};
+use schemars::JsonSchema;
+#[cfg(feature = "server")]
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::broadcast;
+#[cfg(feature = "server")]
+use warp::sse;
+#[cfg(feature = "server")]
+use warp::ws::Message;
+
+#[cfg(feature = "server")]
+use crate::error::{ApiError, FieldError};
+#[cfg(feature = "server")]
+use crate::keys::{Caller, Scope};
+#[cfg(feature = "server")]
+use crate::Supervisor;
+
+// How many past events a new subscriber can ask for
+const REPLAY_CAPACITY: usize = 1000;
+// This many auto-restarts of one process within the window is a crash loop
+const CRASH_LOOP_RESTARTS: usize = 5;
+const CRASH_LOOP_WINDOW_SECONDS: i64 = 300;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Event {
+    /// Increases by one with every event, so a reconnecting subscriber can ask for what it missed.
+    pub id: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub process_id: String,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    /// A process id the supervisor didn't know before, from any source (API, config file, import, restore).
+    Added,
+    Started { pid: u32, run_count: u64 },
+    /// `exit_code` is None when the process was killed by a signal or its status couldn't be read
+    Exited { exit_code: Option<i32> },
+    /// An auto-restart that will happen after `delay_seconds`.
+    RestartScheduled { delay_seconds: u64, reason: String },
+    /// The process has been auto-restarted `restarts` times within `window_seconds`; sent once per loop.
+    CrashLoop { restarts: usize, window_seconds: i64 },
+    /// One run of a periodic process finished.
+    PeriodicRunFinished { run: u64, exit_code: Option<i32> },
+    /// A process left running by a previous supervisor is watched again.
+    Reattached { pid: u32 },
+    Failed { error: String },
+    /// Stopped through the API or the library; other stops are followed by a start or removal.
+    Stopped,
+    Removed,
+    /// An output line matched a log rule whose actions ran; `notify` also fires the `log_alert` trigger.
+    LogMatched { rule: String, stream: String, line: String, notify: bool },
+}
+
+impl EventKind {
+    // The `event` tag, for filtering
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Added => "added",
+            EventKind::Started { .. } => "started",
+            EventKind::Exited { .. } => "exited",
+            EventKind::RestartScheduled { .. } => "restart_scheduled",
+            EventKind::CrashLoop { .. } => "crash_loop",
+            EventKind::PeriodicRunFinished { .. } => "periodic_run_finished",
+            EventKind::Reattached { .. } => "reattached",
+            EventKind::Failed { .. } => "failed",
+            EventKind::Stopped => "stopped",
+            EventKind::Removed => "removed",
+            EventKind::LogMatched { .. } => "log_matched",
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+pub const EVENT_NAMES: &[&str] = &[
+    "added", "started", "exited", "restart_scheduled", "crash_loop", "periodic_run_finished", "reattached", "failed",
+    "stopped", "removed", "log_matched",
+];
+
+// What notifiers can be configured to fire on: the event names and conditions derived from single events
+#[cfg(feature = "server")]
+pub const TRIGGER_NAMES: &[&str] = &[
+    "added", "started", "exited", "restart_scheduled", "crash_loop", "periodic_run_finished", "reattached", "failed",
+    "stopped", "removed", "log_matched", "periodic_failed", "log_alert",
+    // exeio has no health checks yet; accepted so notifier configs can already list it
+    "unhealthy",
+];
+
+impl Event {
+    // The triggers this event fires, its own name first
+    pub fn triggers(&self) -> Vec<&'static str> {
+        let mut triggers = vec![self.kind.name()];
+        if let EventKind::PeriodicRunFinished { exit_code, .. } = self.kind
+            && exit_code != Some(0) {
+                triggers.push("periodic_failed");
+        }
+        if let EventKind::LogMatched { notify: true, .. } = self.kind {
+            triggers.push("log_alert");
+        }
+        triggers
+    }
+
+    // One line for people, e.g. in notifications
+    pub fn describe(&self) -> String {
+        let id = &self.process_id;
+        match &self.kind {
+            EventKind::Added => format!("Process '{}' was added", id),
+            EventKind::Started { pid, run_count } => format!("Process '{}' started (PID {}, run #{})", id, pid, run_count),
+            EventKind::Exited { exit_code: Some(code) } => format!("Process '{}' exited with code {}", id, code),
+            EventKind::Exited { exit_code: None } => format!("Process '{}' was killed by a signal", id),
+            EventKind::RestartScheduled { delay_seconds, .. } => format!("Process '{}' will be restarted in {}s", id, delay_seconds),
+            EventKind::CrashLoop { restarts, window_seconds } => {
+                format!("Process '{}' is crash looping: {} restarts in {}s", id, restarts, window_seconds)
+            }
+            EventKind::PeriodicRunFinished { run, exit_code: Some(code) } => format!("Run #{} of process '{}' exited with code {}", run, id, code),
+            EventKind::PeriodicRunFinished { run, exit_code: None } => format!("Run #{} of process '{}' was killed by a signal", run, id),
+            EventKind::Reattached { pid } => format!("Reattached to process '{}' (PID {})", id, pid),
+            EventKind::Failed { error } => format!("Process '{}' failed to start: {}", id, error),
+            EventKind::Stopped => format!("Process '{}' was stopped", id),
+            EventKind::Removed => format!("Process '{}' was removed", id),
+            EventKind::LogMatched { rule, line, .. } => format!("Process '{}' logged a line matching rule '{}': {}", id, rule, line),
+        }
+    }
+}
+
+struct Bus {
+    next_id: u64,
+    replay: VecDeque<Event>,
+    // Recent auto-restarts per process, for crash loop detection
+    restarts: HashMap<String, VecDeque<chrono::DateTime<chrono::Utc>>>,
+}
+
+impl Bus {
+    fn publish(&mut self, sender: &broadcast::Sender<Event>, process_id: &str, kind: EventKind, now: chrono::DateTime<chrono::Utc>) {
+        let event = Event { id: self.next_id, timestamp: now, process_id: process_id.to_string(), kind };
+        self.next_id += 1;
+        if self.replay.len() == REPLAY_CAPACITY {
+            self.replay.pop_front();
+        }
+        self.replay.push_back(event.clone());
+        // Sending only fails when nobody is subscribed
+        let _ = sender.send(event);
+    }
+
+    // True when this restart makes the process's recent restarts reach the crash loop threshold
+    fn starts_crash_loop(&mut self, process_id: &str, now: chrono::DateTime<chrono::Utc>) -> bool {
+        let restarts = self.restarts.entry(process_id.to_string()).or_default();
+        while restarts.front().is_some_and(|time| (now - *time).num_seconds() >= CRASH_LOOP_WINDOW_SECONDS) {
+            restarts.pop_front();
+        }
+        restarts.push_back(now);
+        restarts.len() == CRASH_LOOP_RESTARTS
+    }
+}
+
+// A supervisor's events
+pub struct EventBus {
+    // Slow subscribers miss the oldest events rather than holding up the supervisor
+    sender: broadcast::Sender<Event>,
+    bus: Mutex<Bus>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(1024).0,
+            bus: Mutex::new(Bus {
+                next_id: 1,
+                replay: VecDeque::with_capacity(REPLAY_CAPACITY),
+                restarts: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn emit(&self, process_id: &str, kind: EventKind, now: chrono::DateTime<chrono::Utc>) {
+        // Numbered, buffered and sent under one lock so ids reach subscribers in order
+        let mut bus = self.bus.lock().unwrap();
+        let crash_loop = match kind {
+            EventKind::RestartScheduled { .. } => bus.starts_crash_loop(process_id, now),
+            EventKind::Removed => {
+                bus.restarts.remove(process_id);
+                false
+            }
+            _ => false,
+        };
+        bus.publish(&self.sender, process_id, kind, now);
+        if crash_loop {
+            bus.publish(&self.sender, process_id, EventKind::CrashLoop { restarts: CRASH_LOOP_RESTARTS, window_seconds: CRASH_LOOP_WINDOW_SECONDS }, now);
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    // The buffered events after `since` and a receiver for everything after them, with nothing lost or repeated
+    // in between
+    pub fn subscribe_since(&self, since: u64) -> (Vec<Event>, broadcast::Receiver<Event>) {
+        let bus = self.bus.lock().unwrap();
+        let replay = bus.replay.iter().filter(|event| event.id > since).cloned().collect();
+        (replay, self.sender.subscribe())
+    }
+}
+
+#[cfg(feature = "server")]
+#[derive(Deserialize, JsonSchema)]
+pub struct EventsQuery {
+    /// Comma-separated process ids; all processes the key may access if absent.
+    process: Option<String>,
+    /// Comma-separated event types, e.g. `exited,restart_scheduled`; all types if absent.
+    #[serde(rename = "type")]
+    event_type: Option<String>,
+    /// Replays the buffered events with a higher id first; `0` replays the whole buffer. The `Last-Event-ID`
+    /// header an EventSource sends when it reconnects does the same.
+    since: Option<u64>,
+}
+
+#[cfg(feature = "server")]
+// What one GET /events stream lets through
+struct EventFilter {
+    caller: Caller,
+    processes: Option<Vec<String>>,
+    types: Option<Vec<String>>,
+}
+
+#[cfg(feature = "server")]
+impl EventFilter {
+    fn new(caller: Caller, query: &EventsQuery) -> Result<Self, ApiError> {
+        let split = |list: &str| list.split(',').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect::<Vec<_>>();
+        let types = query.event_type.as_deref().map(split);
+        if let Some(types) = &types {
+            let unknown: Vec<&String> = types.iter().filter(|name| !EVENT_NAMES.contains(&name.as_str())).collect();
+            if !unknown.is_empty() {
+                return Err(ApiError::Validation(vec![FieldError::new("type", "unknown",
+                    format!("Unknown event type {:?}, expected one of: {}", unknown, EVENT_NAMES.join(", ")))]));
+            }
+        }
+        Ok(Self { caller, processes: query.process.as_deref().map(split), types })
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        self.caller.can_access(&event.process_id)
+            && self.processes.as_ref().is_none_or(|ids| ids.contains(&event.process_id))
+            && self.types.as_ref().is_none_or(|types| types.iter().any(|name| name == event.kind.name()))
+    }
+
+    // The process's output is for callers that may read its logs; without that scope a matched line is sent
+    // empty
+    fn visible(&self, mut event: Event) -> Event {
+        if let EventKind::LogMatched { line, .. } = &mut event.kind
+            && !self.caller.has_scope(Scope::Logs)
+        {
+            line.clear();
+        }
+        event
+    }
+}
+
+#[cfg(feature = "server")]
+// One item of a GET /events stream
+enum Delivery {
+    Event(Event),
+    // The subscriber fell this many events behind and they were dropped
+    Lagged(u64),
+}
+
+#[cfg(feature = "server")]
+impl Delivery {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Delivery::Event(event) => serde_json::to_value(event).unwrap_or_default(),
+            Delivery::Lagged(missed) => serde_json::json!({ "event": "lagged", "missed": missed }),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+// Replayed events first, then live ones, skipping what the filter doesn't let through
+struct Subscription {
+    filter: EventFilter,
+    replay: std::vec::IntoIter<Event>,
+    receiver: broadcast::Receiver<Event>,
+}
+
+#[cfg(feature = "server")]
+impl Subscription {
+    fn new(supervisor: &Supervisor, filter: EventFilter, since: Option<u64>) -> Self {
+        let (replay, receiver) = match since {
+            Some(since) => supervisor.subscribe_since(since),
+            None => (Vec::new(), supervisor.subscribe()),
+        };
+        Self { filter, replay: replay.into_iter(), receiver }
+    }
+
+    // None once the supervisor's event bus is gone
+    async fn next(&mut self) -> Option<Delivery> {
+        for event in self.replay.by_ref() {
+            if self.filter.matches(&event) {
+                return Some(Delivery::Event(self.filter.visible(event)));
+            }
+        }
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if self.filter.matches(&event) => return Some(Delivery::Event(self.filter.visible(event))),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(missed)) => return Some(Delivery::Lagged(missed)),
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+// A WebSocket when the client asked for an upgrade, server-sent events otherwise
+#[cfg(feature = "server")]
+pub async fn handle_events(
+    caller: Caller,
+    query: EventsQuery,
+    ws: Option<warp::ws::Ws>,
+    last_event_id: Option<u64>,
+    supervisor: Supervisor,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    match ws {
+        Some(ws) => Ok(Box::new(handle_events_ws(caller, query, ws, supervisor).await?)),
+        None => Ok(Box::new(handle_events_sse(caller, query, last_event_id, supervisor).await?)),
+    }
+}
+
+#[cfg(feature = "server")]
+async fn handle_events_ws(
+    caller: Caller,
+    query: EventsQuery,
+    ws: warp::ws::Ws,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let filter = EventFilter::new(caller, &query).map_err(warp::reject::custom)?;
+    let mut subscription = Subscription::new(&supervisor, filter, query.since);
+    Ok(ws.on_upgrade(move |socket| async move {
+        let (mut sender, mut receiver) = socket.split();
+        loop {
+            tokio::select! {
+                delivery = subscription.next() => {
+                    let Some(delivery) = delivery else { break };
+                    if sender.send(Message::text(delivery.to_json().to_string())).await.is_err() {
+                        break;
+                    }
+                }
+                // Nothing is expected from the client; this notices when it goes away
+                message = receiver.next() => match message {
+                    Some(Ok(message)) if !message.is_close() => {}
+                    _ => break,
+                },
+            }
+        }
+    }))
+}
+
+#[cfg(feature = "server")]
+async fn handle_events_sse(
+    caller: Caller,
+    query: EventsQuery,
+    last_event_id: Option<u64>,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let filter = EventFilter::new(caller, &query).map_err(warp::reject::custom)?;
+    let subscription = Subscription::new(&supervisor, filter, query.since.or(last_event_id));
+    let stream = futures_util::stream::unfold(subscription, |mut subscription| async move {
+        let delivery = subscription.next().await?;
+        let sse_event = match &delivery {
+            Delivery::Event(event) => sse::Event::default().id(event.id.to_string()).event(event.kind.name()),
+            Delivery::Lagged(_) => sse::Event::default().event("lagged"),
+        };
+        Some((Ok::<_, Infallible>(sse_event.data(delivery.to_json().to_string())), subscription))
+    });
+    Ok(sse::reply(sse::keep_alive().stream(stream)))
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    fn filter(scopes: Vec<Scope>) -> EventFilter {
+        let caller = Caller { name: "test".to_string(), scopes, processes: Vec::new() };
+        EventFilter::new(caller, &EventsQuery { process: None, event_type: None, since: None }).unwrap()
+    }
+
+    fn log_matched() -> Event {
+        Event {
+            id: 1,
+            timestamp: chrono::Utc::now(),
+            process_id: "web".to_string(),
+            kind: EventKind::LogMatched { rule: "errors".to_string(), stream: "stderr".to_string(), line: "secret".to_string(), notify: false },
+        }
+    }
+
+    fn line(event: Event) -> String {
+        match event.kind {
+            EventKind::LogMatched { line, .. } => line,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn matched_lines_need_the_logs_scope() {
+        assert_eq!(line(filter(vec![Scope::Read]).visible(log_matched())), "");
+        assert_eq!(line(filter(vec![Scope::Read, Scope::Logs]).visible(log_matched())), "secret");
+        assert_eq!(line(filter(vec![Scope::Admin]).visible(log_matched())), "secret");
+    }
+}
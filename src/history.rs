@@ -0,0 +1,440 @@
+// History of the saved process configuration, with rollback to an earlier revision. Revisions are appended to a
+// JSON lines file, which keeps the last MAX_REVISIONS
+#[cfg(feature = "server")]
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use crate::audit::AuditContext;
+#[cfg(feature = "server")]
+use crate::config_file::{self, PlannedChange};
+#[cfg(feature = "server")]
+use crate::error::ApiError;
+#[cfg(feature = "server")]
+use crate::{ApiResponse, Supervisor};
+use crate::{Clock, ConfigChange, ProcessConfig};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProcessChange {
+    pub id: String,
+    pub kind: ChangeKind,
+    /// Changed fields, for `changed`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<ConfigChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Revision {
+    pub rev: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Key name, or what changed the config outside the API
+    pub actor: String,
+    pub changes: Vec<ProcessChange>,
+    /// The whole saved config as of this revision
+    pub configs: Vec<ProcessConfig>,
+}
+
+#[cfg(feature = "server")]
+// A revision without the config snapshot, for listings
+#[derive(Serialize, JsonSchema)]
+pub struct RevisionSummary {
+    rev: u64,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    actor: String,
+    changes: Vec<ProcessChange>,
+}
+
+#[cfg(feature = "server")]
+#[derive(Deserialize, JsonSchema)]
+pub struct HistoryQuery {
+    page: Option<usize>,
+    page_size: Option<usize>,
+}
+
+#[cfg(feature = "server")]
+#[derive(Serialize, JsonSchema)]
+pub struct HistoryPage {
+    success: bool,
+    page: usize,
+    page_size: usize,
+    total_revisions: usize,
+    revisions: Vec<RevisionSummary>,
+}
+
+#[cfg(feature = "server")]
+#[derive(Deserialize, JsonSchema)]
+pub struct RollbackParams {
+    /// Only return the plan, change nothing
+    dry_run: Option<bool>,
+}
+
+#[cfg(feature = "server")]
+#[derive(Serialize, JsonSchema)]
+pub struct RollbackResponse {
+    success: bool,
+    message: String,
+    dry_run: bool,
+    rev: u64,
+    /// How the supervised processes were brought in line with the revision
+    plan: Vec<PlannedChange>,
+}
+
+const MAX_REVISIONS: usize = 500;
+// Older revisions are dropped in batches, so the file isn't rewritten on every change once it is full
+const COMPACT_EVERY: usize = 100;
+
+// Where a revision's line is in the file
+struct IndexEntry {
+    rev: u64,
+    offset: u64,
+    #[cfg_attr(not(feature = "server"), allow(dead_code))]
+    len: u64,
+}
+
+pub struct ConfigHistory {
+    // None when the config store keeps no history
+    path: Option<PathBuf>,
+    // Oldest first, read from the file on first use
+    index: Mutex<Option<Vec<IndexEntry>>>,
+    // Timestamps the revisions
+    clock: Arc<dyn Clock>,
+}
+
+impl ConfigHistory {
+    pub fn new(path: Option<PathBuf>, clock: Arc<dyn Clock>) -> Self {
+        Self { path, index: Mutex::new(None), clock }
+    }
+
+    fn index(&self, path: &Path) -> std::io::Result<MutexGuard<'_, Option<Vec<IndexEntry>>>> {
+        let mut index = self.index.lock().unwrap();
+        if index.is_none() {
+            *index = Some(read_index(path)?);
+        }
+        Ok(index)
+    }
+
+    // Revisions newest first, and how many there are
+    #[cfg(feature = "server")]
+    pub fn page(&self, skip: usize, take: usize) -> std::io::Result<(Vec<Revision>, usize)> {
+        let Some(path) = &self.path else {
+            return Ok((Vec::new(), 0));
+        };
+        let index = self.index(path)?;
+        let index = index.as_deref().unwrap_or_default();
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+            Err(e) => return Err(e),
+        };
+        let mut revisions = Vec::new();
+        for entry in index.iter().rev().skip(skip).take(take) {
+            revisions.push(read_revision(&mut file, entry)?);
+        }
+        Ok((revisions, index.len()))
+    }
+
+    #[cfg(feature = "server")]
+    pub fn get(&self, rev: u64) -> std::io::Result<Option<Revision>> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        let index = self.index(path)?;
+        let Some(entry) = index.as_deref().unwrap_or_default().iter().find(|entry| entry.rev == rev) else {
+            return Ok(None);
+        };
+        read_revision(&mut File::open(path)?, entry).map(Some)
+    }
+
+    // Called by the config manager under its write lock, so revisions are numbered in order
+    pub fn record(&self, actor: &str, old: &[ProcessConfig], new: &[ProcessConfig]) {
+        let changes = diff(old, new);
+        let Some(path) = &self.path else {
+            return;
+        };
+        if changes.is_empty() {
+            return;
+        }
+        let mut index = match self.index(path) {
+            Ok(index) => index,
+            Err(e) => {
+                eprintln!("Failed to read config history: {}", e);
+                return;
+            }
+        };
+        let index = index.get_or_insert_with(Vec::new);
+        let mut rev = index.last().map(|entry| entry.rev).unwrap_or(0);
+
+        // The config from before the history started is kept as the first revision, so it can be rolled back to
+        if rev == 0 && !old.is_empty() {
+            rev += 1;
+            append(path, index, &Revision {
+                rev,
+                timestamp: self.clock.now(),
+                actor: "exeio (config before history was kept)".to_string(),
+                changes: diff(&[], old),
+                configs: old.to_vec(),
+            });
+        }
+
+        rev += 1;
+        append(path, index, &Revision {
+            rev,
+            timestamp: self.clock.now(),
+            actor: actor.to_string(),
+            changes,
+            configs: new.to_vec(),
+        });
+
+        if index.len() >= MAX_REVISIONS + COMPACT_EVERY
+            && let Err(e) = compact(path, index) {
+                eprintln!("Failed to compact config history: {}", e);
+        }
+    }
+}
+
+fn read_index(path: &Path) -> std::io::Result<Vec<IndexEntry>> {
+    // Only the revision number is needed from each line
+    #[derive(Deserialize)]
+    struct RevisionNumber {
+        rev: u64,
+    }
+
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut reader = BufReader::new(file);
+    let mut index = Vec::new();
+    let mut offset = 0;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let len = reader.read_until(b'\n', &mut line)? as u64;
+        if len == 0 {
+            break;
+        }
+        // Skip partial lines left by a crash mid-write
+        if let Ok(revision) = serde_json::from_slice::<RevisionNumber>(&line) {
+            index.push(IndexEntry { rev: revision.rev, offset, len });
+        }
+        offset += len;
+    }
+    Ok(index)
+}
+
+#[cfg(feature = "server")]
+fn read_revision(file: &mut File, entry: &IndexEntry) -> std::io::Result<Revision> {
+    let mut line = vec![0u8; entry.len as usize];
+    file.seek(SeekFrom::Start(entry.offset))?;
+    file.read_exact(&mut line)?;
+    serde_json::from_slice(&line).map_err(std::io::Error::other)
+}
+
+fn append(path: &Path, index: &mut Vec<IndexEntry>, revision: &Revision) {
+    let line = match serde_json::to_string(revision) {
+        Ok(line) => format!("{}\n", line),
+        Err(e) => {
+            eprintln!("Failed to serialize config revision: {}", e);
+            return;
+        }
+    };
+    let written = OpenOptions::new().create(true).append(true).open(path).and_then(|mut file| {
+        let offset = file.metadata()?.len();
+        file.write_all(line.as_bytes())?;
+        Ok(offset)
+    });
+    match written {
+        Ok(offset) => index.push(IndexEntry { rev: revision.rev, offset, len: line.len() as u64 }),
+        Err(e) => eprintln!("Failed to write config history: {}", e),
+    }
+}
+
+// Rewrites the file with only the last MAX_REVISIONS revisions
+fn compact(path: &Path, index: &mut Vec<IndexEntry>) -> std::io::Result<()> {
+    let dropped = index.len().saturating_sub(MAX_REVISIONS);
+    let Some(start) = index.get(dropped).map(|entry| entry.offset) else {
+        return Ok(());
+    };
+    let mut kept = Vec::new();
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    file.read_to_end(&mut kept)?;
+
+    let temp_path = path.with_extension("jsonl.tmp");
+    std::fs::write(&temp_path, &kept)?;
+    std::fs::rename(&temp_path, path)?;
+
+    index.drain(..dropped);
+    for entry in index.iter_mut() {
+        entry.offset -= start;
+    }
+    Ok(())
+}
+
+fn diff(old: &[ProcessConfig], new: &[ProcessConfig]) -> Vec<ProcessChange> {
+    let mut changes = Vec::new();
+    for config in new {
+        match old.iter().find(|old_config| old_config.id == config.id) {
+            None => changes.push(ProcessChange { id: config.id.clone(), kind: ChangeKind::Added, fields: Vec::new() }),
+            Some(old_config) => {
+                let fields = crate::config_diff(old_config, config);
+                if !fields.is_empty() {
+                    changes.push(ProcessChange { id: config.id.clone(), kind: ChangeKind::Changed, fields });
+                }
+            }
+        }
+    }
+    for config in old.iter().filter(|config| !new.iter().any(|new_config| new_config.id == config.id)) {
+        changes.push(ProcessChange { id: config.id.clone(), kind: ChangeKind::Removed, fields: Vec::new() });
+    }
+    changes
+}
+
+#[cfg(feature = "server")]
+pub async fn handle_history(query: HistoryQuery, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(50).clamp(1, 1000);
+
+    // Newest first, like /logs and /audit
+    let (revisions, total_revisions) = supervisor
+        .saved()
+        .history()
+        .page((page - 1) * page_size, page_size)
+        .map_err(|e| warp::reject::custom(ApiError::Io(format!("Failed to read config history: {}", e))))?;
+    let revisions = revisions
+        .into_iter()
+        .map(|revision| RevisionSummary {
+            rev: revision.rev,
+            timestamp: revision.timestamp,
+            actor: revision.actor,
+            changes: revision.changes,
+        })
+        .collect();
+
+    Ok(warp::reply::json(&HistoryPage { success: true, page, page_size, total_revisions, revisions }))
+}
+
+#[cfg(feature = "server")]
+pub async fn handle_revision(rev: u64, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    match supervisor.saved().history().get(rev) {
+        Ok(Some(revision)) => Ok(warp::reply::json(&revision)),
+        Ok(None) => Err(warp::reject::custom(ApiError::RevisionNotFound(rev))),
+        Err(e) => Err(warp::reject::custom(ApiError::Io(format!("Failed to read config history: {}", e)))),
+    }
+}
+
+#[cfg(feature = "server")]
+// Saves the revision's config as a new revision, then restarts, starts and removes saved processes to match it
+pub async fn handle_rollback(
+    rev: u64,
+    audit: AuditContext,
+    params: RollbackParams,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let dry_run = params.dry_run.unwrap_or(false);
+    let audit_details = serde_json::json!({ "rev": rev, "dry_run": dry_run });
+
+    let revision = match supervisor.saved().history().get(rev) {
+        Ok(Some(revision)) => revision,
+        Ok(None) => {
+            let error = ApiError::RevisionNotFound(rev);
+            audit.record_error(None, Some(audit_details), &error);
+            return Err(warp::reject::custom(error));
+        }
+        Err(e) => {
+            let error = ApiError::Io(format!("Failed to read config history: {}", e));
+            audit.record_error(None, Some(audit_details), &error);
+            return Err(warp::reject::custom(error));
+        }
+    };
+
+    // Only saved processes are rolled back; transient ones stay unless the revision has one with the same id
+    let saved: HashSet<String> = supervisor.saved().load_configs().into_iter().map(|config| config.id).collect();
+    let plan = config_file::plan(&saved, &revision.configs, &supervisor);
+
+    if !dry_run {
+        let actor = format!("{} (rollback to revision {})", audit.caller.name, rev);
+        if let Err(e) = supervisor.saved().save_configs(&revision.configs, &actor) {
+            let error = ApiError::Io(format!("Failed to save config: {}", e));
+            audit.record_error(None, Some(audit_details), &error);
+            return Err(warp::reject::custom(error));
+        }
+        let reason = format!("Config rolled back to revision {} by '{}'", rev, audit.caller.name);
+        config_file::apply_plan(&plan, &revision.configs, &supervisor, &reason, &actor).await;
+    }
+
+    let response = ApiResponse {
+        success: true,
+        message: format!("{} revision {}: {}",
+            if dry_run { "Dry run of rolling back to" } else { "Rolled back to" }, rev, config_file::plan_summary(&plan)),
+    };
+    let mut audit_details = audit_details;
+    audit_details["plan"] = serde_json::json!(plan);
+    audit.record(None, Some(audit_details), &response);
+
+    Ok(warp::reply::json(&RollbackResponse { success: true, message: response.message, dry_run, rev, plan }))
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    fn config(command: &str) -> ProcessConfig {
+        ProcessConfig {
+            id: "web".to_string(),
+            command: command.to_string(),
+            args: Vec::new(),
+            working_dir: None,
+            auto_restart: false,
+            log_file: "web.log".to_string(),
+            periodic: false,
+            period_seconds: None,
+            hooks: Default::default(),
+            log_rules: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn keeps_the_last_revisions_and_finds_them_by_offset() {
+        let path = std::env::temp_dir().join(format!("exeio-history-test-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let history = ConfigHistory::new(Some(path.clone()), Arc::new(crate::SystemClock));
+
+        let total = MAX_REVISIONS + COMPACT_EVERY + 10;
+        let mut previous = Vec::new();
+        for i in 1..=total {
+            let configs = vec![config(&format!("server-{}", i))];
+            history.record("test", &previous, &configs);
+            previous = configs;
+        }
+
+        // Compacted once, when it reached MAX_REVISIONS + COMPACT_EVERY
+        let (latest, count) = history.page(0, 2).unwrap();
+        assert_eq!(count, MAX_REVISIONS + 10);
+        assert_eq!(latest.iter().map(|revision| revision.rev).collect::<Vec<_>>(), vec![total as u64, total as u64 - 1]);
+        assert!(history.get(1).unwrap().is_none());
+        let oldest = (total - count + 1) as u64;
+        assert_eq!(history.get(oldest).unwrap().unwrap().configs[0].command, format!("server-{}", oldest));
+
+        // A fresh index read from the compacted file agrees
+        let reread = ConfigHistory::new(Some(path.clone()), Arc::new(crate::SystemClock));
+        assert_eq!(reread.page(0, 1).unwrap().1, count);
+        assert_eq!(reread.get(total as u64).unwrap().unwrap().configs[0].command, format!("server-{}", total));
+        let _ = std::fs::remove_file(&path);
+    }
+}
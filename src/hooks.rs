@@ -0,0 +1,303 @@
+// Lifecycle hooks: shell commands run around a process's start, stop and exit, from the process's own config
+// and the config file's [hooks] section. Their output goes to the process log tagged HOOK
+use std::process::Stdio;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command as TokioCommand;
+
+use crate::error::FieldError;
+use crate::{ProcessConfig, Supervisor};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// Runs before each start; if it fails the process isn't started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_start: Option<String>,
+    /// Runs once the process has been started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_start: Option<String>,
+    /// Runs before a running process is killed by a stop, restart or removal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_stop: Option<String>,
+    /// Runs after a process killed by a stop, restart or removal has exited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_stop: Option<String>,
+    /// Runs whenever the process exits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_exit: Option<String>,
+    /// Runs when the process exits on its own with a non-zero code or a signal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_crash: Option<String>,
+    /// Runs when an auto-restart is scheduled or the process is restarted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_restart: Option<String>,
+    /// How long a hook may run before it is killed and counts as failed (default 30)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hook {
+    PreStart,
+    PostStart,
+    PreStop,
+    PostStop,
+    OnExit,
+    OnCrash,
+    OnRestart,
+}
+
+impl Hook {
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::PreStart => "pre_start",
+            Hook::PostStart => "post_start",
+            Hook::PreStop => "pre_stop",
+            Hook::PostStop => "post_stop",
+            Hook::OnExit => "on_exit",
+            Hook::OnCrash => "on_crash",
+            Hook::OnRestart => "on_restart",
+        }
+    }
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        *self == Hooks::default()
+    }
+
+    fn command(&self, hook: Hook) -> Option<&str> {
+        let command = match hook {
+            Hook::PreStart => &self.pre_start,
+            Hook::PostStart => &self.post_start,
+            Hook::PreStop => &self.pre_stop,
+            Hook::PostStop => &self.post_stop,
+            Hook::OnExit => &self.on_exit,
+            Hook::OnCrash => &self.on_crash,
+            Hook::OnRestart => &self.on_restart,
+        };
+        command.as_deref().filter(|command| !command.trim().is_empty())
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_seconds.unwrap_or(30))
+    }
+
+    // Every invalid field, named `<prefix>.<field>`
+    pub fn validate(&self, prefix: &str) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.timeout_seconds == Some(0) {
+            errors.push(FieldError::new(&format!("{}.timeout_seconds", prefix), "out_of_range", "timeout_seconds must be greater than zero"));
+        }
+        errors
+    }
+}
+
+// What a hook is told about the process, as EXEIO_* environment variables
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    pub pid: Option<u32>,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub run_number: u64,
+    pub reason: Option<String>,
+}
+
+impl HookContext {
+    pub fn run(run_number: u64) -> Self {
+        Self { run_number, ..Self::default() }
+    }
+
+    pub fn exited(pid: u32, run_number: u64, exit_status: Option<std::process::ExitStatus>) -> Self {
+        #[cfg(unix)]
+        let signal = exit_status.and_then(|status| std::os::unix::process::ExitStatusExt::signal(&status));
+        #[cfg(not(unix))]
+        let signal = None;
+        Self {
+            pid: Some(pid),
+            exit_code: exit_status.and_then(|status| status.code()),
+            signal,
+            run_number,
+            reason: None,
+        }
+    }
+
+    // Exited with a non-zero code or was killed by a signal
+    pub fn crashed(&self) -> bool {
+        self.signal.is_some() || self.exit_code.is_some_and(|exit_code| exit_code != 0)
+    }
+}
+
+// Sets the config file's [hooks] section, run for every process before its own hooks
+#[cfg(feature = "server")]
+pub fn configure(supervisor: &Supervisor, hooks: Hooks) {
+    *supervisor.global_hooks().write().unwrap() = std::sync::Arc::new(hooks);
+}
+
+// Runs the global and then the process's hook, stopping at the first that fails
+pub async fn run(supervisor: &Supervisor, hook: Hook, config: &ProcessConfig, context: &HookContext) -> Result<(), String> {
+    let global = supervisor.global_hooks().read().unwrap().clone();
+    for hooks in [global.as_ref(), &config.hooks] {
+        if let Some(command) = hooks.command(hook) {
+            // Boxed, as the handlers that start processes would otherwise carry the whole child future
+            Box::pin(run_command(supervisor, hook, command, hooks.timeout(), config, context)).await?;
+        }
+    }
+    Ok(())
+}
+
+// Runs a hook without waiting for it; failures are only logged
+pub fn spawn(supervisor: &Supervisor, hooks: Vec<Hook>, config: ProcessConfig, context: HookContext) {
+    let supervisor = supervisor.clone();
+    tokio::spawn(async move {
+        for hook in hooks {
+            let _ = run(&supervisor, hook, &config, &context).await;
+        }
+    });
+}
+
+async fn run_command(supervisor: &Supervisor, hook: Hook, command: &str, timeout: Duration, config: &ProcessConfig, context: &HookContext) -> Result<(), String> {
+    #[cfg(unix)]
+    let mut cmd = {
+        let mut cmd = TokioCommand::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    #[cfg(not(unix))]
+    let mut cmd = {
+        let mut cmd = TokioCommand::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+    cmd.env("EXEIO_HOOK", hook.name())
+        .env("EXEIO_PROCESS_ID", &config.id)
+        .env("EXEIO_RUN_NUMBER", context.run_number.to_string())
+        .env("EXEIO_LOG_FILE", &config.log_file)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // A hook that times out is dropped, which kills it
+        .kill_on_drop(true);
+    if let Some(pid) = context.pid {
+        cmd.env("EXEIO_PID", pid.to_string());
+    }
+    if let Some(exit_code) = context.exit_code {
+        cmd.env("EXEIO_EXIT_CODE", exit_code.to_string());
+    }
+    if let Some(signal) = context.signal {
+        cmd.env("EXEIO_SIGNAL", signal.to_string());
+    }
+    if let Some(reason) = &context.reason {
+        cmd.env("EXEIO_RESTART_REASON", reason);
+    }
+    if let Some(dir) = &config.working_dir {
+        cmd.current_dir(dir);
+    }
+
+    let result = match tokio::time::timeout(timeout, cmd.output()).await {
+        Err(_) => Err(format!("timed out after {}s", timeout.as_secs())),
+        Ok(Err(e)) => Err(format!("could not be run: {}", e)),
+        Ok(Ok(output)) => {
+            for (stream, tag) in [(&output.stdout, "STDOUT"), (&output.stderr, "STDERR")] {
+                for line in String::from_utf8_lossy(stream).lines() {
+                    log(supervisor, config, &format!("HOOK {} {}: {}", hook.name(), tag, line));
+                }
+            }
+            if output.status.success() {
+                Ok(())
+            } else {
+                Err(format!("exited with {}", output.status))
+            }
+        }
+    };
+    // Hook commands may carry secrets, like process arguments
+    let command = crate::redact::redact_command(command);
+    match &result {
+        Ok(()) => log(supervisor, config, &format!("HOOK {}: `{}` succeeded", hook.name(), command)),
+        Err(e) => {
+            log(supervisor, config, &format!("HOOK {}: `{}` failed: {}", hook.name(), command, e));
+            eprintln!("[{}] {} hook failed: {}", config.id, hook.name(), e);
+        }
+    }
+    result
+}
+
+fn log(supervisor: &Supervisor, config: &ProcessConfig, message: &str) {
+    let entry = format!("[{}] {}\n", supervisor.now().format("%Y-%m-%d %H:%M:%S"), message);
+    let _ = supervisor.logger().append_process_log(&config.log_file, &entry);
+}
+
+#[cfg(all(test, unix, feature = "server"))]
+mod tests {
+    use super::*;
+
+    fn config(test: &str, hooks: Hooks) -> ProcessConfig {
+        let log_file = std::env::temp_dir().join(format!("exeio-hooks-{}-{}.log", test, std::process::id()));
+        let _ = std::fs::remove_file(&log_file);
+        ProcessConfig {
+            id: format!("hooks-{}", test),
+            command: "true".to_string(),
+            args: Vec::new(),
+            working_dir: None,
+            auto_restart: false,
+            log_file: log_file.to_string_lossy().into_owned(),
+            periodic: false,
+            period_seconds: None,
+            hooks,
+            log_rules: Vec::new(),
+        }
+    }
+
+    fn read_log(supervisor: &Supervisor, config: &ProcessConfig) -> String {
+        supervisor.logger().flush_process_logs();
+        std::fs::read_to_string(&config.log_file).unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn a_timed_out_hook_is_killed_and_logged() {
+        let supervisor = Supervisor::builder().config_store(crate::MemoryConfigStore::default()).build();
+        let pid_file = std::env::temp_dir().join(format!("exeio-hooks-pid-{}", std::process::id()));
+        let hooks = Hooks {
+            pre_start: Some(format!("echo $$ > {}; exec sleep 30", pid_file.display())),
+            timeout_seconds: Some(1),
+            ..Hooks::default()
+        };
+        let config = config("timeout", hooks);
+        supervisor.logger().open_process_log(&config.log_file).unwrap();
+
+        let result = run(&supervisor, Hook::PreStart, &config, &HookContext::run(1)).await;
+        assert_eq!(result, Err("timed out after 1s".to_string()));
+        assert!(read_log(&supervisor, &config).contains("HOOK pre_start: `echo $$ >"));
+        assert!(read_log(&supervisor, &config).contains("failed: timed out after 1s"));
+
+        let pid: u32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        // Gone, or a zombie waiting to be reaped
+        assert_eq!(crate::runtime::start_time(pid), None);
+        let _ = std::fs::remove_file(&pid_file);
+        let _ = std::fs::remove_file(&config.log_file);
+    }
+
+    #[tokio::test]
+    async fn logged_hook_commands_are_redacted() {
+        let supervisor = Supervisor::builder().config_store(crate::MemoryConfigStore::default()).build();
+        let hooks = Hooks {
+            post_start: Some("true --password hunter2".to_string()),
+            on_exit: Some("false --token=abc123".to_string()),
+            ..Hooks::default()
+        };
+        let config = config("redact", hooks);
+        supervisor.logger().open_process_log(&config.log_file).unwrap();
+
+        assert!(run(&supervisor, Hook::PostStart, &config, &HookContext::run(1)).await.is_ok());
+        assert!(run(&supervisor, Hook::OnExit, &config, &HookContext::run(1)).await.is_err());
+        let log = read_log(&supervisor, &config);
+        assert!(log.contains("HOOK post_start: `true --password ***` succeeded"));
+        assert!(log.contains("HOOK on_exit: `false --token=***` failed"));
+        assert!(!log.contains("hunter2") && !log.contains("abc123"));
+        let _ = std::fs::remove_file(&config.log_file);
+    }
+}
@@ -0,0 +1,695 @@
+// Converts Procfiles, supervisord [program:x] sections and systemd service units into process configs,
+// for `exeio import` and POST /import
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::AuditContext;
+use crate::error::{ApiError, FieldError};
+use crate::{ApiResponse, FileConfigStore, Hooks, ProcessConfig, SafeConfigManager, Supervisor, SystemClock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Procfile,
+    Supervisord,
+    Systemd,
+}
+
+// A line that was read but could not be carried over as-is
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Unsupported {
+    pub process: Option<String>,
+    pub line: usize,
+    pub directive: String,
+    pub reason: String,
+}
+
+pub struct ParsedImport {
+    pub configs: Vec<ProcessConfig>,
+    pub unsupported: Vec<Unsupported>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ImportRequest {
+    /// procfile, supervisord or systemd; guessed from the content if missing
+    format: Option<ImportFormat>,
+    /// The file contents
+    content: String,
+    /// Process id for a systemd unit, which has no name inside the file
+    name: Option<String>,
+    #[serde(default)]
+    save_for_next_run: bool,
+    /// Only parse and report, add nothing
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Added,
+    // A process with this id already exists and was left alone
+    Exists,
+    // dry_run was set
+    NotAdded,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ImportedProcess {
+    status: ImportStatus,
+    #[serde(flatten)]
+    config: ProcessConfig,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ImportResponse {
+    success: bool,
+    message: String,
+    format: ImportFormat,
+    processes: Vec<ImportedProcess>,
+    unsupported: Vec<Unsupported>,
+}
+
+// Guesses the format from the file name, then from the contents
+pub fn detect_format(content: &str, file_name: Option<&str>) -> ImportFormat {
+    if let Some(name) = file_name {
+        if name.ends_with(".service") {
+            return ImportFormat::Systemd;
+        }
+        if name.starts_with("Procfile") {
+            return ImportFormat::Procfile;
+        }
+        if name.ends_with(".conf") || name.ends_with(".ini") {
+            return ImportFormat::Supervisord;
+        }
+    }
+    if content.contains("[program:") {
+        ImportFormat::Supervisord
+    } else if content.contains("[Service]") {
+        ImportFormat::Systemd
+    } else {
+        ImportFormat::Procfile
+    }
+}
+
+pub fn parse(format: ImportFormat, content: &str, name: Option<&str>) -> Result<ParsedImport, Vec<FieldError>> {
+    let mut errors = Vec::new();
+    let mut parsed = ParsedImport { configs: Vec::new(), unsupported: Vec::new() };
+
+    match format {
+        ImportFormat::Procfile => parse_procfile(content, &mut parsed, &mut errors),
+        ImportFormat::Supervisord => parse_supervisord(content, &mut parsed, &mut errors),
+        ImportFormat::Systemd => match name {
+            Some(name) => parse_systemd(content, name, &mut parsed, &mut errors),
+            None => errors.push(FieldError::new("name", "required", "A process id is needed for a systemd unit")),
+        },
+    }
+
+    let mut seen = HashSet::new();
+    for config in &parsed.configs {
+        if !seen.insert(config.id.as_str()) {
+            errors.push(FieldError::new(&format!("processes.{}", config.id), "duplicate", format!("Process '{}' is defined more than once", config.id)));
+        }
+        for mut error in crate::validate_process_fields(&config.id, &config.command, config.periodic, config.period_seconds, &config.hooks, &config.log_rules) {
+            error.field = format!("processes.{}.{}", config.id, error.field);
+            errors.push(error);
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(parsed)
+}
+
+fn new_config(id: &str, words: Vec<String>, environment: Vec<(String, String)>) -> ProcessConfig {
+    // exeio has no environment setting, so variables are passed through env(1)
+    let mut words = words.into_iter();
+    let (command, args) = if environment.is_empty() {
+        (words.next().unwrap_or_default(), words.collect())
+    } else {
+        let args = environment
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .chain(words)
+            .collect();
+        ("env".to_string(), args)
+    };
+    ProcessConfig {
+        id: id.to_string(),
+        command,
+        args,
+        working_dir: None,
+        auto_restart: false,
+        log_file: crate::get_process_log_path(id).to_string_lossy().into_owned(),
+        periodic: false,
+        period_seconds: None,
+        hooks: Hooks::default(),
+        log_rules: Vec::new(),
+    }
+}
+
+// `name: command`, run through sh like foreman does so $PORT and friends expand
+fn parse_procfile(content: &str, parsed: &mut ParsedImport, errors: &mut Vec<FieldError>) {
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once(':') {
+            Some((name, command)) if !name.trim().is_empty() && !name.contains(char::is_whitespace) => {
+                let words = vec!["sh".to_string(), "-c".to_string(), command.trim().to_string()];
+                parsed.configs.push(new_config(name.trim(), words, Vec::new()));
+            }
+            _ => errors.push(FieldError::new(&format!("line {}", index + 1), "invalid", "Expected 'name: command'")),
+        }
+    }
+}
+
+// INI sections as (line number, section name, [(line number, key, value)])
+type Section = (usize, String, Vec<(usize, String, String)>);
+
+// How a value goes on over several lines
+#[derive(Clone, Copy, PartialEq)]
+enum Continuation {
+    // systemd: the line ends with a backslash
+    Backslash,
+    // supervisord (Python's configparser): the next lines are indented
+    Indented,
+}
+
+fn parse_ini(content: &str, continuation: Continuation) -> (Vec<Section>, Vec<usize>) {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut invalid_lines = Vec::new();
+    let mut pending = String::new();
+    let mut pending_line = 0;
+
+    for (index, raw) in content.lines().enumerate() {
+        if continuation == Continuation::Indented
+            && raw.starts_with(char::is_whitespace)
+            && !raw.trim().is_empty()
+            && !raw.trim_start().starts_with(['#', ';'])
+            && let Some((_, _, value)) = sections.last_mut().and_then(|(_, _, entries)| entries.last_mut()) {
+                value.push(' ');
+                value.push_str(raw.trim());
+                continue;
+        }
+        if pending.is_empty() {
+            pending_line = index + 1;
+        }
+        if continuation == Continuation::Backslash
+            && let Some(continued) = raw.strip_suffix('\\') {
+                pending.push_str(continued);
+                pending.push(' ');
+                continue;
+        }
+        pending.push_str(raw);
+        let line = std::mem::take(&mut pending);
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            sections.push((pending_line, section.trim().to_string(), Vec::new()));
+            continue;
+        }
+        match (line.split_once('='), sections.last_mut()) {
+            (Some((key, value)), Some((_, _, entries))) => {
+                entries.push((pending_line, key.trim().to_string(), value.trim().to_string()));
+            }
+            _ => invalid_lines.push(pending_line),
+        }
+    }
+    (sections, invalid_lines)
+}
+
+fn parse_supervisord(content: &str, parsed: &mut ParsedImport, errors: &mut Vec<FieldError>) {
+    let (sections, invalid_lines) = parse_ini(content, Continuation::Indented);
+    for line in invalid_lines {
+        errors.push(FieldError::new(&format!("line {}", line), "invalid", "Expected [section] or key=value"));
+    }
+
+    for (section_line, section, entries) in sections {
+        let Some(name) = section.strip_prefix("program:") else {
+            parsed.unsupported.push(Unsupported {
+                process: None,
+                line: section_line,
+                directive: format!("[{}]", section),
+                reason: "Only [program:x] sections are imported".to_string(),
+            });
+            continue;
+        };
+
+        let mut words = None;
+        let mut environment = Vec::new();
+        let mut working_dir = None;
+        let mut auto_restart = true;
+        let mut log_file = None;
+        let mut unsupported = |line: usize, key: &str, value: &str, reason: &str| {
+            parsed.unsupported.push(Unsupported {
+                process: Some(name.to_string()),
+                line,
+                directive: format!("{}={}", key, value),
+                reason: reason.to_string(),
+            });
+        };
+
+        for (line, key, value) in entries {
+            // supervisord allows ` ;` comments after a value
+            let value = value.split(" ;").next().unwrap_or_default().trim().to_string();
+            match key.as_str() {
+                "command" => match split_command(&value) {
+                    Ok(split) => words = Some(split),
+                    Err(e) => errors.push(FieldError::new(&format!("processes.{}.command", name), "invalid", e)),
+                },
+                "directory" => working_dir = Some(value),
+                "environment" => match parse_supervisord_environment(&value) {
+                    Ok(variables) => environment = variables,
+                    Err(e) => errors.push(FieldError::new(&format!("processes.{}.environment", name), "invalid", e)),
+                },
+                "autorestart" => match value.to_lowercase().as_str() {
+                    "true" => auto_restart = true,
+                    "false" => auto_restart = false,
+                    "unexpected" => {
+                        auto_restart = true;
+                        unsupported(line, &key, &value, "exeio has no expected exit codes, the process is restarted after any exit");
+                    }
+                    _ => errors.push(FieldError::new(&format!("processes.{}.autorestart", name), "invalid", "Expected true, false or unexpected")),
+                },
+                "stdout_logfile" if value == "AUTO" => {}
+                "stdout_logfile" if value == "NONE" => unsupported(line, &key, &value, "exeio always keeps a log, the default one is used"),
+                "stdout_logfile" => log_file = Some(value),
+                "redirect_stderr" if value == "true" => {}
+                "redirect_stderr" => unsupported(line, &key, &value, "exeio always writes stderr to the same log as stdout"),
+                "autostart" if value == "true" => {}
+                "user" => unsupported(line, &key, &value, "Processes run as the user exeio runs as"),
+                "startsecs" => match value.parse::<u64>() {
+                    // What exeio does anyway: the process counts as started as soon as it is spawned
+                    Ok(0) => {}
+                    Ok(seconds) => unsupported(line, &key, &value,
+                        &format!("exeio has no {}s startup grace period, restarts are delayed by its backoff instead", seconds)),
+                    Err(_) => errors.push(FieldError::new(&format!("processes.{}.startsecs", name), "invalid", "Expected a number of seconds")),
+                },
+                _ => unsupported(line, &key, &value, "Not supported by exeio"),
+            }
+        }
+
+        let Some(words) = words else {
+            errors.push(FieldError::new(&format!("processes.{}.command", name), "required", "[program:x] sections need a command"));
+            continue;
+        };
+        let mut config = new_config(name, words, environment);
+        config.working_dir = working_dir;
+        config.auto_restart = auto_restart;
+        if let Some(log_file) = log_file {
+            config.log_file = log_file;
+        }
+        parsed.configs.push(config);
+    }
+}
+
+// KEY="value",KEY2=value2
+fn parse_supervisord_environment(value: &str) -> Result<Vec<(String, String)>, String> {
+    let mut pairs = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for c in value.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ',') => pairs.push(std::mem::take(&mut current)),
+            (None, c) => current.push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("Unterminated quote in environment".to_string());
+    }
+    pairs.push(current);
+
+    pairs
+        .into_iter()
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => Ok((key.trim().to_string(), value.to_string())),
+            None => Err(format!("Expected KEY=value in environment, got '{}'", pair.trim())),
+        })
+        .collect()
+}
+
+fn parse_systemd(content: &str, name: &str, parsed: &mut ParsedImport, errors: &mut Vec<FieldError>) {
+    let (sections, invalid_lines) = parse_ini(content, Continuation::Backslash);
+    for line in invalid_lines {
+        errors.push(FieldError::new(&format!("line {}", line), "invalid", "Expected [Section] or Key=Value"));
+    }
+
+    let mut words = None;
+    let mut environment = Vec::new();
+    let mut working_dir = None;
+    let mut auto_restart = false;
+    let mut unsupported = |line: usize, key: &str, value: &str, reason: &str| {
+        parsed.unsupported.push(Unsupported {
+            process: Some(name.to_string()),
+            line,
+            directive: format!("{}={}", key, value),
+            reason: reason.to_string(),
+        });
+    };
+
+    for (_, section, entries) in sections {
+        for (line, key, value) in entries {
+            match (section.as_str(), key.as_str()) {
+                ("Unit", "Description" | "Documentation") | ("Install", _) => {}
+                ("Service", "ExecStart") if words.is_some() => {
+                    unsupported(line, &key, &value, "Only the first ExecStart is imported");
+                }
+                ("Service", "ExecStart") => {
+                    // `-`, `+`, `!` and `:` only change how systemd runs the command
+                    let command = value.trim_start_matches(['-', '+', '!', ':']);
+                    if command.starts_with('@') {
+                        unsupported(line, &key, &value, "Setting argv[0] with @ is not supported");
+                    }
+                    match split_command(command.trim_start_matches('@')) {
+                        Ok(split) => words = Some(split),
+                        Err(e) => errors.push(FieldError::new(&format!("processes.{}.command", name), "invalid", e)),
+                    }
+                }
+                ("Service", "WorkingDirectory") => working_dir = Some(value.trim_start_matches('-').to_string()),
+                ("Service", "Environment") => match split_command(&value) {
+                    Ok(assignments) => {
+                        for assignment in assignments {
+                            match assignment.split_once('=') {
+                                Some((key, value)) => environment.push((key.to_string(), value.to_string())),
+                                None => errors.push(FieldError::new(&format!("processes.{}.environment", name), "invalid",
+                                    format!("Expected KEY=value in Environment, got '{}'", assignment))),
+                            }
+                        }
+                    }
+                    Err(e) => errors.push(FieldError::new(&format!("processes.{}.environment", name), "invalid", e)),
+                },
+                ("Service", "Restart") => auto_restart = value != "no",
+                ("Service", "Type") if matches!(value.as_str(), "simple" | "exec") => {}
+                ("Service", "Type") => unsupported(line, &key, &value, "exeio supervises the started process itself, like Type=simple"),
+                ("Service", "User" | "Group") => unsupported(line, &key, &value, "Processes run as the user exeio runs as"),
+                _ => unsupported(line, &key, &value, "Not supported by exeio"),
+            }
+        }
+    }
+
+    match words {
+        Some(words) => {
+            let mut config = new_config(name, words, environment);
+            config.working_dir = working_dir;
+            config.auto_restart = auto_restart;
+            parsed.configs.push(config);
+        }
+        None => errors.push(FieldError::new(&format!("processes.{}.command", name), "required", "The unit has no ExecStart")),
+    }
+}
+
+// Splits a command line on whitespace, honouring single and double quotes and backslash escapes
+fn split_command(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                if let Some(escaped) = chars.next() {
+                    word.push(escaped);
+                    in_word = true;
+                }
+            }
+            (Some(_), c) => word.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        return Err(format!("Unterminated quote in '{}'", line));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+fn unsupported_line(unsupported: &Unsupported) -> String {
+    match &unsupported.process {
+        Some(process) => format!("{} (line {}): {}: {}", process, unsupported.line, unsupported.directive, unsupported.reason),
+        None => format!("line {}: {}: {}", unsupported.line, unsupported.directive, unsupported.reason),
+    }
+}
+
+// `exeio import`, which works on the saved config without a running supervisor
+pub fn run_cli(file: &Path, format: Option<ImportFormat>, name: Option<String>, save: bool) -> Result<(), String> {
+    let content = std::fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let file_name = file.file_name().map(|name| name.to_string_lossy().into_owned());
+    let format = format.unwrap_or_else(|| detect_format(&content, file_name.as_deref()));
+    let name = name.or_else(|| file.file_stem().map(|stem| stem.to_string_lossy().into_owned()));
+
+    let parsed = parse(format, &content, name.as_deref()).map_err(|errors| {
+        let fields: Vec<String> = errors.iter().map(|e| format!("  {}: {}", e.field, e.message)).collect();
+        format!("{} is not a valid {:?} file:\n{}", file.display(), format, fields.join("\n"))
+    })?;
+
+    println!("Imported {} process(es) from {} ({:?}):", parsed.configs.len(), file.display(), format);
+    let configs = SafeConfigManager::new(Arc::new(FileConfigStore::default()), Arc::new(SystemClock));
+    let saved: HashSet<String> = configs.load_configs().into_iter().map(|config| config.id).collect();
+    for config in &parsed.configs {
+        let mut line = format!("  {}: {} {}", config.id, config.command, crate::redact::redact_args(&config.args).join(" "));
+        if let Some(working_dir) = &config.working_dir {
+            line.push_str(&format!(" (in {})", working_dir));
+        }
+        if config.auto_restart {
+            line.push_str(" [auto-restart]");
+        }
+        if save && saved.contains(&config.id) {
+            line.push_str(" - already saved, skipped");
+        } else if save {
+            configs
+                .save_process_config(config, "exeio import")
+                .map_err(|e| format!("Failed to save process {}: {}", config.id, e))?;
+            line.push_str(" - saved");
+        }
+        println!("{}", line);
+    }
+
+    if !parsed.unsupported.is_empty() {
+        println!("Not imported:");
+        for unsupported in &parsed.unsupported {
+            println!("  {}", unsupported_line(unsupported));
+        }
+    }
+    if save {
+        println!("Saved to {}, they start with the supervisor", crate::get_config_path().display());
+    } else {
+        println!("Nothing saved, run again with --save to add them to {}", crate::get_config_path().display());
+    }
+    Ok(())
+}
+
+pub async fn handle_import(
+    audit: AuditContext,
+    req: ImportRequest,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let format = req.format.unwrap_or_else(|| detect_format(&req.content, None));
+    let audit_details = serde_json::json!({
+        "format": format,
+        "name": req.name,
+        "save_for_next_run": req.save_for_next_run,
+        "dry_run": req.dry_run,
+    });
+
+    let parsed = match parse(format, &req.content, req.name.as_deref()) {
+        Ok(parsed) => parsed,
+        Err(errors) => {
+            let error = ApiError::Validation(errors);
+            audit.record_error(None, Some(audit_details), &error);
+            return Err(warp::reject::custom(error));
+        }
+    };
+
+    if let Some(config) = parsed.configs.iter().find(|config| !audit.caller.can_access(&config.id)) {
+        let error = ApiError::Forbidden(format!("Key '{}' is not allowed to add process '{}'", audit.caller.name, config.id));
+        audit.record_error(Some(&config.id), Some(audit_details), &error);
+        return Err(warp::reject::custom(error));
+    }
+
+    let mut imported = Vec::new();
+    for config in parsed.configs {
+        let status = if supervisor.contains(&config.id) {
+            ImportStatus::Exists
+        } else if req.dry_run {
+            ImportStatus::NotAdded
+        } else {
+            if req.save_for_next_run
+                && let Err(e) = supervisor.saved().save_process_config(&config, &audit.caller.name) {
+                    let error = ApiError::Io(format!("Failed to save config for process {}: {}", config.id, e));
+                    audit.record_error(Some(&config.id), Some(audit_details), &error);
+                    return Err(warp::reject::custom(error));
+            }
+            supervisor.launch(config.clone()).await;
+            ImportStatus::Added
+        };
+        imported.push(ImportedProcess { status, config });
+    }
+
+    let count = |wanted: fn(&ImportStatus) -> bool| imported.iter().filter(|process| wanted(&process.status)).count();
+    let message = format!("{:?} import: {} added, {} already existed, {} not added (dry run), {} unsupported directive(s)",
+        format,
+        count(|status| matches!(status, ImportStatus::Added)),
+        count(|status| matches!(status, ImportStatus::Exists)),
+        count(|status| matches!(status, ImportStatus::NotAdded)),
+        parsed.unsupported.len());
+
+    let response = ApiResponse { success: true, message };
+    let ids: Vec<&str> = imported.iter().map(|process| process.config.id.as_str()).collect();
+    let mut audit_details = audit_details;
+    audit_details["processes"] = serde_json::json!(ids);
+    audit.record(None, Some(audit_details), &response);
+
+    Ok(warp::reply::json(&ImportResponse {
+        success: true,
+        message: response.message,
+        format,
+        processes: imported,
+        unsupported: parsed.unsupported,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Case {
+        name: &'static str,
+        content: &'static str,
+        // Each process as `id: command | arg | arg`, or the fields of the errors
+        expected: Result<Vec<&'static str>, Vec<&'static str>>,
+        unsupported: Vec<&'static str>,
+    }
+
+    fn check(format: ImportFormat, name: Option<&str>, cases: Vec<Case>) {
+        for case in cases {
+            match (parse(format, case.content, name), &case.expected) {
+                (Ok(parsed), Ok(expected)) => {
+                    let processes: Vec<String> = parsed
+                        .configs
+                        .iter()
+                        .map(|config| format!("{}: {}", config.id, std::iter::once(&config.command).chain(&config.args).cloned().collect::<Vec<_>>().join(" | ")))
+                        .collect();
+                    assert_eq!(&processes, expected, "{}", case.name);
+                    let unsupported: Vec<&str> = parsed.unsupported.iter().map(|unsupported| unsupported.directive.as_str()).collect();
+                    assert_eq!(unsupported, case.unsupported, "{}", case.name);
+                }
+                (Err(errors), Err(expected)) => {
+                    let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+                    assert_eq!(&fields, expected, "{}", case.name);
+                }
+                (result, _) => panic!("{}: expected {:?}, got {}", case.name, case.expected, if result.is_ok() { "processes" } else { "errors" }),
+            }
+        }
+    }
+
+    #[test]
+    fn procfile() {
+        check(ImportFormat::Procfile, None, vec![
+            Case { name: "command", content: "web: bundle exec rails s -p $PORT",
+                expected: Ok(vec!["web: sh | -c | bundle exec rails s -p $PORT"]), unsupported: vec![] },
+            Case { name: "quotes are left to sh", content: "web: node 'my server.js' \"a b\"",
+                expected: Ok(vec!["web: sh | -c | node 'my server.js' \"a b\""]), unsupported: vec![] },
+            Case { name: "colons in the command", content: "web: ./web --bind 0.0.0.0:80",
+                expected: Ok(vec!["web: sh | -c | ./web --bind 0.0.0.0:80"]), unsupported: vec![] },
+            Case { name: "comments and blank lines", content: "# dev only\n\nweb: ./web\n  # worker: ./worker\nworker: ./worker\n",
+                expected: Ok(vec!["web: sh | -c | ./web", "worker: sh | -c | ./worker"]), unsupported: vec![] },
+            Case { name: "no continuation lines", content: "web: ./web \\\n  --port 80",
+                expected: Err(vec!["line 2"]), unsupported: vec![] },
+            Case { name: "no name", content: "bundle exec rails s",
+                expected: Err(vec!["line 1"]), unsupported: vec![] },
+            Case { name: "name with a space", content: "my web: ./web",
+                expected: Err(vec!["line 1"]), unsupported: vec![] },
+            Case { name: "duplicate", content: "web: ./a\nweb: ./b",
+                expected: Err(vec!["processes.web"]), unsupported: vec![] },
+        ]);
+    }
+
+    #[test]
+    fn supervisord() {
+        check(ImportFormat::Supervisord, None, vec![
+            Case { name: "quoting", content: "[program:web]\ncommand=/usr/bin/python \"my app.py\" --name 'a b' c\\ d",
+                expected: Ok(vec!["web: /usr/bin/python | my app.py | --name | a b | c d"]), unsupported: vec![] },
+            Case { name: "environment quoting", content: "[program:web]\ncommand=./web\nenvironment=KEY=\"a,b\",OTHER='c d'",
+                expected: Ok(vec!["web: env | KEY=a,b | OTHER=c d | ./web"]), unsupported: vec![] },
+            Case { name: "comments", content: "; top\n[program:web]\n# own line\n  ; indented\ncommand=./web ; trailing\n",
+                expected: Ok(vec!["web: ./web"]), unsupported: vec![] },
+            Case { name: "continuation lines", content: "[program:web]\ncommand=./web\n    --port 80\nenvironment=A=\"1\",\n    B=2\n",
+                expected: Ok(vec!["web: env | A=1 | B=2 | ./web | --port | 80"]), unsupported: vec![] },
+            Case { name: "unsupported directives",
+                content: "[supervisord]\nlogfile=/var/log/supervisord.log\n[program:web]\ncommand=./web\nuser=www\nstartsecs=5\npriority=1\nautorestart=unexpected",
+                expected: Ok(vec!["web: ./web"]),
+                unsupported: vec!["[supervisord]", "user=www", "startsecs=5", "priority=1", "autorestart=unexpected"] },
+            Case { name: "startsecs=0 is what exeio does", content: "[program:web]\ncommand=./web\nstartsecs=0",
+                expected: Ok(vec!["web: ./web"]), unsupported: vec![] },
+            Case { name: "invalid startsecs", content: "[program:web]\ncommand=./web\nstartsecs=soon",
+                expected: Err(vec!["processes.web.startsecs"]), unsupported: vec![] },
+            Case { name: "invalid autorestart", content: "[program:web]\ncommand=./web\nautorestart=maybe",
+                expected: Err(vec!["processes.web.autorestart"]), unsupported: vec![] },
+            Case { name: "unterminated quote", content: "[program:web]\ncommand=./web \"oops",
+                expected: Err(vec!["processes.web.command", "processes.web.command"]), unsupported: vec![] },
+            Case { name: "missing command", content: "[program:web]\ndirectory=/srv/web",
+                expected: Err(vec!["processes.web.command"]), unsupported: vec![] },
+            Case { name: "key outside a section", content: "command=./web",
+                expected: Err(vec!["line 1"]), unsupported: vec![] },
+        ]);
+    }
+
+    #[test]
+    fn systemd() {
+        check(ImportFormat::Systemd, Some("web"), vec![
+            Case { name: "quoting", content: "[Service]\nExecStart=/usr/bin/web \"a b\" 'c d' e\\ f",
+                expected: Ok(vec!["web: /usr/bin/web | a b | c d | e f"]), unsupported: vec![] },
+            Case { name: "prefixes", content: "[Service]\nExecStart=-/usr/bin/web --x",
+                expected: Ok(vec!["web: /usr/bin/web | --x"]), unsupported: vec![] },
+            Case { name: "continuation lines", content: "[Service]\nExecStart=/usr/bin/web \\\n    --port 80 \\\n    --verbose\n",
+                expected: Ok(vec!["web: /usr/bin/web | --port | 80 | --verbose"]), unsupported: vec![] },
+            Case { name: "comments", content: "# unit\n[Unit]\nDescription=Web\n; note\n[Service]\nExecStart=/usr/bin/web\n[Install]\nWantedBy=multi-user.target",
+                expected: Ok(vec!["web: /usr/bin/web"]), unsupported: vec![] },
+            Case { name: "environment", content: "[Service]\nEnvironment=A=1 \"B=two words\"\nExecStart=/usr/bin/web",
+                expected: Ok(vec!["web: env | A=1 | B=two words | /usr/bin/web"]), unsupported: vec![] },
+            Case { name: "unsupported directives",
+                content: "[Service]\nType=forking\nUser=www\nExecStart=/usr/bin/web\nExecStart=/usr/bin/other\nLimitNOFILE=1024",
+                expected: Ok(vec!["web: /usr/bin/web"]),
+                unsupported: vec!["Type=forking", "User=www", "ExecStart=/usr/bin/other", "LimitNOFILE=1024"] },
+            Case { name: "argv[0]", content: "[Service]\nExecStart=@/usr/bin/web web-main",
+                expected: Ok(vec!["web: /usr/bin/web | web-main"]), unsupported: vec!["ExecStart=@/usr/bin/web web-main"] },
+            Case { name: "no ExecStart", content: "[Service]\nType=simple",
+                expected: Err(vec!["processes.web.command"]), unsupported: vec![] },
+            Case { name: "key outside a section", content: "ExecStart=/usr/bin/web",
+                expected: Err(vec!["line 1", "processes.web.command"]), unsupported: vec![] },
+        ]);
+    }
+}
@@ -0,0 +1,424 @@
+// Named API keys with scopes and optional process restrictions, stored hashed on disk
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ApiResponse;
+use crate::audit::AuditContext;
+use crate::error::{ApiError, FieldError};
+
+// Name reserved for the key given with --api-key (or generated at startup)
+pub const MASTER_KEY_NAME: &str = "master";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Logs,
+    Input,
+    Control,
+    Admin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub name: String,
+    pub key_hash: String,
+    pub scopes: Vec<Scope>,
+    // Process id globs this key may act on, empty means all processes
+    #[serde(default)]
+    pub processes: Vec<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub rotated_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+// The authenticated identity behind a request
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub processes: Vec<String>,
+}
+
+impl Caller {
+    pub fn admin(name: String) -> Self {
+        Self {
+            name,
+            scopes: vec![Scope::Admin],
+            processes: Vec::new(),
+        }
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&Scope::Admin) || self.scopes.contains(&scope)
+    }
+
+    pub fn can_access(&self, process_id: &str) -> bool {
+        self.processes.is_empty() || self.processes.iter().any(|pattern| glob_match(pattern, process_id))
+    }
+}
+
+pub struct KeyStore {
+    path: PathBuf,
+    master_key_hash: String,
+    keys: RwLock<Vec<ApiKeyRecord>>,
+}
+
+impl KeyStore {
+    // A store that exists but can't be read or parsed is an error, so the next save can't overwrite the keys in it
+    pub fn load(path: PathBuf, master_key: &str) -> Result<Self, String> {
+        let keys = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse key store {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read key store {}: {}", path.display(), e)),
+        };
+
+        Ok(Self {
+            path,
+            master_key_hash: hash_key(master_key),
+            keys: RwLock::new(keys),
+        })
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn authenticate(&self, provided_key: &str) -> Option<Caller> {
+        let provided_hash = hash_key(provided_key);
+        if constant_time_eq(provided_hash.as_bytes(), self.master_key_hash.as_bytes()) {
+            return Some(Caller::admin(MASTER_KEY_NAME.to_string()));
+        }
+
+        // Compare against every key so the time taken doesn't reveal which one matched
+        let keys = self.keys.read().unwrap();
+        let mut matched = None;
+        for key in keys.iter() {
+            if constant_time_eq(provided_hash.as_bytes(), key.key_hash.as_bytes()) && !key.revoked {
+                matched = Some(key);
+            }
+        }
+        matched.map(|k| Caller {
+            name: k.name.clone(),
+            scopes: k.scopes.clone(),
+            processes: k.processes.clone(),
+        })
+    }
+
+    pub fn list(&self) -> Vec<ApiKeyRecord> {
+        self.keys.read().unwrap().clone()
+    }
+
+    // Returns the new secret, which is only ever shown once
+    pub fn create(&self, name: &str, scopes: Vec<Scope>, processes: Vec<String>) -> Result<String, ApiError> {
+        let mut invalid_fields = Vec::new();
+        if name.trim().is_empty() {
+            invalid_fields.push(FieldError::new("name", "empty", "Key name cannot be empty or just whitespace"));
+        } else if name == MASTER_KEY_NAME {
+            invalid_fields.push(FieldError::new("name", "reserved", format!("Key name '{}' is reserved", MASTER_KEY_NAME)));
+        }
+        if scopes.is_empty() {
+            invalid_fields.push(FieldError::new("scopes", "empty", "A key needs at least one scope"));
+        }
+        if !invalid_fields.is_empty() {
+            return Err(ApiError::Validation(invalid_fields));
+        }
+
+        let mut keys = self.keys.write().unwrap();
+        if keys.iter().any(|k| k.name == name) {
+            return Err(ApiError::KeyExists(name.to_string()));
+        }
+
+        // Changes are saved before they take effect, so a failed save leaves the keys as they were
+        let mut updated = keys.clone();
+        let secret = crate::generate_api_key();
+        updated.push(ApiKeyRecord {
+            name: name.to_string(),
+            key_hash: hash_key(&secret),
+            scopes,
+            processes,
+            created_at: chrono::Utc::now(),
+            rotated_at: None,
+            revoked: false,
+        });
+        self.save(&updated)?;
+        *keys = updated;
+        Ok(secret)
+    }
+
+    pub fn revoke(&self, name: &str) -> Result<(), ApiError> {
+        let mut keys = self.keys.write().unwrap();
+        let mut updated = keys.clone();
+        let key = updated.iter_mut()
+            .find(|k| k.name == name)
+            .ok_or_else(|| ApiError::KeyNotFound(name.to_string()))?;
+        key.revoked = true;
+        self.save(&updated)?;
+        *keys = updated;
+        Ok(())
+    }
+
+    pub fn rotate(&self, name: &str) -> Result<String, ApiError> {
+        let mut keys = self.keys.write().unwrap();
+        let mut updated = keys.clone();
+        // Revoked keys can't be brought back by rotating them
+        let key = updated.iter_mut()
+            .find(|k| k.name == name && !k.revoked)
+            .ok_or_else(|| ApiError::KeyNotFound(name.to_string()))?;
+
+        let secret = crate::generate_api_key();
+        key.key_hash = hash_key(&secret);
+        key.rotated_at = Some(chrono::Utc::now());
+        self.save(&updated)?;
+        *keys = updated;
+        Ok(secret)
+    }
+
+    fn save(&self, keys: &[ApiKeyRecord]) -> Result<(), ApiError> {
+        // Same atomic write as the process config: temp file, then rename. Created owner-only, like the API key file
+        let temp_path = self.path.with_extension("json.tmp");
+        let json = serde_json::to_string_pretty(keys).map_err(|e| ApiError::Internal(e.to_string()))?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&temp_path)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
+            .and_then(|_| std::fs::rename(&temp_path, &self.path))
+            .map_err(|e| ApiError::Io(format!("Failed to save key store {}: {}", self.path.display(), e)))
+    }
+}
+
+pub fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Minimal glob matching supporting `*` and `?`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+pub fn get_keys_path() -> PathBuf {
+    crate::get_config_path().with_file_name("keys.json")
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateKeyRequest {
+    name: String,
+    scopes: Vec<Scope>,
+    /// Process id globs (`*`, `?`) the key may act on, empty for all
+    #[serde(default)]
+    processes: Vec<String>,
+}
+
+// A key as listed, without its hash
+#[derive(Serialize, JsonSchema)]
+pub struct KeyInfo {
+    name: String,
+    scopes: Vec<Scope>,
+    processes: Vec<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    rotated_at: Option<chrono::DateTime<chrono::Utc>>,
+    revoked: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct KeySecretResponse {
+    success: bool,
+    message: String,
+    name: String,
+    key: String,
+}
+
+pub async fn handle_list_keys(keys: Arc<KeyStore>) -> Result<impl warp::Reply, warp::Rejection> {
+    let key_list: Vec<KeyInfo> = keys.list()
+        .into_iter()
+        .map(|k| KeyInfo {
+            name: k.name,
+            scopes: k.scopes,
+            processes: k.processes,
+            created_at: k.created_at,
+            rotated_at: k.rotated_at,
+            revoked: k.revoked,
+        })
+        .collect();
+
+    Ok(warp::reply::json(&key_list))
+}
+
+pub async fn handle_create_key(audit: AuditContext, req: CreateKeyRequest, keys: Arc<KeyStore>) -> Result<impl warp::Reply, warp::Rejection> {
+    let audit_details = serde_json::json!({
+        "name": req.name,
+        "scopes": req.scopes,
+        "processes": req.processes
+    });
+
+    match keys.create(&req.name, req.scopes, req.processes) {
+        Ok(secret) => {
+            let message = format!("Key '{}' created. Store it now, it will not be shown again.", req.name);
+            audit.record(None, Some(audit_details), &ApiResponse { success: true, message: message.clone() });
+            Ok(warp::reply::json(&KeySecretResponse {
+                success: true,
+                message,
+                name: req.name,
+                key: secret,
+            }))
+        }
+        Err(error) => {
+            audit.record_error(None, Some(audit_details), &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+pub async fn handle_revoke_key(name: String, audit: AuditContext, keys: Arc<KeyStore>) -> Result<impl warp::Reply, warp::Rejection> {
+    let audit_details = serde_json::json!({ "name": name });
+
+    match keys.revoke(&name) {
+        Ok(()) => {
+            let response = ApiResponse {
+                success: true,
+                message: format!("Key '{}' revoked", name),
+            };
+            audit.record(None, Some(audit_details), &response);
+            Ok(warp::reply::json(&response))
+        }
+        Err(error) => {
+            audit.record_error(None, Some(audit_details), &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+pub async fn handle_rotate_key(name: String, audit: AuditContext, keys: Arc<KeyStore>) -> Result<impl warp::Reply, warp::Rejection> {
+    let audit_details = serde_json::json!({ "name": name });
+
+    match keys.rotate(&name) {
+        Ok(secret) => {
+            let message = format!("Key '{}' rotated. The previous secret no longer works.", name);
+            audit.record(None, Some(audit_details), &ApiResponse { success: true, message: message.clone() });
+            Ok(warp::reply::json(&KeySecretResponse {
+                success: true,
+                message,
+                name,
+                key: secret,
+            }))
+        }
+        Err(error) => {
+            audit.record_error(None, Some(audit_details), &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("exeio-keys-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("keys.json")
+    }
+
+    #[test]
+    fn refuses_a_store_that_does_not_parse() {
+        let path = temp_store_path("corrupt");
+        std::fs::write(&path, "[{ not json").unwrap();
+        assert!(KeyStore::load(path.clone(), "master-secret").is_err());
+        // Left as it was for the operator to fix
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[{ not json");
+    }
+
+    #[test]
+    fn starts_empty_without_a_store() {
+        let path = temp_store_path("missing");
+        let _ = std::fs::remove_file(&path);
+        let store = KeyStore::load(path, "master-secret").unwrap();
+        assert!(store.list().is_empty());
+        assert_eq!(store.authenticate("master-secret").unwrap().name, MASTER_KEY_NAME);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saves_the_store_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_store_path("mode");
+        let _ = std::fs::remove_file(&path);
+        let store = KeyStore::load(path.clone(), "master-secret").unwrap();
+        let secret = store.create("ci", vec![Scope::Read], Vec::new()).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        let reloaded = KeyStore::load(path, "master-secret").unwrap();
+        assert_eq!(reloaded.authenticate(&secret).unwrap().name, "ci");
+    }
+
+    #[test]
+    fn a_failed_save_changes_nothing() {
+        let path = temp_store_path("unsaved");
+        let _ = std::fs::remove_file(&path);
+        let store = KeyStore::load(path.clone(), "master-secret").unwrap();
+        let secret = store.create("ci", vec![Scope::Read], Vec::new()).unwrap();
+
+        // The temp file can't be created once its directory is gone
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(store.create("deploy", vec![Scope::Control], Vec::new()).is_err());
+        assert!(store.rotate("ci").is_err());
+        assert!(store.revoke("ci").is_err());
+
+        assert_eq!(store.list().len(), 1);
+        assert_eq!(store.authenticate(&secret).unwrap().name, "ci");
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_match("web-*", "web-1"));
+        assert!(glob_match("job-?", "job-a"));
+        assert!(!glob_match("job-?", "job-ab"));
+        assert!(!glob_match("web-*", "worker"));
+    }
+}
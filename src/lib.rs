@@ -0,0 +1,1044 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, BufReader as TokioBufReader};
+use tokio::process::Command as TokioCommand;
+use std::path::PathBuf;
+
+mod actor;
+#[cfg(feature = "server")]
+mod audit;
+#[cfg(feature = "server")]
+mod backup;
+#[cfg(feature = "server")]
+mod client;
+#[cfg(feature = "server")]
+mod config_file;
+#[cfg(feature = "server")]
+mod email;
+mod error;
+mod events;
+mod history;
+mod hooks;
+#[cfg(feature = "server")]
+mod import;
+#[cfg(feature = "server")]
+mod keys;
+mod log_rules;
+#[cfg(feature = "server")]
+mod network;
+#[cfg(feature = "server")]
+mod openapi;
+#[cfg(feature = "server")]
+mod ratelimit;
+mod redact;
+#[cfg(unix)]
+mod runtime;
+#[cfg(feature = "server")]
+mod server;
+mod supervisor;
+#[cfg(feature = "server")]
+mod tui;
+#[cfg(feature = "server")]
+mod ui;
+#[cfg(all(unix, feature = "server"))]
+mod unix_socket;
+#[cfg(all(unix, feature = "server"))]
+mod upgrade;
+#[cfg(feature = "server")]
+mod webhooks;
+
+pub use error::{ApiError, FieldError};
+pub use events::{Event, EventKind};
+pub use hooks::Hooks;
+pub use log_rules::{LogRule, RuleAction, RuleCounters, RuleStream};
+#[cfg(feature = "server")]
+pub use server::{Cli, run};
+pub use supervisor::{
+    Clock, ConfigStore, FileConfigStore, FileLogSink, LogSink, MemoryConfigStore, Supervisor, SupervisorBuilder, SystemClock,
+};
+use hooks::{Hook, HookContext};
+// The server modules reach these as crate::
+#[cfg(feature = "server")]
+use server::{
+    ApiResponse, ApplyMode, EXEIO_VERSION, LocalPeer, NETWORK_POLICY, RATE_LIMITER, SETTINGS, change_summary,
+    generate_api_key, get_api_key_path, log_exeio_event, read_api_key_file, validate_api_key,
+};
+
+#[derive(Debug, Clone)]
+struct RestartRequest {
+    process_id: String,
+    delay_seconds: u64,
+    reason: String,
+}
+
+// Hands an auto-restart to the supervisor's restart handler, which carries it out after the delay, and runs
+// the on_restart hook
+fn schedule_restart(supervisor: &Supervisor, config: &ProcessConfig, context: HookContext, request: RestartRequest) {
+    supervisor.emit(&request.process_id, EventKind::RestartScheduled {
+        delay_seconds: request.delay_seconds,
+        reason: request.reason.clone(),
+    });
+    hooks::spawn(supervisor, vec![Hook::OnRestart], config.clone(), HookContext { reason: Some(request.reason.clone()), ..context });
+    supervisor.request_restart(request);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProcessConfig {
+    pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub working_dir: Option<String>,
+    pub auto_restart: bool,
+    pub log_file: String,
+    pub periodic: bool,
+    pub period_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_rules: Vec<LogRule>,
+}
+
+#[derive(Debug)]
+struct ManagedProcess {
+    config: ProcessConfig,
+    pid: Option<u32>,
+    actor: Option<actor::ActorHandle>, // Owns the child while it runs, see actor.rs
+    run_count: u64,
+    last_run: Option<chrono::DateTime<chrono::Utc>>,
+    periodic_handle: Option<tokio::task::JoinHandle<()>>,
+    status: ProcessStatus,
+    last_exit_time: Option<chrono::DateTime<chrono::Utc>>, // Track when process last exited
+    pending_restart: bool, // Config was changed with apply=on_next_restart and isn't in effect yet
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum ProcessStatus {
+    Running,
+    Stopped,
+    WaitingForPeriod,
+    Failed,
+    ManuallyStopped,
+    // The pre_start hook failed, so the process wasn't started or restarted
+    PreStartFailed,
+    // Just added and waiting on its pre_start hook
+    Starting,
+}
+
+type ProcessMap = Arc<Mutex<HashMap<String, ManagedProcess>>>;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AddProcessRequest {
+    pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub working_dir: Option<String>,
+    pub auto_restart: bool,
+    pub save_for_next_run: bool,
+    pub periodic: Option<bool>,
+    pub period_seconds: Option<u64>,
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub log_rules: Vec<LogRule>,
+}
+
+impl AddProcessRequest {
+    // A one-off process with no arguments, for library callers to fill in with struct update syntax
+    pub fn new(id: impl Into<String>, command: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            command: command.into(),
+            args: Vec::new(),
+            working_dir: None,
+            auto_restart: false,
+            save_for_next_run: false,
+            periodic: None,
+            period_seconds: None,
+            hooks: Hooks::default(),
+            log_rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+struct ConfigChange {
+    field: String,
+    old: serde_json::Value,
+    new: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ProcessInfo {
+    pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub working_dir: Option<String>,
+    /// running, stopped, waiting, failed, manually_stopped, pre_start_failed or starting
+    pub status: String,
+    pub is_running: bool,
+    pub log_file: String,
+    pub auto_restart: bool,
+    pub periodic: bool,
+    pub period_seconds: Option<u64>,
+    pub run_count: u64,
+    pub last_run: Option<chrono::DateTime<chrono::Utc>>,
+    /// The config was changed with apply=on_next_restart and isn't in effect yet
+    pub pending_restart: bool,
+    /// Match counts of the process's log rules
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub log_rules: Vec<RuleCounters>,
+    /// Pid of the running process, or of a periodic process's current run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// CPU and memory use of the running process, where the OS reports them (Linux)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ProcessResources>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ProcessResources {
+    /// User and system CPU time used since the process started; sample twice for a CPU percentage
+    pub cpu_seconds: f64,
+    /// Resident memory
+    pub memory_bytes: u64,
+}
+
+// Thread-safe logging and config management, one of each per supervisor
+// Process logs go to the log sink; exeio's own log is always written to disk
+struct SafeLogger {
+    #[cfg(feature = "server")]
+    files: FileLogSink,
+    sink: Arc<dyn LogSink>,
+    writer: std::sync::mpsc::Sender<LogWrite>,
+}
+
+// Process log lines are written in order by one thread, so output relays and actors never block on file I/O
+enum LogWrite {
+    Append { log_file: String, content: String },
+    #[cfg(feature = "server")]
+    Flush(std::sync::mpsc::Sender<()>),
+}
+
+impl SafeLogger {
+    // The writer thread ends when the logger is dropped
+    fn new(sink: Arc<dyn LogSink>) -> Self {
+        let (writer, writes) = std::sync::mpsc::channel();
+        let thread_sink = sink.clone();
+        std::thread::Builder::new()
+            .name("exeio-log-writer".to_string())
+            .spawn(move || {
+                for write in writes {
+                    match write {
+                        LogWrite::Append { log_file, content } => {
+                            if let Err(e) = thread_sink.append(&log_file, &content) {
+                                eprintln!("Failed to write log {}: {}", log_file, e);
+                            }
+                        }
+                        #[cfg(feature = "server")]
+                        LogWrite::Flush(done) => {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            })
+            .expect("Failed to start the log writer thread");
+        Self {
+            #[cfg(feature = "server")]
+            files: FileLogSink::default(),
+            sink,
+            writer,
+        }
+    }
+
+    fn process_sink(&self) -> &Arc<dyn LogSink> {
+        &self.sink
+    }
+
+    #[cfg(feature = "server")]
+    fn safe_append_log(&self, log_path: &str, content: &str) -> Result<(), std::io::Error> {
+        self.files.append(log_path, content)
+    }
+
+    // Queued for the writer thread; only fails if it has gone
+    fn append_process_log(&self, log_file: &str, content: &str) -> Result<(), std::io::Error> {
+        self.writer
+            .send(LogWrite::Append { log_file: log_file.to_string(), content: content.to_string() })
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "log writer stopped"))
+    }
+
+    // Blocks until everything queued so far has been written
+    #[cfg(feature = "server")]
+    fn flush_process_logs(&self) {
+        let (done, written) = std::sync::mpsc::channel();
+        if self.writer.send(LogWrite::Flush(done)).is_ok() {
+            let _ = written.recv();
+        }
+    }
+
+    fn open_process_log(&self, log_file: &str) -> Result<(), std::io::Error> {
+        self.sink.open(log_file)
+    }
+}
+
+struct SafeConfigManager {
+    config_lock: RwLock<()>,
+    store: Arc<dyn ConfigStore>,
+    history: history::ConfigHistory,
+}
+
+impl SafeConfigManager {
+    fn new(store: Arc<dyn ConfigStore>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            config_lock: RwLock::new(()),
+            history: history::ConfigHistory::new(store.history_path(), clock),
+            store,
+        }
+    }
+
+    #[cfg(feature = "server")]
+    fn history(&self) -> &history::ConfigHistory {
+        &self.history
+    }
+
+    fn read_configs(&self) -> Vec<ProcessConfig> {
+        self.store.load().unwrap_or_else(|e| {
+            eprintln!("Failed to load saved configs: {}", e);
+            Vec::new()
+        })
+    }
+
+    fn load_configs(&self) -> Vec<ProcessConfig> {
+        let _read_guard = self.config_lock.read().unwrap();
+        self.read_configs()
+    }
+
+    // `actor` is recorded in the config history as whoever made the change
+    fn save_configs(&self, configs: &[ProcessConfig], actor: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let _write_guard = self.config_lock.write().unwrap();
+        let old = self.read_configs();
+        
+        self.store.save(configs)?;
+
+        self.history.record(actor, &old, configs);
+        
+        Ok(())
+    }
+
+    fn save_process_config(&self, config: &ProcessConfig, actor: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut configs = self.load_configs();
+        configs.retain(|c| c.id != config.id);
+        configs.push(config.clone());
+        self.save_configs(&configs, actor)
+    }
+
+    fn is_saved(&self, id: &str) -> bool {
+        self.load_configs().iter().any(|c| c.id == id)
+    }
+
+    fn remove_process_config(&self, id: &str, actor: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut configs = self.load_configs();
+        configs.retain(|c| c.id != id);
+        self.save_configs(&configs, actor)
+    }
+}
+
+async fn start_process(supervisor: &Supervisor, config: ProcessConfig) {
+    let processes = supervisor.process_map();
+    if let Err(e) = supervisor.logger().open_process_log(&config.log_file) {
+        eprintln!("Failed to open log file {}: {}", config.log_file, e);
+        // Drops the entry add_process reserved the id with
+        let mut processes_lock = processes.lock().unwrap();
+        if processes_lock.get(&config.id).is_some_and(|managed_process| matches!(managed_process.status, ProcessStatus::Starting)) {
+            processes_lock.remove(&config.id);
+        }
+        return;
+    }
+    
+    // Get current run count
+    let current_run_count = {
+        let processes_lock = processes.lock().unwrap();
+        if let Some(managed_process) = processes_lock.get(&config.id) {
+            managed_process.run_count
+        } else {
+            supervisor.emit(&config.id, EventKind::Added);
+            1
+        }
+    };
+    
+    // Log process start
+    supervisor.log_system(&config.log_file, &format!("Starting process '{}' (Run #{})", config.id, current_run_count));
+    
+    if config.periodic && config.period_seconds.is_some() {
+        start_periodic_process(supervisor, config).await;
+    } else if let Err(e) = hooks::run(supervisor, Hook::PreStart, &config, &HookContext::run(current_run_count)).await {
+        block_start(supervisor, config, current_run_count, &e);
+    } else {
+        start_regular_process(supervisor, config, current_run_count).await;
+    }
+}
+
+// Leaves a process whose pre_start hook failed stopped with the PreStartFailed status; it isn't auto-restarted
+fn block_start(supervisor: &Supervisor, config: ProcessConfig, run_count: u64, error: &str) {
+    supervisor.log_system(&config.log_file, &format!("Not starting process '{}': pre_start hook {}", config.id, error));
+    supervisor.emit(&config.id, EventKind::Failed { error: format!("pre_start hook {}", error) });
+
+    let mut processes_lock = supervisor.process_map().lock().unwrap();
+    let managed_process = processes_lock.entry(config.id.clone()).or_insert_with(|| ManagedProcess {
+        config: config.clone(),
+        pid: None,
+        actor: None,
+        run_count,
+        last_run: None,
+        periodic_handle: None,
+        status: ProcessStatus::PreStartFailed,
+        last_exit_time: None,
+        pending_restart: false,
+    });
+    managed_process.config = config;
+    managed_process.pending_restart = false;
+    managed_process.status = ProcessStatus::PreStartFailed;
+}
+
+async fn start_regular_process(supervisor: &Supervisor, config: ProcessConfig, run_count: u64) {
+    let processes = supervisor.process_map();
+    let mut cmd = TokioCommand::new(&config.command);
+    cmd.args(&config.args);
+
+    // Named pipes outlive the supervisor, so a restarted exeio can reattach to the process
+    #[cfg(unix)]
+    let fifos = runtime::Fifos::attach(&mut cmd, supervisor, &config.id);
+    #[cfg(not(unix))]
+    cmd.stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    
+    if let Some(ref dir) = config.working_dir {
+        cmd.current_dir(dir);
+    }
+    
+    let spawned = cmd.spawn();
+    // Drops our copies of the child's ends, otherwise the output pipes never reach end of file
+    drop(cmd);
+
+    match spawned {
+        Ok(mut child) => {
+            // Only None once the child has been reaped, which the actor does
+            let child_id = child.id().unwrap_or_default();
+
+            #[cfg(unix)]
+            let attached = fifos.map(|fifos| fifos.open());
+            #[cfg(not(unix))]
+            let attached: Option<std::io::Result<(tokio::process::ChildStdin, tokio::process::ChildStdout, tokio::process::ChildStderr)>> = None;
+            let rules = log_rules::Matcher::new(&config, supervisor);
+            let stdin: Option<Box<dyn tokio::io::AsyncWrite + Unpin + Send>> = match attached {
+                Some(Ok((stdin, stdout, stderr))) => {
+                    actor::relay_output(supervisor.clone(), stdout, config.log_file.clone(), config.id.clone(), false, rules.clone());
+                    actor::relay_output(supervisor.clone(), stderr, config.log_file.clone(), config.id.clone(), true, rules.clone());
+                    Some(Box::new(stdin))
+                }
+                Some(Err(e)) => {
+                    eprintln!("Failed to attach to the output of process {}: {}", config.id, e);
+                    None
+                }
+                None => {
+                    if let Some(stdout) = child.stdout.take() {
+                        actor::relay_output(supervisor.clone(), stdout, config.log_file.clone(), config.id.clone(), false, rules.clone());
+                    }
+                    if let Some(stderr) = child.stderr.take() {
+                        actor::relay_output(supervisor.clone(), stderr, config.log_file.clone(), config.id.clone(), true, rules.clone());
+                    }
+                    child.stdin.take().map(|stdin| Box::new(stdin) as Box<dyn tokio::io::AsyncWrite + Unpin + Send>)
+                }
+            };
+            
+            {
+                // Held until the entry is in, so an actor whose process exits at once finds it
+                let mut processes_lock = processes.lock().unwrap();
+                let actor = actor::spawn(supervisor.clone(), config.clone(), child_id, run_count, actor::Watched::Child(child), stdin);
+                processes_lock.insert(config.id.clone(), ManagedProcess {
+                    config: config.clone(),
+                    pid: Some(child_id),
+                    actor: Some(actor),
+                    run_count,
+                    last_run: Some(supervisor.now()),
+                    periodic_handle: None,
+                    status: ProcessStatus::Running,
+                    last_exit_time: None,
+                    pending_restart: false,
+                });
+            }
+            #[cfg(unix)]
+            runtime::save(supervisor);
+            
+            supervisor.emit(&config.id, EventKind::Started { pid: child_id, run_count });
+            println!("Started process: {} ({})", config.id, config.command);
+            hooks::spawn(supervisor, vec![Hook::PostStart], config.clone(), HookContext { pid: Some(child_id), ..HookContext::run(run_count) });
+        }
+        Err(e) => {
+            eprintln!("Failed to start process {}: {}", config.id, e);
+            supervisor.emit(&config.id, EventKind::Failed { error: e.to_string() });
+            
+            let managed_process = ManagedProcess {
+                config: config.clone(),
+                pid: None,
+                actor: None,
+                run_count: 0,
+                last_run: None,
+                periodic_handle: None,
+                status: ProcessStatus::Failed,
+                last_exit_time: None,
+                pending_restart: false,
+            };
+            
+            {
+                let mut processes_lock = processes.lock().unwrap();
+                processes_lock.insert(config.id.clone(), managed_process);
+            }
+            
+            // If auto-restart is enabled and process failed to start, try to restart after a delay
+            if config.auto_restart {
+                schedule_restart(supervisor, &config, HookContext::run(run_count), RestartRequest {
+                    process_id: config.id.clone(),
+                    delay_seconds: 5,
+                    reason: format!("Auto-restarting process '{}' after failed start", config.id),
+                });
+            }
+        }
+    }
+}
+
+async fn start_periodic_process(supervisor: &Supervisor, config: ProcessConfig) {
+    let period_seconds = config.period_seconds.unwrap_or(60);
+    let owner = supervisor.clone();
+    let config_clone = config.clone();
+    let rules = log_rules::Matcher::new(&config, supervisor);
+    
+    let periodic_handle = tokio::spawn(async move {
+        let mut run_count = 0u64;
+        
+        loop {
+            run_count += 1;
+            
+            // Log periodic run start
+            owner.log_system(&config_clone.log_file, &format!("Starting periodic run #{} (every {}s)", run_count, period_seconds));
+            
+            // Update run count and status
+            {
+                let mut processes_lock = owner.process_map().lock().unwrap();
+                if let Some(managed_process) = processes_lock.get_mut(&config_clone.id) {
+                    managed_process.run_count = run_count;
+                    managed_process.last_run = Some(owner.now());
+                    managed_process.status = ProcessStatus::Running;
+                }
+            }
+            
+            // A failed pre_start hook skips this run
+            if let Err(e) = hooks::run(&owner, Hook::PreStart, &config_clone, &HookContext::run(run_count)).await {
+                owner.log_system(&config_clone.log_file, &format!("Skipping run #{}: pre_start hook {}", run_count, e));
+                owner.emit(&config_clone.id, EventKind::Failed { error: format!("pre_start hook {}", e) });
+                if let Some(managed_process) = owner.process_map().lock().unwrap().get_mut(&config_clone.id) {
+                    managed_process.status = ProcessStatus::PreStartFailed;
+                }
+                tokio::time::sleep(Duration::from_secs(period_seconds)).await;
+                continue;
+            }
+
+            // Run the command
+            let mut cmd = TokioCommand::new(&config_clone.command);
+            cmd.args(&config_clone.args)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                // Stopping the process aborts this task, which takes the run down with it
+                .kill_on_drop(true);
+            
+            if let Some(ref dir) = config_clone.working_dir {
+                cmd.current_dir(dir);
+            }
+            
+            match cmd.spawn() {
+                Ok(mut child) => {
+                    let pid = child.id().unwrap_or_default();
+                    // Set for the length of the run, an upgrade waits for it to clear
+                    if let Some(managed_process) = owner.process_map().lock().unwrap().get_mut(&config_clone.id) {
+                        managed_process.pid = Some(pid);
+                    }
+                    owner.emit(&config_clone.id, EventKind::Started { pid, run_count });
+                    hooks::spawn(&owner, vec![Hook::PostStart], config_clone.clone(), HookContext { pid: Some(pid), ..HookContext::run(run_count) });
+
+                    // Handle stdout
+                    if let Some(stdout) = child.stdout.take() {
+                        let log_file_path = config_clone.log_file.clone();
+                        let process_id = config_clone.id.clone();
+                        let run_num = run_count;
+                        let rules = rules.clone();
+                        let owner = owner.clone();
+                        
+                        tokio::spawn(async move {
+                            let reader = TokioBufReader::new(stdout);
+                            let mut lines = reader.lines();
+                            
+                            while let Ok(Some(line)) = lines.next_line().await {
+                                let log_entry = format!("[{}] RUN#{} STDOUT: {}\n", 
+                                    owner.now().format("%Y-%m-%d %H:%M:%S"), run_num, line);
+                                let _ = owner.logger().append_process_log(&log_file_path, &log_entry);
+                                if let Some(rules) = &rules {
+                                    rules.check(&line, false);
+                                }
+                                println!("[{}] Run#{}: {}", process_id, run_num, line);
+                            }
+                        });
+                    }
+                    
+                    // Handle stderr
+                    if let Some(stderr) = child.stderr.take() {
+                        let log_file_path = config_clone.log_file.clone();
+                        let process_id = config_clone.id.clone();
+                        let run_num = run_count;
+                        let rules = rules.clone();
+                        let owner = owner.clone();
+                        
+                        tokio::spawn(async move {
+                            let reader = TokioBufReader::new(stderr);
+                            let mut lines = reader.lines();
+                            
+                            while let Ok(Some(line)) = lines.next_line().await {
+                                let log_entry = format!("[{}] RUN#{} STDERR: {}\n", 
+                                    owner.now().format("%Y-%m-%d %H:%M:%S"), run_num, line);
+                                let _ = owner.logger().append_process_log(&log_file_path, &log_entry);
+                                if let Some(rules) = &rules {
+                                    rules.check(&line, true);
+                                }
+                                eprintln!("[{}] Run#{} ERROR: {}", process_id, run_num, line);
+                            }
+                        });
+                    }
+                    
+                    // Wait for the process to complete
+                    match child.wait().await {
+                        Ok(status) => {
+                            owner.log_system(&config_clone.log_file, &format!("Run #{} completed with status: {}", run_count, status));
+                            owner.emit(&config_clone.id, EventKind::PeriodicRunFinished { run: run_count, exit_code: status.code() });
+                            let context = HookContext::exited(pid, run_count, Some(status));
+                            if context.crashed() {
+                                hooks::spawn(&owner, vec![Hook::OnExit, Hook::OnCrash], config_clone.clone(), context);
+                            } else {
+                                hooks::spawn(&owner, vec![Hook::OnExit], config_clone.clone(), context);
+                            }
+                        }
+                        Err(e) => {
+                            owner.log_system(&config_clone.log_file, &format!("Run #{} failed: {}", run_count, e));
+                        }
+                    }
+                }
+                Err(e) => {
+                    owner.log_system(&config_clone.log_file, &format!("Failed to start run #{}: {}", run_count, e));
+                    owner.emit(&config_clone.id, EventKind::Failed { error: e.to_string() });
+                }
+            }
+            
+            // Update status to waiting
+            {
+                let mut processes_lock = owner.process_map().lock().unwrap();
+                if let Some(managed_process) = processes_lock.get_mut(&config_clone.id) {
+                    managed_process.pid = None;
+                    managed_process.status = ProcessStatus::WaitingForPeriod;
+                }
+            }
+            
+            // Wait for the next period
+            tokio::time::sleep(Duration::from_secs(period_seconds)).await;
+        }
+    });
+    
+    let managed_process = ManagedProcess {
+        config: config.clone(),
+        pid: None,
+        actor: None,
+        run_count: 0,
+        last_run: None,
+        periodic_handle: Some(periodic_handle),
+        status: ProcessStatus::WaitingForPeriod,
+        last_exit_time: None,
+        pending_restart: false,
+    };
+    
+    {
+        let mut processes_lock = supervisor.process_map().lock().unwrap();
+        processes_lock.insert(config.id.clone(), managed_process);
+    }
+    
+    println!("Started periodic process: {} (every {}s)", config.id, period_seconds);
+}
+
+// Ids name the log file and runtime FIFOs, so they must be safe as a file name
+fn is_valid_process_id(id: &str) -> bool {
+    id != "." && id != ".." && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+// Collects every invalid field so clients can fix them all at once
+fn validate_process_fields(id: &str, command: &str, periodic: bool, period_seconds: Option<u64>, hooks: &Hooks, log_rules: &[LogRule]) -> Vec<FieldError> {
+    let mut invalid_fields = Vec::new();
+    if id.trim().is_empty() {
+        invalid_fields.push(FieldError::new("id", "empty", "Process ID cannot be empty or just whitespace"));
+    } else if !is_valid_process_id(id) {
+        invalid_fields.push(FieldError::new("id", "invalid", "Process ID may only contain letters, digits, '.', '_' and '-', and cannot be '.' or '..'"));
+    }
+    if command.trim().is_empty() {
+        invalid_fields.push(FieldError::new("command", "empty", "Process command cannot be empty or just whitespace"));
+    }
+    if periodic {
+        match period_seconds {
+            Some(0) => invalid_fields.push(FieldError::new("period_seconds", "out_of_range", "period_seconds must be greater than zero")),
+            Some(_) => {}
+            None => invalid_fields.push(FieldError::new("period_seconds", "required", "Periodic processes must specify period_seconds")),
+        }
+    }
+    invalid_fields.extend(hooks.validate("hooks"));
+    invalid_fields.extend(log_rules::validate(log_rules));
+    invalid_fields
+}
+
+// Stops the child and periodic task of a process, leaving its entry in place. Doesn't wait for the child to
+// exit; the returned task finishes once its actor has reaped it, and must be awaited before a replacement starts
+#[must_use]
+fn halt_process(supervisor: &Supervisor, managed_process: &mut ManagedProcess, status: ProcessStatus) -> Option<tokio::task::JoinHandle<()>> {
+    let reaped = managed_process.actor.take().map(actor::ActorHandle::kill);
+    // The actor leaves the entry alone once the pid no longer matches
+    managed_process.pid = None;
+    if let Some(handle) = managed_process.periodic_handle.take() {
+        handle.abort();
+    }
+    if matches!(status, ProcessStatus::ManuallyStopped) {
+        supervisor.emit(&managed_process.config.id, EventKind::Stopped);
+    }
+    managed_process.status = status;
+    reaped
+}
+
+// Field-by-field differences between two configs. Changes are found on the configs as they are, and reported with
+// secret-looking arguments, hook commands and rule input masked
+fn config_diff(old: &ProcessConfig, new: &ProcessConfig) -> Vec<ConfigChange> {
+    let masked = |config: &ProcessConfig| {
+        let mut value = serde_json::to_value(config).unwrap_or_default();
+        value["args"] = serde_json::json!(redact::redact_args(&config.args));
+        // Only where present, as they are left out when empty
+        if value.get("hooks").is_some() {
+            value["hooks"] = serde_json::json!(redact::redact_hooks(&config.hooks));
+        }
+        if value.get("log_rules").is_some() {
+            value["log_rules"] = serde_json::json!(redact::redact_log_rules(&config.log_rules));
+        }
+        value
+    };
+    let (old_raw, new_raw) = (serde_json::to_value(old).unwrap_or_default(), serde_json::to_value(new).unwrap_or_default());
+    let (old_masked, new_masked) = (masked(old), masked(new));
+
+    let mut changes = Vec::new();
+    if let (Some(old_fields), Some(new_fields)) = (old_raw.as_object(), new_raw.as_object()) {
+        // Fields left out when empty, such as hooks, are only in one of them
+        let mut fields: Vec<&String> = old_fields.keys().chain(new_fields.keys()).collect();
+        fields.sort_unstable();
+        fields.dedup();
+        for field in fields {
+            if old_fields.get(field) != new_fields.get(field) {
+                changes.push(ConfigChange {
+                    field: field.clone(),
+                    old: old_masked.get(field).cloned().unwrap_or_default(),
+                    new: new_masked.get(field).cloned().unwrap_or_default(),
+                });
+            }
+        }
+    }
+    changes
+}
+
+fn process_info(supervisor: &Supervisor, id: &str, managed_process: &ManagedProcess) -> ProcessInfo {
+    let is_running = managed_process.actor.is_some() || managed_process.periodic_handle.is_some();
+    let status_str = match &managed_process.status {
+        ProcessStatus::Running => "running",
+        ProcessStatus::Stopped => "stopped",
+        ProcessStatus::WaitingForPeriod => "waiting",
+        ProcessStatus::Failed => "failed",
+        ProcessStatus::ManuallyStopped => "manually_stopped",
+        ProcessStatus::PreStartFailed => "pre_start_failed",
+        ProcessStatus::Starting => "starting",
+    };
+
+    ProcessInfo {
+        id: id.to_string(),
+        command: managed_process.config.command.clone(),
+        args: managed_process.config.args.clone(),
+        working_dir: managed_process.config.working_dir.clone(),
+        status: status_str.to_string(),
+        is_running,
+        log_file: managed_process.config.log_file.clone(),
+        auto_restart: managed_process.config.auto_restart,
+        periodic: managed_process.config.periodic,
+        period_seconds: managed_process.config.period_seconds,
+        run_count: managed_process.run_count,
+        last_run: managed_process.last_run,
+        pending_restart: managed_process.pending_restart,
+        log_rules: log_rules::counters(supervisor, &managed_process.config),
+        pid: managed_process.pid,
+        resources: managed_process.pid.and_then(process_resources),
+    }
+}
+
+#[cfg(unix)]
+fn process_resources(pid: u32) -> Option<ProcessResources> {
+    runtime::resources(pid)
+}
+
+#[cfg(not(unix))]
+fn process_resources(_pid: u32) -> Option<ProcessResources> {
+    None
+}
+
+fn get_config_path() -> std::path::PathBuf {
+    let mut config_dir = dirs::home_dir().unwrap_or_else(|| {
+        eprintln!("Could not determine home directory, using current directory instead");
+        std::env::current_dir().unwrap_or_default()
+    });
+    
+    config_dir.push(".config");
+    config_dir.push("exeio");
+    
+    // Create the directory if it doesn't exist
+    std::fs::create_dir_all(&config_dir).unwrap_or_else(|e| {
+        eprintln!("Failed to create config directory: {}", e);
+    });
+    
+    config_dir.push("processes.json");
+    config_dir
+}
+
+fn get_logs_dir() -> PathBuf {
+    let mut logs_dir = dirs::home_dir().unwrap_or_else(|| {
+        eprintln!("Could not determine home directory, using current directory instead");
+        std::env::current_dir().unwrap_or_default()
+    });
+    
+    logs_dir.push(".local");
+    logs_dir.push("share");
+    logs_dir.push("exeio");
+    logs_dir.push("logs");
+    
+    // Create the directory if it doesn't exist
+    std::fs::create_dir_all(&logs_dir).unwrap_or_else(|e| {
+        eprintln!("Failed to create logs directory: {}", e);
+    });
+    
+    logs_dir
+}
+
+fn get_process_log_path(process_id: &str) -> PathBuf {
+    let mut path = get_logs_dir();
+    path.push(format!("{}.log", process_id));
+    path
+}
+
+#[cfg(feature = "server")]
+fn get_lock_file_path() -> PathBuf {
+    let mut lock_dir = dirs::home_dir().unwrap_or_else(|| {
+        eprintln!("Could not determine home directory, using /tmp instead");
+        PathBuf::from("/tmp")
+    });
+    
+    lock_dir.push(".local");
+    lock_dir.push("share");
+    lock_dir.push("exeio");
+    
+    // Create the directory if it doesn't exist
+    std::fs::create_dir_all(&lock_dir).unwrap_or_else(|e| {
+        eprintln!("Failed to create lock directory: {}", e);
+    });
+    
+    lock_dir.push("exeio.lock");
+    lock_dir
+}
+
+fn read_logs_reverse_paginated(
+    log_file_path: &str, 
+    page: usize, 
+    page_size: usize
+) -> Result<(Vec<String>, usize), std::io::Error> {
+    let file = File::open(log_file_path)?;
+    let file_size = file.metadata()?.len();
+    
+    if file_size == 0 {
+        return Ok((Vec::new(), 0));
+    }
+    
+    // For small files (< 512KB) or early pages, use simpler approach
+    if file_size < 524_288 || page <= 3 {
+        return read_logs_simple(file, page, page_size);
+    }
+    
+    // For large files, use chunked reading
+    read_logs_chunked(file, file_size, page, page_size)
+}
+
+fn read_logs_simple(
+    file: File, 
+    page: usize, 
+    page_size: usize
+) -> Result<(Vec<String>, usize), std::io::Error> {
+    let mut reader = BufReader::with_capacity(32768, file);
+    let mut all_lines = Vec::new();
+    let mut line = String::new();
+    
+    while reader.read_line(&mut line)? > 0 {
+        let trimmed = line.trim_end();
+        if !trimmed.is_empty() {
+            all_lines.push(trimmed.to_string());
+        }
+        line.clear();
+    }
+    
+    let total_lines = all_lines.len();
+    all_lines.reverse(); // Reverse to get newest first
+    
+    let lines_to_skip = (page - 1) * page_size;
+    let result_lines: Vec<String> = all_lines
+        .into_iter()
+        .skip(lines_to_skip)
+        .take(page_size)
+        .collect();
+    
+    Ok((result_lines, total_lines))
+}
+
+fn read_logs_chunked(
+    file: File,
+    file_size: u64,
+    page: usize,
+    page_size: usize,
+) -> Result<(Vec<String>, usize), std::io::Error> {
+    use std::io::{Seek, SeekFrom};
+    
+    let mut reader = BufReader::with_capacity(32768, file);
+    let mut lines = Vec::new();
+    let mut total_lines = 0;
+    let mut buffer = Vec::new();
+    let mut pos = file_size;
+    
+    let lines_to_skip = (page - 1) * page_size;
+    let lines_to_read = page_size;
+    let mut lines_found = 0;
+    let mut lines_collected = 0;
+    
+    const CHUNK_SIZE: u64 = 32768; // 32KB chunks
+    
+    while pos > 0 && (lines_collected < lines_to_read || total_lines == 0) {
+        let chunk_start = pos.saturating_sub(CHUNK_SIZE);
+        let chunk_size = pos - chunk_start;
+        
+        reader.seek(SeekFrom::Start(chunk_start))?;
+        
+        let mut chunk = vec![0u8; chunk_size as usize];
+        reader.read_exact(&mut chunk)?;
+        
+        if buffer.is_empty() {
+            buffer = chunk;
+        } else {
+            let mut new_buffer = Vec::with_capacity(chunk.len() + buffer.len());
+            new_buffer.extend_from_slice(&chunk);
+            new_buffer.extend_from_slice(&buffer);
+            buffer = new_buffer;
+        }
+        
+        let mut line_start = buffer.len();
+        
+        // Scan backwards for newlines
+        for (i, &byte) in buffer.iter().enumerate().rev() {
+            if byte == b'\n' || i == 0 {
+                let line_end = if byte == b'\n' { line_start } else { buffer.len() };
+                let actual_start = if byte == b'\n' && i > 0 { i + 1 } else { i };
+                
+                if actual_start < line_end {
+                    let line = String::from_utf8_lossy(&buffer[actual_start..line_end]);
+                    let trimmed_line = line.trim_end();
+                    if !trimmed_line.is_empty() {
+                        total_lines += 1;
+                        lines_found += 1;
+                        
+                        // Only collect lines we want to return (after skipping)
+                        if lines_found > lines_to_skip && lines_collected < lines_to_read {
+                            lines.push(trimmed_line.to_string());
+                            lines_collected += 1;
+                        }
+                    }
+                }
+                line_start = i;
+            }
+        }
+        
+        // Keep only the unprocessed part of buffer for next iteration
+        if line_start > 0 {
+            buffer.truncate(line_start);
+        } else {
+            buffer.clear();
+        }
+        
+        pos = chunk_start;
+        
+        // Early termination if we have collected enough lines and read the whole file
+        if lines_collected >= lines_to_read && pos == 0 {
+            break;
+        }
+    }
+    
+    lines.reverse();
+    Ok((lines, total_lines))
+}
+
+// Calculate restart delay with exponential backoff and recent exit detection
+fn calculate_restart_delay(now: chrono::DateTime<chrono::Utc>, run_count: u64, last_exit_time: Option<chrono::DateTime<chrono::Utc>>) -> u64 {
+    // If the process exited very recently (within 10 seconds), increase delay significantly
+    let rapid_restart_penalty = if let Some(last_exit) = last_exit_time {
+        let time_since_last_exit = now.signed_duration_since(last_exit);
+        if time_since_last_exit.num_seconds() < 10 {
+            20 // Add 20 seconds penalty for rapid restarts
+        } else {
+            0
+        }
+    } else {
+        0
+    };
+    
+    // Base delay calculation with exponential backoff
+    let base_delay = if run_count <= 3 {
+        2 // 2 seconds for first 3 restarts
+    } else if run_count <= 6 {
+        5 // 5 seconds for next 3 restarts
+    } else if run_count <= 10 {
+        15 // 15 seconds for next 4 restarts
+    } else if run_count <= 15 {
+        30 // 30 seconds for next 5 restarts
+    } else {
+        60 // 1 minute for subsequent restarts
+    };
+    
+    base_delay + rapid_restart_penalty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn process_ids_are_safe_file_names() {
+        for id in ["web", "web-1", "worker_2", "api.v2", "..hidden"] {
+            assert!(is_valid_process_id(id), "{}", id);
+        }
+        for id in [".", "..", "../etc", "a/b", "web 1", "web@2", "über"] {
+            assert!(!is_valid_process_id(id), "{}", id);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+// Log rules: regexes matched against a process's output lines as they are relayed to its log, each with
+// actions to run on a match and a cooldown between runs. Counters are kept per process and rule name
+use std::sync::Arc;
+
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::actor::ProcessCommand;
+use crate::error::FieldError;
+use crate::events::EventKind;
+use crate::{ProcessConfig, Supervisor};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleStream {
+    #[default]
+    Both,
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    // Publish a log_matched event
+    Event,
+    // Publish a log_matched event that also fires the log_alert trigger of webhooks and email
+    Notify,
+    Restart,
+    // Send the rule's `input` line to the process's stdin
+    Input,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LogRule {
+    /// Names the rule in events and counters, unique per process
+    pub name: String,
+    /// Regular expression searched for in each output line
+    pub pattern: String,
+    /// stdout, stderr or both (default)
+    #[serde(default)]
+    pub stream: RuleStream,
+    /// Any of event, notify, restart and input
+    pub actions: Vec<RuleAction>,
+    /// The line the input action sends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    /// Matches within this long after the actions last ran are only counted (default 60)
+    #[serde(default = "default_cooldown_seconds")]
+    pub cooldown_seconds: u64,
+}
+
+fn default_cooldown_seconds() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RuleCounters {
+    pub name: String,
+    /// Lines that matched, including those during a cooldown
+    pub matches: u64,
+    /// Times the actions ran
+    pub triggered: u64,
+    pub last_match: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_triggered: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl RuleCounters {
+    fn new(name: &str) -> Self {
+        Self { name: name.to_string(), matches: 0, triggered: 0, last_match: None, last_triggered: None }
+    }
+}
+
+// Every invalid field of a process's rules, named `log_rules[<index>].<field>`
+pub fn validate(rules: &[LogRule]) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        let field = |name: &str| format!("log_rules[{}].{}", index, name);
+        if rule.name.trim().is_empty() {
+            errors.push(FieldError::new(&field("name"), "empty", "Rule name cannot be empty"));
+        } else if rules[..index].iter().any(|other| other.name == rule.name) {
+            errors.push(FieldError::new(&field("name"), "duplicate", format!("Another rule is already named '{}'", rule.name)));
+        }
+        if let Err(e) = Regex::new(&rule.pattern) {
+            errors.push(FieldError::new(&field("pattern"), "invalid", format!("Invalid regular expression: {}", e)));
+        }
+        if rule.actions.is_empty() {
+            errors.push(FieldError::new(&field("actions"), "empty", "List at least one action: event, notify, restart or input"));
+        }
+        match (rule.actions.contains(&RuleAction::Input), &rule.input) {
+            (true, None) => errors.push(FieldError::new(&field("input"), "required", "The input action needs an input line")),
+            (false, Some(_)) => errors.push(FieldError::new(&field("input"), "unused", "input is only used by the input action")),
+            _ => {}
+        }
+    }
+    errors
+}
+
+// The counters of the process's current rules, in rule order
+pub fn counters(supervisor: &Supervisor, config: &ProcessConfig) -> Vec<RuleCounters> {
+    let counters_lock = supervisor.rule_counters().lock().unwrap();
+    let process_counters = counters_lock.get(&config.id);
+    config.log_rules
+        .iter()
+        .map(|rule| {
+            process_counters
+                .and_then(|process_counters| process_counters.get(&rule.name))
+                .cloned()
+                .unwrap_or_else(|| RuleCounters::new(&rule.name))
+        })
+        .collect()
+}
+
+pub fn forget(supervisor: &Supervisor, process_id: &str) {
+    supervisor.rule_counters().lock().unwrap().remove(process_id);
+}
+
+// A process's compiled rules, shared by its stdout and stderr relays
+pub struct Matcher {
+    rules: Vec<(LogRule, Regex)>,
+    process_id: String,
+    log_file: String,
+    supervisor: Supervisor,
+}
+
+impl Matcher {
+    // None for a process without rules. Invalid patterns were rejected with the config and are skipped
+    pub fn new(config: &ProcessConfig, supervisor: &Supervisor) -> Option<Arc<Self>> {
+        let rules: Vec<(LogRule, Regex)> = config.log_rules
+            .iter()
+            .filter_map(|rule| Regex::new(&rule.pattern).ok().map(|regex| (rule.clone(), regex)))
+            .collect();
+        if rules.is_empty() {
+            return None;
+        }
+        Some(Arc::new(Self {
+            rules,
+            process_id: config.id.clone(),
+            log_file: config.log_file.clone(),
+            supervisor: supervisor.clone(),
+        }))
+    }
+
+    pub fn check(&self, line: &str, stderr: bool) {
+        for (rule, regex) in &self.rules {
+            let stream_matches = match rule.stream {
+                RuleStream::Both => true,
+                RuleStream::Stdout => !stderr,
+                RuleStream::Stderr => stderr,
+            };
+            if stream_matches && regex.is_match(line) && self.count(rule) {
+                self.act(rule, line, stderr);
+            }
+        }
+    }
+
+    // Counts a match; true when the rule's cooldown is over and its actions should run
+    fn count(&self, rule: &LogRule) -> bool {
+        let now = self.supervisor.now();
+        let mut counters_lock = self.supervisor.rule_counters().lock().unwrap();
+        let counters = counters_lock
+            .entry(self.process_id.clone())
+            .or_default()
+            .entry(rule.name.clone())
+            .or_insert_with(|| RuleCounters::new(&rule.name));
+        counters.matches += 1;
+        counters.last_match = Some(now);
+        let cooling_down = counters.last_triggered
+            .is_some_and(|triggered| (now - triggered).num_seconds() < rule.cooldown_seconds as i64);
+        if cooling_down {
+            return false;
+        }
+        counters.triggered += 1;
+        counters.last_triggered = Some(now);
+        true
+    }
+
+    fn act(&self, rule: &LogRule, line: &str, stderr: bool) {
+        let actions: Vec<&str> = rule.actions.iter().map(|action| match action {
+            RuleAction::Event => "event",
+            RuleAction::Notify => "notify",
+            RuleAction::Restart => "restart",
+            RuleAction::Input => "input",
+        }).collect();
+        self.log(&format!("Log rule '{}' matched, running: {}", rule.name, actions.join(", ")));
+
+        let notify = rule.actions.contains(&RuleAction::Notify);
+        if notify || rule.actions.contains(&RuleAction::Event) {
+            self.supervisor.emit(&self.process_id, EventKind::LogMatched {
+                rule: rule.name.clone(),
+                stream: if stderr { "stderr" } else { "stdout" }.to_string(),
+                line: line.to_string(),
+                notify,
+            });
+        }
+
+        if let Some(input) = rule.input.as_ref().filter(|_| rule.actions.contains(&RuleAction::Input)) {
+            let sent = {
+                let processes_lock = self.supervisor.process_map().lock().unwrap();
+                processes_lock
+                    .get(&self.process_id)
+                    .and_then(|managed_process| managed_process.actor.as_ref())
+                    .filter(|actor| actor.is_interactive())
+                    .is_some_and(|actor| actor.send(ProcessCommand::Input(input.clone())).is_ok())
+            };
+            if !sent {
+                self.log(&format!("Log rule '{}' could not send input: the process has no stdin", rule.name));
+            }
+        }
+
+        if rule.actions.contains(&RuleAction::Restart) {
+            let supervisor = self.supervisor.clone();
+            let process_id = self.process_id.clone();
+            let reason = format!("Log rule '{}' matched: {}", rule.name, line);
+            // Not awaited here: the restart kills the process this line came from
+            tokio::spawn(async move {
+                let _ = supervisor.restart_because(&process_id, &reason).await;
+            });
+        }
+    }
+
+    fn log(&self, message: &str) {
+        self.supervisor.log_system(&self.log_file, message);
+    }
+}
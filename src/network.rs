@@ -0,0 +1,319 @@
+// Client address resolution (X-Forwarded-For from trusted proxies), CIDR allow/deny lists per route group, and CORS
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use warp::Filter;
+
+use crate::NETWORK_POLICY;
+use crate::ratelimit::{self, RouteGroup};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    // Accepts `10.0.0.0/8`, `fd00::/8` or a bare address for a single host
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let network: IpAddr = address
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid address in CIDR '{}'", value))?;
+        let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max_prefix)
+                .ok_or_else(|| format!("Invalid prefix length in CIDR '{}'", value))?,
+            None => max_prefix,
+        };
+        Ok(Self { network: network.to_canonical(), prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+// A rule given as GROUP=CIDR[,CIDR...], where GROUP `all` applies to every route group
+#[derive(Debug, Clone)]
+pub struct AccessRule {
+    group: Option<RouteGroup>,
+    ranges: Vec<Cidr>,
+}
+
+impl fmt::Display for AccessRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ranges: Vec<String> = self.ranges.iter().map(Cidr::to_string).collect();
+        write!(f, "{}={}", self.group.map(ratelimit::group_name).unwrap_or("all"), ranges.join(","))
+    }
+}
+
+pub fn parse_access_rule(value: &str) -> Result<AccessRule, String> {
+    let (group, ranges) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid rule '{}', expected GROUP=CIDR[,CIDR...]", value))?;
+    let group = match group {
+        "all" => None,
+        name => Some(RouteGroup::parse(name).ok_or_else(|| {
+            format!("Unknown route group '{}', expected public, read, control, admin or all", name)
+        })?),
+    };
+    let ranges = ranges
+        .split(',')
+        .filter(|r| !r.trim().is_empty())
+        .map(Cidr::from_str)
+        .collect::<Result<Vec<_>, _>>()?;
+    if ranges.is_empty() {
+        return Err(format!("Rule '{}' has no address ranges", value));
+    }
+    Ok(AccessRule { group, ranges })
+}
+
+#[derive(Default)]
+pub struct NetworkPolicy {
+    allow: HashMap<RouteGroup, Vec<Cidr>>,
+    deny: HashMap<RouteGroup, Vec<Cidr>>,
+    trusted_proxies: Vec<Cidr>,
+}
+
+const ALL_GROUPS: [RouteGroup; 4] = [RouteGroup::Public, RouteGroup::Read, RouteGroup::Control, RouteGroup::Admin];
+
+impl NetworkPolicy {
+    pub fn new(allow: &[AccessRule], deny: &[AccessRule], trusted_proxies: &[Cidr]) -> Self {
+        fn by_group(rules: &[AccessRule]) -> HashMap<RouteGroup, Vec<Cidr>> {
+            let mut map: HashMap<RouteGroup, Vec<Cidr>> = HashMap::new();
+            for rule in rules {
+                let groups = match rule.group {
+                    Some(group) => vec![group],
+                    None => ALL_GROUPS.to_vec(),
+                };
+                for group in groups {
+                    map.entry(group).or_default().extend(rule.ranges.iter().copied());
+                }
+            }
+            map
+        }
+
+        Self {
+            allow: by_group(allow),
+            deny: by_group(deny),
+            trusted_proxies: trusted_proxies.to_vec(),
+        }
+    }
+
+    // The real client address: the peer, unless it is a trusted proxy, in which case the
+    // right-most X-Forwarded-For entry that isn't itself a trusted proxy. An entry that isn't an
+    // address ends the walk, and the trusted hop that passed it on counts as the client
+    pub fn resolve_client(&self, remote: Option<SocketAddr>, forwarded_for: Option<&str>) -> Option<IpAddr> {
+        let peer = remote?.ip().to_canonical();
+        if !self.is_trusted_proxy(peer) {
+            return Some(peer);
+        }
+
+        let Some(header) = forwarded_for else {
+            return Some(peer);
+        };
+
+        let mut client = peer;
+        for entry in header.split(',').rev() {
+            // Skipping it would let a client hide behind an address it put further left
+            let Ok(ip) = entry.trim().parse::<IpAddr>() else {
+                break;
+            };
+            let ip = ip.to_canonical();
+            client = ip;
+            if !self.is_trusted_proxy(ip) {
+                break;
+            }
+        }
+        Some(client)
+    }
+
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|range| range.contains(ip))
+    }
+
+    // Unix socket clients (no address) are not subject to address rules
+    pub fn check(&self, group: RouteGroup, client: Option<IpAddr>) -> Result<(), String> {
+        let ip = match client {
+            Some(ip) => ip,
+            None => return Ok(()),
+        };
+
+        if self.deny.get(&group).is_some_and(|ranges| ranges.iter().any(|r| r.contains(ip))) {
+            return Err(format!("Access from {} is denied", ip));
+        }
+        match self.allow.get(&group) {
+            Some(ranges) if !ranges.iter().any(|r| r.contains(ip)) => {
+                Err(format!("Access from {} is not allowed for this endpoint", ip))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// The peer address of a TCP connection accepted outside warp's own server, set as a request extension
+#[derive(Clone, Copy)]
+pub struct RemoteAddr(pub SocketAddr);
+
+// Extracts the resolved client address, None for Unix socket clients
+pub fn with_client_ip() -> impl Filter<Extract = (Option<IpAddr>,), Error = warp::Rejection> + Clone {
+    warp::addr::remote()
+        .and(warp::ext::optional::<RemoteAddr>())
+        .and(warp::header::optional::<String>("x-forwarded-for"))
+        .map(|remote: Option<SocketAddr>, accepted: Option<RemoteAddr>, forwarded_for: Option<String>| {
+            let remote = remote.or(accepted.map(|accepted| accepted.0));
+            NETWORK_POLICY.read().unwrap().resolve_client(remote, forwarded_for.as_deref())
+        })
+}
+
+pub fn check_access(group: RouteGroup, client: Option<IpAddr>) -> Result<(), warp::Rejection> {
+    NETWORK_POLICY
+        .read()
+        .unwrap()
+        .check(group, client)
+        .map_err(|message| warp::reject::custom(crate::error::ApiError::Forbidden(message)))
+}
+
+pub struct CorsOptions {
+    pub origins: Vec<String>,
+    pub methods: Vec<String>,
+    pub headers: Vec<String>,
+}
+
+// None when no origins are configured, so no CORS headers are sent and browsers block cross-origin calls
+pub fn build_cors(options: &CorsOptions) -> Result<Option<warp::cors::Builder>, String> {
+    if options.origins.is_empty() {
+        return Ok(None);
+    }
+
+    let mut methods = Vec::new();
+    for method in &options.methods {
+        methods.push(
+            warp::http::Method::from_bytes(method.to_uppercase().as_bytes())
+                .map_err(|_| format!("Invalid CORS method '{}'", method))?,
+        );
+    }
+
+    let mut cors = warp::cors()
+        .allow_methods(methods)
+        .allow_headers(options.headers.iter().map(String::as_str));
+    if options.origins.iter().any(|o| o == "*") {
+        cors = cors.allow_any_origin();
+    } else {
+        // warp panics on an origin it can't parse, so only scheme://host[:port] gets through
+        for origin in &options.origins {
+            if !is_valid_origin(origin) {
+                return Err(format!("Invalid CORS origin '{}', expected scheme://host[:port], e.g. https://example.com", origin));
+            }
+        }
+        cors = cors.allow_origins(options.origins.iter().map(String::as_str));
+    }
+    Ok(Some(cors))
+}
+
+// scheme://host[:port] with nothing after it, the form of a browser's Origin header
+fn is_valid_origin(origin: &str) -> bool {
+    let Some((scheme, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    let host = match authority.rsplit_once(':') {
+        // An IPv6 address has colons of its own, the port comes after the closing bracket
+        Some((host, port)) if !port.contains(']') => {
+            if port.parse::<u16>().is_err() {
+                return false;
+            }
+            host
+        }
+        _ => authority,
+    };
+    valid_scheme
+        && !host.is_empty()
+        && !authority.contains(['/', '?', '#', '@'])
+        && authority.parse::<warp::http::uri::Authority>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cors(origin: &str) -> Result<Option<warp::cors::Builder>, String> {
+        build_cors(&CorsOptions {
+            origins: vec![origin.to_string()],
+            methods: vec!["GET".to_string()],
+            headers: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn accepts_scheme_host_and_port() {
+        for origin in ["https://example.com", "http://localhost:3000", "http://127.0.0.1:8080", "http://[::1]:8080", "http://[::1]"] {
+            assert!(cors(origin).is_ok(), "{}", origin);
+        }
+    }
+
+    #[test]
+    fn rejects_anything_else() {
+        for origin in [
+            "example.com",
+            "https://",
+            "https://example.com/",
+            "https://example.com/app",
+            "https://example.com?x=1",
+            "https://user@example.com",
+            "https://example.com:port",
+            "https://example.com:99999",
+            "://example.com",
+            "https://exa mple.com",
+        ] {
+            assert!(cors(origin).is_err(), "{}", origin);
+        }
+    }
+
+    #[test]
+    fn walks_forwarded_for_to_the_first_untrusted_address() {
+        let proxies = [Cidr::from_str("10.0.0.0/8").unwrap()];
+        let policy = NetworkPolicy::new(&[], &[], &proxies);
+        let proxy: SocketAddr = "10.0.0.1:443".parse().unwrap();
+        let ip = |ip: &str| Some(ip.parse::<IpAddr>().unwrap());
+
+        assert_eq!(policy.resolve_client(Some(proxy), Some("203.0.113.7, 10.0.0.2")), ip("203.0.113.7"));
+        assert_eq!(policy.resolve_client(Some(proxy), None), ip("10.0.0.1"));
+        assert_eq!(policy.resolve_client(Some("198.51.100.1:80".parse().unwrap()), Some("203.0.113.7")), ip("198.51.100.1"));
+        // Garbage stops the walk at the hop that passed it on, instead of reaching the spoofed entry left of it
+        assert_eq!(policy.resolve_client(Some(proxy), Some("127.0.0.1, junk, 10.0.0.2")), ip("10.0.0.2"));
+        assert_eq!(policy.resolve_client(Some(proxy), Some("127.0.0.1, unknown")), ip("10.0.0.1"));
+    }
+}
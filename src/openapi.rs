@@ -0,0 +1,308 @@
+// The endpoint catalog and the OpenAPI 3 document generated from it and the request/response types
+use schemars::JsonSchema;
+use schemars::r#gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use serde_json::json;
+
+use crate::audit::{AuditPage, AuditQuery};
+use crate::backup::{Archive, ExportParams, RestoreRequest, RestoreResponse};
+use crate::config_file::{ReloadParams, ReloadResponse};
+use crate::error::ErrorBody;
+use crate::events::{Event, EventsQuery};
+use crate::history::{HistoryPage, HistoryQuery, Revision, RollbackParams, RollbackResponse};
+use crate::import::{ImportRequest, ImportResponse};
+use crate::keys::{CreateKeyRequest, KeyInfo, KeySecretResponse, Scope};
+#[cfg(unix)]
+use crate::upgrade::UpgradeParams;
+use crate::webhooks::{DeadLettersPage, RedeliverResponse, WebhookTestResponse};
+use crate::server::{
+    InfoResponse, LogsPage, PaginationParams, PatchProcessRequest, PatchProcessResponse, ProcessInputRequest,
+    ProcessSpec,
+};
+use crate::{AddProcessRequest, ApiResponse, ProcessInfo};
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+type QueryFn = fn(&mut SchemaGenerator) -> Vec<serde_json::Value>;
+
+fn schema<T: JsonSchema>(generator: &mut SchemaGenerator) -> Schema {
+    generator.subschema_for::<T>()
+}
+
+// Each field of a query struct becomes a query parameter
+fn query<T: JsonSchema>(generator: &mut SchemaGenerator) -> Vec<serde_json::Value> {
+    let root = generator.root_schema_for::<T>();
+    let Some(object) = root.schema.object else {
+        return Vec::new();
+    };
+    object
+        .properties
+        .iter()
+        .map(|(name, property)| json!({
+            "name": name,
+            "in": "query",
+            "required": object.required.contains(name),
+            "schema": property,
+        }))
+        .collect()
+}
+
+pub struct Endpoint {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub summary: &'static str,
+    // None for public endpoints
+    pub scope: Option<Scope>,
+    // Pre-/v1 paths, kept working but marked deprecated in the spec
+    pub legacy: bool,
+    operation_id: &'static str,
+    request: Option<SchemaFn>,
+    query: Option<QueryFn>,
+    response: SchemaFn,
+}
+
+impl Endpoint {
+    pub fn describe(&self) -> String {
+        let access = match self.scope {
+            Some(scope) => format!("{:?}", scope).to_lowercase(),
+            None => "public".to_string(),
+        };
+        format!("{} {} - {} ({})", self.method, self.path, self.summary, access)
+    }
+}
+
+macro_rules! endpoint {
+    ($method:literal $path:literal, $operation_id:literal, $summary:literal, $scope:expr, $legacy:expr,
+     request: $request:expr, query: $query:expr, response: $response:ty) => {
+        Endpoint {
+            method: $method,
+            path: $path,
+            summary: $summary,
+            scope: $scope,
+            legacy: $legacy,
+            operation_id: $operation_id,
+            request: $request,
+            query: $query,
+            response: schema::<$response>,
+        }
+    };
+}
+
+pub const ENDPOINTS: &[Endpoint] = &[
+    // Versioned API
+    endpoint!("GET" "/v1/processes", "listProcesses", "List processes", Some(Scope::Read), false,
+        request: None, query: None, response: Vec<ProcessInfo>),
+    endpoint!("POST" "/v1/processes", "createProcess", "Add a process", Some(Scope::Control), false,
+        request: Some(schema::<AddProcessRequest>), query: None, response: ApiResponse),
+    endpoint!("GET" "/v1/processes/{id}", "getProcess", "Get a process", Some(Scope::Read), false,
+        request: None, query: None, response: ProcessInfo),
+    endpoint!("PUT" "/v1/processes/{id}", "putProcess", "Create or replace a process, restarting it", Some(Scope::Control), false,
+        request: Some(schema::<ProcessSpec>), query: None, response: ApiResponse),
+    endpoint!("PATCH" "/v1/processes/{id}", "patchProcess", "Change some fields of a process config", Some(Scope::Control), false,
+        request: Some(schema::<PatchProcessRequest>), query: None, response: PatchProcessResponse),
+    endpoint!("DELETE" "/v1/processes/{id}", "deleteProcess", "Stop and remove a process", Some(Scope::Control), false,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/v1/processes/{id}/actions/restart", "restartProcess", "Restart a process", Some(Scope::Control), false,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/v1/processes/{id}/actions/stop", "stopProcess", "Stop a process", Some(Scope::Control), false,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/v1/processes/{id}/input", "sendInput", "Send input to a process", Some(Scope::Input), false,
+        request: Some(schema::<ProcessInputRequest>), query: None, response: ApiResponse),
+    endpoint!("GET" "/v1/processes/{id}/logs", "getLogs", "Get paginated process logs, newest first", Some(Scope::Logs), false,
+        request: None, query: Some(query::<PaginationParams>), response: LogsPage),
+    endpoint!("DELETE" "/v1/processes/{id}/logs", "clearLogs", "Clear a process log", Some(Scope::Control), false,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/v1/actions/restart-all", "restartAll", "Restart all processes", Some(Scope::Control), false,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/v1/actions/stop-all", "stopAll", "Stop all processes", Some(Scope::Control), false,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/v1/actions/shutdown", "shutdown", "Shut down the supervisor", Some(Scope::Admin), false,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/v1/actions/reload", "reloadConfig", "Reconcile processes and settings with the --config file", Some(Scope::Admin), false,
+        request: None, query: Some(query::<ReloadParams>), response: ReloadResponse),
+    endpoint!("POST" "/v1/actions/import", "importProcesses", "Add the processes of a Procfile, supervisord config or systemd unit", Some(Scope::Control), false,
+        request: Some(schema::<ImportRequest>), query: None, response: ImportResponse),
+    endpoint!("GET" "/v1/export", "exportState", "Download an archive of all process configs and settings", Some(Scope::Admin), false,
+        request: None, query: Some(query::<ExportParams>), response: Archive),
+    endpoint!("POST" "/v1/actions/restore", "restoreState", "Restore an archive from GET /v1/export", Some(Scope::Admin), false,
+        request: Some(schema::<RestoreRequest>), query: None, response: RestoreResponse),
+    endpoint!("GET" "/v1/config/history", "getConfigHistory", "List revisions of the saved process config, newest first", Some(Scope::Admin), false,
+        request: None, query: Some(query::<HistoryQuery>), response: HistoryPage),
+    endpoint!("GET" "/v1/config/history/{rev}", "getConfigRevision", "Get a config revision with the full saved config", Some(Scope::Admin), false,
+        request: None, query: None, response: Revision),
+    endpoint!("POST" "/v1/config/history/{rev}/actions/rollback", "rollbackConfig", "Restore the saved config of a revision and reconcile processes", Some(Scope::Admin), false,
+        request: None, query: Some(query::<RollbackParams>), response: RollbackResponse),
+    #[cfg(unix)]
+    endpoint!("POST" "/v1/actions/upgrade", "upgrade", "Re-exec a new exeio binary, keeping processes and the listening socket", Some(Scope::Admin), false,
+        request: None, query: Some(query::<UpgradeParams>), response: ApiResponse),
+    endpoint!("GET" "/v1/events", "streamEvents", "Stream process lifecycle events as server-sent events, or over a WebSocket", Some(Scope::Read), false,
+        request: None, query: Some(query::<EventsQuery>), response: Event),
+    endpoint!("POST" "/v1/webhooks/{name}/actions/test", "testWebhook", "Send a test event to a webhook once and report its answer", Some(Scope::Admin), false,
+        request: None, query: None, response: WebhookTestResponse),
+    endpoint!("GET" "/v1/webhooks/dead-letters", "listWebhookDeadLetters", "List webhook deliveries that ran out of retries", Some(Scope::Admin), false,
+        request: None, query: None, response: DeadLettersPage),
+    endpoint!("POST" "/v1/webhooks/dead-letters/actions/redeliver", "redeliverWebhooks", "Send every dead letter once more, keeping the ones that still fail", Some(Scope::Admin), false,
+        request: None, query: None, response: RedeliverResponse),
+    endpoint!("POST" "/v1/email/actions/test", "testEmail", "Send a test email with the [email] settings of the config file", Some(Scope::Admin), false,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("GET" "/v1/info", "getInfo", "Supervisor information", None, false,
+        request: None, query: None, response: InfoResponse),
+    endpoint!("GET" "/v1/openapi.json", "getOpenApi", "This OpenAPI document", None, false,
+        request: None, query: None, response: serde_json::Value),
+    endpoint!("GET" "/v1/keys", "listKeys", "List API keys", Some(Scope::Admin), false,
+        request: None, query: None, response: Vec<KeyInfo>),
+    endpoint!("POST" "/v1/keys", "createKey", "Create an API key, the secret is only returned once", Some(Scope::Admin), false,
+        request: Some(schema::<CreateKeyRequest>), query: None, response: KeySecretResponse),
+    endpoint!("DELETE" "/v1/keys/{name}", "revokeKey", "Revoke an API key", Some(Scope::Admin), false,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/v1/keys/{name}/actions/rotate", "rotateKey", "Rotate an API key", Some(Scope::Admin), false,
+        request: None, query: None, response: KeySecretResponse),
+    endpoint!("GET" "/v1/audit", "getAuditLog", "Query the audit log, newest first", Some(Scope::Admin), false,
+        request: None, query: Some(query::<AuditQuery>), response: AuditPage),
+    // Legacy paths
+    endpoint!("POST" "/add", "legacyAddProcess", "Add new process", Some(Scope::Control), true,
+        request: Some(schema::<AddProcessRequest>), query: None, response: ApiResponse),
+    endpoint!("PATCH" "/process/{id}", "legacyPatchProcess", "Update process config", Some(Scope::Control), true,
+        request: Some(schema::<PatchProcessRequest>), query: None, response: PatchProcessResponse),
+    endpoint!("POST" "/restart/{id}", "legacyRestartProcess", "Restart process", Some(Scope::Control), true,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/stop/{id}", "legacyStopProcess", "Stop process", Some(Scope::Control), true,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/remove/{id}", "legacyRemoveProcess", "Remove process", Some(Scope::Control), true,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/restart-all", "legacyRestartAll", "Restart all processes", Some(Scope::Control), true,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/stop-all", "legacyStopAll", "Stop all processes", Some(Scope::Control), true,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/input/{id}", "legacySendInput", "Send input to process", Some(Scope::Input), true,
+        request: Some(schema::<ProcessInputRequest>), query: None, response: ApiResponse),
+    endpoint!("POST" "/clear-log/{id}", "legacyClearLog", "Clear process log", Some(Scope::Control), true,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("GET" "/list", "legacyListProcesses", "List all processes", Some(Scope::Read), true,
+        request: None, query: None, response: Vec<ProcessInfo>),
+    endpoint!("GET" "/events", "legacyStreamEvents", "Stream process events", Some(Scope::Read), true,
+        request: None, query: Some(query::<EventsQuery>), response: Event),
+    endpoint!("POST" "/webhooks/{name}/test", "legacyTestWebhook", "Test webhook", Some(Scope::Admin), true,
+        request: None, query: None, response: WebhookTestResponse),
+    endpoint!("GET" "/webhooks/dead-letters", "legacyListWebhookDeadLetters", "List webhook dead letters", Some(Scope::Admin), true,
+        request: None, query: None, response: DeadLettersPage),
+    endpoint!("POST" "/webhooks/dead-letters/redeliver", "legacyRedeliverWebhooks", "Redeliver webhook dead letters", Some(Scope::Admin), true,
+        request: None, query: None, response: RedeliverResponse),
+    endpoint!("GET" "/info", "legacyGetInfo", "Get supervisor information", None, true,
+        request: None, query: None, response: InfoResponse),
+    endpoint!("GET" "/logs/{id}", "legacyGetLogs", "Get paginated process logs", Some(Scope::Logs), true,
+        request: None, query: Some(query::<PaginationParams>), response: LogsPage),
+    endpoint!("POST" "/shutdown", "legacyShutdown", "Shutdown supervisor", Some(Scope::Admin), true,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/reload", "legacyReloadConfig", "Reload config file", Some(Scope::Admin), true,
+        request: None, query: Some(query::<ReloadParams>), response: ReloadResponse),
+    endpoint!("POST" "/import", "legacyImportProcesses", "Import processes", Some(Scope::Control), true,
+        request: Some(schema::<ImportRequest>), query: None, response: ImportResponse),
+    endpoint!("GET" "/export", "legacyExportState", "Export supervisor state", Some(Scope::Admin), true,
+        request: None, query: Some(query::<ExportParams>), response: Archive),
+    endpoint!("POST" "/restore", "legacyRestoreState", "Restore supervisor state", Some(Scope::Admin), true,
+        request: Some(schema::<RestoreRequest>), query: None, response: RestoreResponse),
+    endpoint!("GET" "/config/history", "legacyGetConfigHistory", "Config history", Some(Scope::Admin), true,
+        request: None, query: Some(query::<HistoryQuery>), response: HistoryPage),
+    endpoint!("GET" "/config/history/{rev}", "legacyGetConfigRevision", "Config revision", Some(Scope::Admin), true,
+        request: None, query: None, response: Revision),
+    endpoint!("POST" "/config/rollback/{rev}", "legacyRollbackConfig", "Roll back config", Some(Scope::Admin), true,
+        request: None, query: Some(query::<RollbackParams>), response: RollbackResponse),
+    #[cfg(unix)]
+    endpoint!("POST" "/upgrade", "legacyUpgrade", "Upgrade supervisor in place", Some(Scope::Admin), true,
+        request: None, query: Some(query::<UpgradeParams>), response: ApiResponse),
+    endpoint!("GET" "/keys", "legacyListKeys", "List API keys", Some(Scope::Admin), true,
+        request: None, query: None, response: Vec<KeyInfo>),
+    endpoint!("POST" "/keys", "legacyCreateKey", "Create API key", Some(Scope::Admin), true,
+        request: Some(schema::<CreateKeyRequest>), query: None, response: KeySecretResponse),
+    endpoint!("POST" "/keys/{name}/revoke", "legacyRevokeKey", "Revoke API key", Some(Scope::Admin), true,
+        request: None, query: None, response: ApiResponse),
+    endpoint!("POST" "/keys/{name}/rotate", "legacyRotateKey", "Rotate API key", Some(Scope::Admin), true,
+        request: None, query: None, response: KeySecretResponse),
+    endpoint!("GET" "/audit", "legacyGetAuditLog", "Query audit log", Some(Scope::Admin), true,
+        request: None, query: Some(query::<AuditQuery>), response: AuditPage),
+];
+
+fn path_parameters(path: &str) -> Vec<serde_json::Value> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| json!({
+            "name": name,
+            "in": "path",
+            "required": true,
+            // Revision numbers are the only non-string path parameters
+            "schema": if name == "rev" { json!({ "type": "integer", "minimum": 1 }) } else { json!({ "type": "string" }) },
+        }))
+        .collect()
+}
+
+pub fn spec() -> serde_json::Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let mut paths = serde_json::Map::new();
+
+    for endpoint in ENDPOINTS {
+        let mut parameters = path_parameters(endpoint.path);
+        if let Some(query) = endpoint.query {
+            parameters.extend(query(&mut generator));
+        }
+
+        let mut operation = json!({
+            "operationId": endpoint.operation_id,
+            "summary": endpoint.summary,
+            "parameters": parameters,
+            "responses": {
+                "200": {
+                    "description": "Success",
+                    "content": { "application/json": { "schema": (endpoint.response)(&mut generator) } },
+                },
+                "default": {
+                    "description": "Error, see `code` for the reason",
+                    "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ErrorBody" } } },
+                },
+            },
+        });
+        match endpoint.scope {
+            Some(scope) => {
+                operation["security"] = json!([{ "apiKey": [] }]);
+                operation["x-exeio-scope"] = json!(scope);
+            }
+            None => operation["security"] = json!([]),
+        }
+        if let Some(request) = endpoint.request {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": request(&mut generator) } },
+            });
+        }
+        if endpoint.legacy {
+            operation["deprecated"] = json!(true);
+        }
+
+        let item = paths
+            .entry(endpoint.path)
+            .or_insert_with(|| json!({}));
+        item[endpoint.method.to_lowercase()] = operation;
+    }
+
+    generator.subschema_for::<ErrorBody>();
+    let schemas = generator.take_definitions();
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "exeio - Process Supervisor",
+            "version": crate::EXEIO_VERSION,
+            "description": "Scoped API keys go in the `exeio-api-key` header. Paths without the /v1 prefix are the legacy API.",
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "apiKey": { "type": "apiKey", "in": "header", "name": "exeio-api-key" },
+            },
+        },
+    })
+}
+
+pub async fn handle_openapi() -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&spec()))
+}
@@ -0,0 +1,446 @@
+// Token-bucket rate limits per client IP and per API key, and lockout after repeated failed authentication
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use warp::Filter;
+
+use crate::LocalPeer;
+use crate::keys::Scope;
+
+// Routes are rate limited in groups, so cheap reads can get a bigger budget than control calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteGroup {
+    Public,
+    Read,
+    Control,
+    Admin,
+}
+
+impl RouteGroup {
+    pub fn for_scope(scope: Scope) -> Self {
+        match scope {
+            Scope::Read | Scope::Logs => RouteGroup::Read,
+            Scope::Input | Scope::Control => RouteGroup::Control,
+            Scope::Admin => RouteGroup::Admin,
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "public" => Some(RouteGroup::Public),
+            "read" => Some(RouteGroup::Read),
+            "control" => Some(RouteGroup::Control),
+            "admin" => Some(RouteGroup::Admin),
+            _ => None,
+        }
+    }
+}
+
+// `requests` per `per_seconds`, which is also the burst size
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    requests: f64,
+    per_seconds: f64,
+}
+
+impl RateLimit {
+    fn refill_per_second(&self) -> f64 {
+        self.requests / self.per_seconds
+    }
+}
+
+// Parses `GROUP=REQUESTS/SECONDS` or `GROUP=off`, as given to --rate-limit
+pub fn parse_rate_limit(value: &str) -> Result<(RouteGroup, Option<RateLimit>), String> {
+    let (group, limit) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid rate limit '{}', expected GROUP=REQUESTS/SECONDS", value))?;
+    let group = RouteGroup::parse(group)
+        .ok_or_else(|| format!("Unknown route group '{}', expected public, read, control or admin", group))?;
+
+    if limit == "off" {
+        return Ok((group, None));
+    }
+
+    let (requests, seconds) = limit
+        .split_once('/')
+        .and_then(|(r, s)| Some((r.parse::<u32>().ok()?, s.parse::<u32>().ok()?)))
+        .filter(|(r, s)| *r > 0 && *s > 0)
+        .ok_or_else(|| format!("Invalid rate limit '{}', expected GROUP=REQUESTS/SECONDS", value))?;
+
+    Ok((group, Some(RateLimit { requests: requests as f64, per_seconds: seconds as f64 })))
+}
+
+// The inverse of parse_rate_limit
+pub fn format_rate_limit(group: RouteGroup, limit: Option<RateLimit>) -> String {
+    match limit {
+        Some(limit) => format!("{}={}/{}", group_name(group), limit.requests, limit.per_seconds),
+        None => format!("{}=off", group_name(group)),
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &RateLimit) -> Self {
+        Self { tokens: limit.requests, last_refill: Instant::now() }
+    }
+
+    // Takes a token, or returns how long until one is available
+    fn try_take(&mut self, limit: &RateLimit) -> Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.refill_per_second()).min(limit.requests);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / limit.refill_per_second()))
+        }
+    }
+
+    fn is_full(&self, limit: &RateLimit) -> bool {
+        self.tokens + self.last_refill.elapsed().as_secs_f64() * limit.refill_per_second() >= limit.requests
+    }
+}
+
+struct FailureRecord {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+impl FailureRecord {
+    // Failures older than the lockout duration are forgotten, as are lockouts that have run out
+    fn is_expired(&self, now: Instant, duration: Duration) -> bool {
+        match self.locked_until {
+            Some(locked_until) => locked_until <= now,
+            None => now.duration_since(self.last_failure) > duration,
+        }
+    }
+}
+
+pub struct LockoutPolicy {
+    pub max_failures: u32,
+    pub duration: Duration,
+}
+
+#[derive(Default)]
+struct Counters {
+    allowed: AtomicU64,
+    limited_by_ip: AtomicU64,
+    limited_by_key: AtomicU64,
+    auth_failures: AtomicU64,
+    lockouts: AtomicU64,
+    rejected_while_locked: AtomicU64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RateLimitStats {
+    allowed: u64,
+    limited_by_ip: u64,
+    limited_by_key: u64,
+    auth_failures: u64,
+    lockouts: u64,
+    rejected_while_locked: u64,
+    currently_locked_out: usize,
+    limits: HashMap<RouteGroup, String>,
+}
+
+fn default_limits() -> HashMap<RouteGroup, RateLimit> {
+    let defaults = [
+        (RouteGroup::Public, 60),
+        (RouteGroup::Read, 300),
+        (RouteGroup::Control, 60),
+        (RouteGroup::Admin, 30),
+    ];
+    defaults
+        .into_iter()
+        .map(|(group, requests)| (group, RateLimit { requests: requests as f64, per_seconds: 60.0 }))
+        .collect()
+}
+
+// Past this many tracked buckets, idle (full) ones are dropped, then the least recently used
+const MAX_TRACKED_BUCKETS: usize = 4096;
+// Same for failure records, oldest failure first, so a flood of sources can't grow them without bound
+const MAX_TRACKED_FAILURES: usize = 4096;
+
+pub struct RateLimiter {
+    limits: RwLock<HashMap<RouteGroup, RateLimit>>,
+    lockout: RwLock<LockoutPolicy>,
+    buckets: Mutex<HashMap<(RouteGroup, String), TokenBucket>>,
+    failures: Mutex<HashMap<String, FailureRecord>>,
+    counters: Counters,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {
+            limits: RwLock::new(default_limits()),
+            lockout: RwLock::new(LockoutPolicy { max_failures: 10, duration: Duration::from_secs(300) }),
+            buckets: Mutex::new(HashMap::new()),
+            failures: Mutex::new(HashMap::new()),
+            counters: Counters::default(),
+        }
+    }
+
+    // Applies the overrides on top of the defaults, so it can be called again on config reload
+    pub fn configure(&self, overrides: &[(RouteGroup, Option<RateLimit>)], lockout: LockoutPolicy) {
+        let mut limits = default_limits();
+        for (group, limit) in overrides {
+            match limit {
+                Some(limit) => limits.insert(*group, *limit),
+                None => limits.remove(group),
+            };
+        }
+        *self.limits.write().unwrap() = limits;
+        *self.lockout.write().unwrap() = lockout;
+    }
+
+    fn take(&self, group: RouteGroup, client: String) -> Result<(), Duration> {
+        let limit = match self.limits.read().unwrap().get(&group) {
+            Some(limit) => *limit,
+            None => return Ok(()),
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let key = (group, client);
+        if buckets.len() >= MAX_TRACKED_BUCKETS && !buckets.contains_key(&key) {
+            let limits = self.limits.read().unwrap();
+            buckets.retain(|(g, _), bucket| limits.get(g).is_some_and(|l| !bucket.is_full(l)));
+            while buckets.len() >= MAX_TRACKED_BUCKETS {
+                let Some(oldest) = buckets.iter().min_by_key(|(_, bucket)| bucket.last_refill).map(|(key, _)| key.clone()) else {
+                    break;
+                };
+                buckets.remove(&oldest);
+            }
+        }
+        buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(&limit))
+            .try_take(&limit)
+    }
+
+    pub fn check_ip(&self, group: RouteGroup, source: &str) -> Result<(), TooManyRequests> {
+        self.take(group, format!("ip:{}", source)).map_err(|retry_after| {
+            self.counters.limited_by_ip.fetch_add(1, Ordering::Relaxed);
+            TooManyRequests::new(format!("Rate limit exceeded for {} requests from this address", group_name(group)), retry_after)
+        })
+    }
+
+    pub fn check_key(&self, group: RouteGroup, key_name: &str) -> Result<(), TooManyRequests> {
+        self.take(group, format!("key:{}", key_name))
+            .map(|_| {
+                self.counters.allowed.fetch_add(1, Ordering::Relaxed);
+            })
+            .map_err(|retry_after| {
+                self.counters.limited_by_key.fetch_add(1, Ordering::Relaxed);
+                TooManyRequests::new(format!("Rate limit exceeded for {} requests with key '{}'", group_name(group), key_name), retry_after)
+            })
+    }
+
+    pub fn check_lockout(&self, source: &str) -> Result<(), TooManyRequests> {
+        let mut failures = self.failures.lock().unwrap();
+        if let Some(record) = failures.get(source)
+            && let Some(locked_until) = record.locked_until {
+                let now = Instant::now();
+                if locked_until > now {
+                    self.counters.rejected_while_locked.fetch_add(1, Ordering::Relaxed);
+                    return Err(TooManyRequests::new(
+                        "Too many failed authentication attempts, try again later".to_string(),
+                        locked_until - now,
+                    ));
+                }
+                failures.remove(source);
+        }
+        Ok(())
+    }
+
+    pub fn record_failure(&self, source: &str) {
+        self.counters.auth_failures.fetch_add(1, Ordering::Relaxed);
+        let lockout = self.lockout.read().unwrap();
+        if lockout.max_failures == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() >= MAX_TRACKED_FAILURES && !failures.contains_key(source) {
+            failures.retain(|_, record| !record.is_expired(now, lockout.duration));
+            while failures.len() >= MAX_TRACKED_FAILURES {
+                let Some(oldest) = failures.iter().min_by_key(|(_, record)| record.last_failure).map(|(source, _)| source.clone()) else {
+                    break;
+                };
+                failures.remove(&oldest);
+            }
+        }
+        let record = failures
+            .entry(source.to_string())
+            .or_insert(FailureRecord { count: 0, last_failure: now, locked_until: None });
+        if record.is_expired(now, lockout.duration) {
+            *record = FailureRecord { count: 0, last_failure: now, locked_until: None };
+        }
+        record.count += 1;
+        record.last_failure = now;
+        if record.count >= lockout.max_failures && record.locked_until.is_none() {
+            record.locked_until = Some(now + lockout.duration);
+            self.counters.lockouts.fetch_add(1, Ordering::Relaxed);
+            eprintln!("exeio: locking out {} for {}s after {} failed authentication attempts",
+                source, lockout.duration.as_secs(), record.count);
+        }
+    }
+
+    pub fn record_success(&self, source: &str) {
+        let mut failures = self.failures.lock().unwrap();
+        if failures.get(source).is_some_and(|r| r.locked_until.is_none()) {
+            failures.remove(source);
+        }
+    }
+
+    pub fn stats(&self) -> RateLimitStats {
+        let now = Instant::now();
+        let currently_locked_out = {
+            let duration = self.lockout.read().unwrap().duration;
+            let mut failures = self.failures.lock().unwrap();
+            // Expired records are dropped here as well as when the table fills up
+            failures.retain(|_, record| !record.is_expired(now, duration));
+            failures.values().filter(|r| r.locked_until.is_some()).count()
+        };
+        let limits = self.limits.read().unwrap()
+            .iter()
+            .map(|(group, limit)| (*group, format!("{}/{}s", limit.requests, limit.per_seconds)))
+            .collect();
+
+        RateLimitStats {
+            allowed: self.counters.allowed.load(Ordering::Relaxed),
+            limited_by_ip: self.counters.limited_by_ip.load(Ordering::Relaxed),
+            limited_by_key: self.counters.limited_by_key.load(Ordering::Relaxed),
+            auth_failures: self.counters.auth_failures.load(Ordering::Relaxed),
+            lockouts: self.counters.lockouts.load(Ordering::Relaxed),
+            rejected_while_locked: self.counters.rejected_while_locked.load(Ordering::Relaxed),
+            currently_locked_out,
+            limits,
+        }
+    }
+}
+
+pub fn group_name(group: RouteGroup) -> &'static str {
+    match group {
+        RouteGroup::Public => "public",
+        RouteGroup::Read => "read",
+        RouteGroup::Control => "control",
+        RouteGroup::Admin => "admin",
+    }
+}
+
+#[derive(Debug)]
+pub struct TooManyRequests {
+    pub message: String,
+    pub retry_after: Duration,
+}
+
+impl TooManyRequests {
+    fn new(message: String, retry_after: Duration) -> Self {
+        Self { message, retry_after }
+    }
+
+    // Whole seconds for the Retry-After header, rounded up
+    pub fn retry_after_secs(&self) -> u64 {
+        self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0)
+    }
+}
+
+impl warp::reject::Reject for TooManyRequests {}
+
+// Identifies the client for per-IP limits and lockout; Unix socket clients are told apart by uid
+pub fn client_source(client: Option<IpAddr>, peer: Option<&LocalPeer>) -> String {
+    match (client, peer) {
+        (Some(ip), _) => ip.to_string(),
+        (None, Some(peer)) => format!("unix:uid={}", peer.uid),
+        (None, None) => "unix".to_string(),
+    }
+}
+
+// Address rules and per-IP limit for routes without authentication, such as /info
+pub fn with_rate_limit(group: RouteGroup) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    crate::network::with_client_ip()
+        .and(warp::ext::optional::<LocalPeer>())
+        .and_then(move |client: Option<IpAddr>, peer: Option<LocalPeer>| async move {
+            crate::network::check_access(group, client)?;
+            crate::RATE_LIMITER
+                .check_ip(group, &client_source(client, peer.as_ref()))
+                .map_err(warp::reject::custom)
+        })
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(max_failures: u32, duration: Duration) -> RateLimiter {
+        let limiter = RateLimiter::new();
+        limiter.configure(&[], LockoutPolicy { max_failures, duration });
+        limiter
+    }
+
+    #[test]
+    fn locks_out_after_max_failures() {
+        let limiter = limiter(3, Duration::from_secs(60));
+        for _ in 0..2 {
+            limiter.record_failure("10.0.0.1");
+            assert!(limiter.check_lockout("10.0.0.1").is_ok());
+        }
+        limiter.record_failure("10.0.0.1");
+        assert!(limiter.check_lockout("10.0.0.1").is_err());
+        assert!(limiter.check_lockout("10.0.0.2").is_ok());
+    }
+
+    #[test]
+    fn forgets_failures_older_than_the_lockout() {
+        let limiter = limiter(2, Duration::from_millis(20));
+        limiter.record_failure("10.0.0.1");
+        std::thread::sleep(Duration::from_millis(40));
+        limiter.record_failure("10.0.0.1");
+        assert!(limiter.check_lockout("10.0.0.1").is_ok());
+        assert_eq!(limiter.failures.lock().unwrap()["10.0.0.1"].count, 1);
+    }
+
+    #[test]
+    fn caps_tracked_failures() {
+        let limiter = limiter(10, Duration::from_secs(60));
+        for i in 0..MAX_TRACKED_FAILURES + 10 {
+            limiter.record_failure(&format!("source-{}", i));
+        }
+        let failures = limiter.failures.lock().unwrap();
+        assert_eq!(failures.len(), MAX_TRACKED_FAILURES);
+        assert!(failures.contains_key(&format!("source-{}", MAX_TRACKED_FAILURES + 9)));
+    }
+
+    #[test]
+    fn caps_tracked_buckets() {
+        let limiter = RateLimiter::new();
+        for i in 0..MAX_TRACKED_BUCKETS + 10 {
+            let _ = limiter.check_ip(RouteGroup::Read, &format!("10.0.{}.{}", i / 256, i % 256));
+        }
+        assert!(limiter.buckets.lock().unwrap().len() <= MAX_TRACKED_BUCKETS);
+    }
+
+    #[test]
+    fn unix_clients_are_keyed_by_uid() {
+        let peer = LocalPeer { uid: 1000, trusted: false };
+        assert_eq!(client_source(None, Some(&peer)), "unix:uid=1000");
+        assert_eq!(client_source(None, None), "unix");
+        assert_eq!(client_source(Some("127.0.0.1".parse().unwrap()), Some(&peer)), "127.0.0.1");
+    }
+}
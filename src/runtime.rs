@@ -0,0 +1,376 @@
+// Runtime state that lets a restarted supervisor adopt the still-running children of the previous one
+// instead of starting second copies of them
+#[cfg(feature = "server")]
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+use tokio::net::unix::pipe;
+
+#[cfg(feature = "server")]
+use crate::events::EventKind;
+#[cfg(feature = "server")]
+use crate::{ManagedProcess, ProcessStatus, actor};
+use crate::{ProcessConfig, ProcessResources, Supervisor};
+
+#[derive(Serialize, Deserialize)]
+struct RuntimeEntry {
+    config: ProcessConfig,
+    pid: u32,
+    // From /proc/<pid>/stat, so a recycled pid isn't mistaken for the process
+    start_time: u64,
+    run_count: u64,
+    last_run: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// Where the exeio binary keeps its runtime state
+#[cfg(feature = "server")]
+pub fn default_dir() -> PathBuf {
+    crate::get_lock_file_path().with_file_name("run")
+}
+
+// The supervisor's runtime directory, created if need be; None when it was built without one, and then keeps
+// no pipes or state
+pub fn runtime_dir(supervisor: &Supervisor) -> Option<PathBuf> {
+    let dir = supervisor.runtime_dir()?.to_path_buf();
+    std::fs::create_dir_all(&dir).unwrap_or_else(|e| {
+        eprintln!("Failed to create runtime directory: {}", e);
+    });
+    Some(dir)
+}
+
+// Field 22 of /proc/<pid>/stat, in clock ticks since boot; None once the process is gone
+pub fn start_time(pid: u32) -> Option<u64> {
+    parse_start_time(&std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?)
+}
+
+fn parse_start_time(stat: &str) -> Option<u64> {
+    // The command name in field 2 may contain spaces and parentheses, so count from the last ')'
+    let mut fields = stat.rsplit_once(')')?.1.split_whitespace();
+    if fields.next()? == "Z" {
+        return None;
+    }
+    fields.nth(18)?.parse().ok()
+}
+
+// utime and stime from /proc/<pid>/stat and the resident set from /proc/<pid>/statm
+pub fn resources(pid: u32) -> Option<ProcessResources> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let ticks: u64 = fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?;
+    let statm = std::fs::read_to_string(format!("/proc/{}/statm", pid)).ok()?;
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+
+    let (ticks_per_second, page_size) = unsafe { (libc::sysconf(libc::_SC_CLK_TCK), libc::sysconf(libc::_SC_PAGESIZE)) };
+    if ticks_per_second <= 0 || page_size <= 0 {
+        return None;
+    }
+    Some(ProcessResources {
+        cpu_seconds: ticks as f64 / ticks_per_second as f64,
+        memory_bytes: resident_pages * page_size as u64,
+    })
+}
+
+// The stdin, stdout and stderr of a regular process, as named pipes in the runtime directory
+pub struct Fifos {
+    stdin: PathBuf,
+    stdout: PathBuf,
+    stderr: PathBuf,
+}
+
+impl Fifos {
+    fn paths(dir: &Path, id: &str) -> Self {
+        Self {
+            stdin: dir.join(format!("{}.stdin", id)),
+            stdout: dir.join(format!("{}.stdout", id)),
+            stderr: dir.join(format!("{}.stderr", id)),
+        }
+    }
+
+    fn create(dir: &Path, id: &str) -> io::Result<Self> {
+        let fifos = Self::paths(dir, id);
+        for path in [&fifos.stdin, &fifos.stdout, &fifos.stderr] {
+            // A previous run of the process may still hold the old ones, it keeps them after the unlink
+            let _ = std::fs::remove_file(path);
+            let c_path = CString::new(path.as_os_str().as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(fifos)
+    }
+
+    // Points the command's stdio at new pipes for process `id`, or at ordinary pipes if the supervisor has no
+    // runtime directory or that fails
+    pub fn attach(cmd: &mut tokio::process::Command, supervisor: &Supervisor, id: &str) -> Option<Self> {
+        let Some(dir) = runtime_dir(supervisor) else {
+            cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+            return None;
+        };
+        match Self::create(&dir, id).and_then(|fifos| {
+            // The child opens its ends read-write, so each pipe always has both a reader and a writer:
+            // output doesn't fail with EPIPE while no supervisor is attached and stdin never reaches end of file
+            let open = |path: &PathBuf| OpenOptions::new().read(true).write(true).open(path);
+            let stdio = (open(&fifos.stdin)?, open(&fifos.stdout)?, open(&fifos.stderr)?);
+            Ok((fifos, stdio))
+        }) {
+            Ok((fifos, (stdin, stdout, stderr))) => {
+                cmd.stdin(Stdio::from(stdin)).stdout(Stdio::from(stdout)).stderr(Stdio::from(stderr));
+                Some(fifos)
+            }
+            Err(e) => {
+                eprintln!("Failed to create pipes for process {}, it won't survive a supervisor restart: {}", id, e);
+                cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+                None
+            }
+        }
+    }
+
+    // The supervisor's ends: a writer for stdin and readers for stdout and stderr, for the process's actor
+    pub fn open(&self) -> io::Result<(pipe::Sender, pipe::Receiver, pipe::Receiver)> {
+        // The child already has every pipe open, so opening never waits for the other end
+        let options = pipe::OpenOptions::new();
+        Ok((options.open_sender(&self.stdin)?, options.open_receiver(&self.stdout)?, options.open_receiver(&self.stderr)?))
+    }
+
+    // Whether process `id` was started with pipes in the runtime directory, so another supervisor can reattach
+    // to its output
+    #[cfg(feature = "server")]
+    pub fn exist(supervisor: &Supervisor, id: &str) -> bool {
+        use std::os::unix::fs::FileTypeExt;
+
+        let Some(dir) = supervisor.runtime_dir() else {
+            return false;
+        };
+        let fifos = Self::paths(dir, id);
+        [&fifos.stdin, &fifos.stdout, &fifos.stderr]
+            .iter()
+            .all(|path| std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo()))
+    }
+
+    #[cfg(feature = "server")]
+    fn remove(&self) {
+        for path in [&self.stdin, &self.stdout, &self.stderr] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// Replaces `path` through a temp file created owner-only, since the state holds the configs with their arguments
+pub fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let temp_path = path.with_extension("json.tmp");
+    // A leftover one would keep its mode
+    let _ = std::fs::remove_file(&temp_path);
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&temp_path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .and_then(|_| std::fs::rename(&temp_path, path))
+}
+
+// Rewrites the state file with every regular process that has a live child; called whenever one starts or exits
+pub fn save(supervisor: &Supervisor) {
+    let Some(dir) = runtime_dir(supervisor) else {
+        return;
+    };
+    let entries: Vec<RuntimeEntry> = supervisor
+        .process_map()
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|managed_process| !managed_process.config.periodic)
+        .filter_map(|managed_process| {
+            let pid = managed_process.pid?;
+            Some(RuntimeEntry {
+                config: managed_process.config.clone(),
+                pid,
+                start_time: start_time(pid)?,
+                run_count: managed_process.run_count,
+                last_run: managed_process.last_run,
+            })
+        })
+        .collect();
+
+    let _guard = supervisor.state_lock().lock().unwrap();
+    let path = dir.join("state.json");
+    let written = serde_json::to_string_pretty(&entries)
+        .map_err(io::Error::other)
+        .and_then(|json| write_private(&path, &json));
+    if let Err(e) = written {
+        eprintln!("Failed to write runtime state {}: {}", path.display(), e);
+    }
+}
+
+// Takes over the processes a previous supervisor left running. Returns their ids; loading the saved configs
+// skips them, so they aren't started twice
+#[cfg(feature = "server")]
+pub fn adopt(supervisor: &Supervisor) -> HashSet<String> {
+    let processes = supervisor.process_map();
+    let Some(dir) = runtime_dir(supervisor) else {
+        return HashSet::new();
+    };
+    let entries: Vec<RuntimeEntry> = match std::fs::read_to_string(dir.join("state.json")) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => return HashSet::new(),
+    };
+    let saved = supervisor.saved().load_configs();
+
+    let mut adopted = HashSet::new();
+    for entry in entries {
+        if start_time(entry.pid) != Some(entry.start_time) || adopted.contains(&entry.config.id) {
+            // Reaps it if it exited while an upgrade exec'd this binary, it's still our child then
+            unsafe { libc::waitpid(entry.pid as libc::pid_t, std::ptr::null_mut(), libc::WNOHANG) };
+            continue;
+        }
+        let config = entry.config;
+        if let Err(e) = supervisor.logger().open_process_log(&config.log_file) {
+            eprintln!("Failed to open log file {}: {}", config.log_file, e);
+            continue;
+        }
+
+        let rules = crate::log_rules::Matcher::new(&config, supervisor);
+        let stdin: Option<Box<dyn tokio::io::AsyncWrite + Unpin + Send>> = match Fifos::paths(&dir, &config.id).open() {
+            Ok((stdin, stdout, stderr)) => {
+                actor::relay_output(supervisor.clone(), stdout, config.log_file.clone(), config.id.clone(), false, rules.clone());
+                actor::relay_output(supervisor.clone(), stderr, config.log_file.clone(), config.id.clone(), true, rules.clone());
+                Some(Box::new(stdin))
+            }
+            Err(e) => {
+                eprintln!("Failed to reattach to the output of process {}: {}", config.id, e);
+                None
+            }
+        };
+
+        // Same as a config changed with apply=on_next_restart: the saved one is used when it restarts
+        let pending_restart = saved
+            .iter()
+            .find(|saved_config| saved_config.id == config.id)
+            .is_some_and(|saved_config| !crate::config_diff(&config, saved_config).is_empty());
+
+        supervisor.log_system(&config.log_file,
+            &format!("Reattached to process '{}' (PID: {}) left running by the previous supervisor", config.id, entry.pid));
+        println!("Reattached to process: {} (PID {})", config.id, entry.pid);
+
+        // Held until the entry is in, so an actor whose process is already gone finds it
+        let mut processes_lock = processes.lock().unwrap();
+        let watched = actor::Watched::Adopted { pid: entry.pid, start_time: entry.start_time };
+        let actor = actor::spawn(supervisor.clone(), config.clone(), entry.pid, entry.run_count, watched, stdin);
+        processes_lock.insert(config.id.clone(), ManagedProcess {
+            config: config.clone(),
+            pid: Some(entry.pid),
+            actor: Some(actor),
+            run_count: entry.run_count,
+            last_run: entry.last_run,
+            periodic_handle: None,
+            status: ProcessStatus::Running,
+            last_exit_time: None,
+            pending_restart,
+        });
+        drop(processes_lock);
+        supervisor.emit(&config.id, EventKind::Reattached { pid: entry.pid });
+        adopted.insert(config.id);
+    }
+
+    // The pipes of processes that weren't adopted belong to nobody now
+    if let Ok(dir_entries) = std::fs::read_dir(&dir) {
+        for path in dir_entries.flatten().map(|dir_entry| dir_entry.path()) {
+            let is_pipe = matches!(path.extension().and_then(|ext| ext.to_str()), Some("stdin" | "stdout" | "stderr"));
+            let id = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            if is_pipe && !adopted.contains(id) {
+                Fifos::paths(&dir, id).remove();
+            }
+        }
+    }
+    save(supervisor);
+    adopted
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn parses_the_start_time_after_the_command_name() {
+        let fields = "S 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 123456 19";
+        assert_eq!(parse_start_time(&format!("42 (worker) {}", fields)), Some(123456));
+        // A name with spaces and parentheses of its own
+        assert_eq!(parse_start_time(&format!("42 (a) b (c)) {}", fields)), Some(123456));
+        assert_eq!(parse_start_time(&format!("42 (worker) Z{}", &fields[1..])), None);
+        assert_eq!(parse_start_time("42 (worker) S 1 2"), None);
+        assert!(start_time(std::process::id()).is_some());
+    }
+
+    fn config(id: &str, dir: &Path) -> ProcessConfig {
+        ProcessConfig {
+            id: id.to_string(),
+            command: "sleep".to_string(),
+            args: vec!["30".to_string()],
+            working_dir: None,
+            auto_restart: false,
+            log_file: dir.join(format!("{}.log", id)).to_string_lossy().into_owned(),
+            periodic: false,
+            period_seconds: None,
+            hooks: Default::default(),
+            log_rules: Vec::new(),
+        }
+    }
+
+    fn managed(config: ProcessConfig, pid: u32) -> ManagedProcess {
+        ManagedProcess {
+            config,
+            pid: Some(pid),
+            actor: None,
+            run_count: 4,
+            last_run: None,
+            periodic_handle: None,
+            status: ProcessStatus::Running,
+            last_exit_time: None,
+            pending_restart: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn adopts_live_processes_from_the_state_file() {
+        let dir = std::env::temp_dir().join(format!("exeio-adopt-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+
+        let old = Supervisor::builder().config_store(crate::MemoryConfigStore::default()).runtime_dir(&dir).build();
+        old.process_map().lock().unwrap().insert("live".to_string(), managed(config("live", &dir), child.id()));
+        // Its pid was never a process of ours, or has been reused since
+        old.process_map().lock().unwrap().insert("gone".to_string(), managed(config("gone", &dir), u32::MAX - 1));
+        save(&old);
+        assert_eq!(std::fs::metadata(dir.join("state.json")).unwrap().permissions().mode() & 0o777, 0o600);
+        std::fs::write(dir.join("orphan.stdout"), "").unwrap();
+
+        let new = Supervisor::builder().config_store(crate::MemoryConfigStore::default()).runtime_dir(&dir).build();
+        let adopted = adopt(&new);
+        assert_eq!(adopted, HashSet::from(["live".to_string()]));
+        {
+            let processes = new.process_map().lock().unwrap();
+            let live = &processes["live"];
+            assert_eq!(live.pid, Some(child.id()));
+            assert_eq!(live.run_count, 4);
+            assert!(matches!(live.status, ProcessStatus::Running));
+            assert!(!processes.contains_key("gone"));
+        }
+        assert!(!dir.join("orphan.stdout").exists());
+
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1803 @@
+// The exeio binary: command line, HTTP API and the process that serves it, on top of the Supervisor
+use clap::Parser;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::Filter;
+use std::path::PathBuf;
+use std::fs;
+
+use crate::audit::{self, AuditContext, AuditLog};
+use crate::error::{self, ApiError};
+use crate::keys::{self, Caller, KeyStore, Scope};
+use crate::network::{self, NetworkPolicy};
+use crate::ratelimit::{self, RateLimiter, RouteGroup};
+use crate::{
+    AddProcessRequest, ConfigChange, Hooks, LogRule, ProcessConfig, ProcessInfo, Supervisor, backup, client, config_diff, config_file, email,
+    events, get_config_path, get_lock_file_path, get_logs_dir, get_process_log_path, history, import, openapi, redact, tui, ui,
+    validate_process_fields, webhooks,
+};
+#[cfg(unix)]
+use crate::{runtime, unix_socket, upgrade};
+
+#[derive(Parser)]
+#[command(name = "exeio")]
+#[command(about = "A process supervisor written in rust to help server programmers to run processes and monitor them from outside the server through a rest API", long_about = None)]
+#[command(author ="philosan")]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
+    // `exeio -P 8080` keeps serving as it did before `exeio serve` existed
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// Host to bind to
+    #[arg(short = 'H', long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to bind to
+    #[arg(short = 'P', long="port", default_value_t = 8080)]
+    port: u16,
+
+    /// API key for authentication (visible in `ps`, prefer EXEIO_API_KEY or --api-key-file).
+    /// If no key is given, a random key is generated once and reused on later runs
+    #[arg(short = 'k', long="api-key", env = "EXEIO_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Read the API key from this file
+    #[arg(long = "api-key-file", value_name = "PATH", conflicts_with = "api_key")]
+    api_key_file: Option<PathBuf>,
+
+    /// File holding the hashed, scoped API keys managed through /keys
+    #[arg(long = "keys-file", value_name = "PATH")]
+    keys_file: Option<PathBuf>,
+
+    /// Append-only JSON lines audit log of mutating API calls
+    #[arg(long = "audit-log", value_name = "PATH")]
+    audit_log: Option<PathBuf>,
+
+    /// Store a SHA-256 hash of input sent to processes in the audit log instead of the text
+    #[arg(long = "audit-hash-input")]
+    audit_hash_input: bool,
+
+    /// Rate limit for a route group (public, read, control, admin) as GROUP=REQUESTS/SECONDS or GROUP=off, applied per client IP and per key (repeatable)
+    #[arg(long = "rate-limit", value_name = "GROUP=REQUESTS/SECONDS", value_parser = ratelimit::parse_rate_limit)]
+    rate_limits: Vec<(RouteGroup, Option<ratelimit::RateLimit>)>,
+
+    /// Failed authentications from one address before it is locked out (0 disables lockout)
+    #[arg(long = "auth-max-failures", default_value_t = 10)]
+    auth_max_failures: u32,
+
+    /// How long a locked out address is rejected, in seconds
+    #[arg(long = "auth-lockout-seconds", default_value_t = 300)]
+    auth_lockout_seconds: u64,
+
+    /// Only accept clients from these ranges for a route group (public, read, control, admin or all), as GROUP=CIDR[,CIDR...] (repeatable)
+    #[arg(long = "allow", value_name = "GROUP=CIDR", value_parser = network::parse_access_rule)]
+    allow: Vec<network::AccessRule>,
+
+    /// Reject clients from these ranges for a route group, as GROUP=CIDR[,CIDR...]; deny wins over allow (repeatable)
+    #[arg(long = "deny", value_name = "GROUP=CIDR", value_parser = network::parse_access_rule)]
+    deny: Vec<network::AccessRule>,
+
+    /// Reverse proxy whose X-Forwarded-For header is trusted to carry the client address (repeatable)
+    #[arg(long = "trusted-proxy", value_name = "CIDR")]
+    trusted_proxies: Vec<network::Cidr>,
+
+    /// Origin allowed to call the API from a browser, or * for any (repeatable, default: none)
+    #[arg(long = "cors-origin", value_name = "ORIGIN")]
+    cors_origins: Vec<String>,
+
+    /// Method allowed in CORS requests (repeatable)
+    #[arg(long = "cors-method", value_name = "METHOD", default_values = ["GET", "POST", "PUT", "PATCH", "DELETE"])]
+    cors_methods: Vec<String>,
+
+    /// Request header allowed in CORS requests (repeatable)
+    #[arg(long = "cors-header", value_name = "HEADER", default_values = ["content-type", "exeio-api-key"])]
+    cors_headers: Vec<String>,
+
+    /// Declarative TOML or YAML file of processes and settings, applied at startup and on SIGHUP or POST /reload
+    #[arg(long = "config", value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Also serve the API on a Unix domain socket at this path
+    #[cfg(unix)]
+    #[arg(long = "unix-socket", value_name = "PATH")]
+    unix_socket: Option<PathBuf>,
+
+    /// File mode of the Unix socket, in octal
+    #[cfg(unix)]
+    #[arg(long = "unix-socket-mode", default_value = "660", value_parser = unix_socket::parse_octal_mode)]
+    unix_socket_mode: u32,
+
+    /// Owner of the Unix socket as USER[:GROUP] (names or numeric ids)
+    #[cfg(unix)]
+    #[arg(long = "unix-socket-owner", value_name = "USER[:GROUP]", requires = "unix_socket")]
+    unix_socket_owner: Option<String>,
+
+    /// Only serve the Unix socket, do not listen on TCP
+    #[cfg(unix)]
+    #[arg(long = "no-tcp", requires = "unix_socket")]
+    no_tcp: bool,
+
+    /// Let root, the exeio user and --peer-cred-user/--peer-cred-group members use the Unix socket without an API key
+    #[cfg(unix)]
+    #[arg(long = "peer-cred-auth", requires = "unix_socket")]
+    peer_cred_auth: bool,
+
+    /// Additional user trusted by --peer-cred-auth (repeatable)
+    #[cfg(unix)]
+    #[arg(long = "peer-cred-user", value_name = "USER", requires = "peer_cred_auth")]
+    peer_cred_users: Vec<String>,
+
+    /// Group whose members are trusted by --peer-cred-auth (repeatable)
+    #[cfg(unix)]
+    #[arg(long = "peer-cred-group", value_name = "GROUP", requires = "peer_cred_auth")]
+    peer_cred_groups: Vec<String>,
+}
+
+#[derive(clap::Subcommand)]
+enum CliCommand {
+    /// Run the supervisor and its API (the default when no subcommand is given)
+    Serve(Box<ServeArgs>),
+
+    /// Convert a Procfile, supervisord config or systemd service unit into exeio processes
+    Import {
+        /// File to import
+        file: PathBuf,
+
+        /// Input format, guessed from the file name and contents if not given
+        #[arg(long, value_enum)]
+        format: Option<import::ImportFormat>,
+
+        /// Process id for a systemd unit (default: the unit file name)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Add the processes to the saved config, so the supervisor starts them on its next start
+        #[arg(long)]
+        save: bool,
+    },
+
+    #[command(flatten)]
+    Client(client::ClientCommand),
+
+    /// Top-like terminal dashboard of a running supervisor: processes, logs, input and controls
+    Tui {
+        #[command(flatten)]
+        connection: client::Connection,
+    },
+}
+
+
+// Reported by /info and the OpenAPI document
+pub const EXEIO_VERSION: &str = "1.0.0";
+
+// Body of PUT /v1/processes/:id, the id comes from the path
+#[derive(Deserialize, JsonSchema)]
+pub struct ProcessSpec {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    working_dir: Option<String>,
+    #[serde(default)]
+    auto_restart: bool,
+    /// Keep the process in the saved config; false removes it from there
+    #[serde(default)]
+    save_for_next_run: bool,
+    #[serde(default)]
+    periodic: bool,
+    period_seconds: Option<u64>,
+    #[serde(default)]
+    hooks: Hooks,
+    #[serde(default)]
+    log_rules: Vec<LogRule>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyMode {
+    // Restart the process with the new config if it is running
+    #[default]
+    RestartNow,
+    // Update the config now; it takes effect the next time the process (re)starts
+    OnNextRestart,
+    // Only change the saved config, leaving the running process and its live config alone
+    SaveOnly,
+}
+
+impl ApplyMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApplyMode::RestartNow => "restart_now",
+            ApplyMode::OnNextRestart => "on_next_restart",
+            ApplyMode::SaveOnly => "save_only",
+        }
+    }
+}
+
+// Body of PATCH /process/:id, only the given fields change
+#[derive(Deserialize, JsonSchema)]
+pub struct PatchProcessRequest {
+    command: Option<String>,
+    args: Option<Vec<String>>,
+    /// null clears the working directory
+    #[serde(default, deserialize_with = "deserialize_present")]
+    working_dir: Option<Option<String>>,
+    auto_restart: Option<bool>,
+    periodic: Option<bool>,
+    /// null clears the period
+    #[serde(default, deserialize_with = "deserialize_present")]
+    period_seconds: Option<Option<u64>>,
+    /// Replaces all of the process's hooks
+    hooks: Option<Hooks>,
+    /// Replaces all of the process's log rules
+    log_rules: Option<Vec<LogRule>>,
+    /// restart_now (default), on_next_restart or save_only
+    #[serde(default)]
+    apply: ApplyMode,
+    /// Add to (true) or drop from (false) the saved config; by default a saved process stays saved
+    save_for_next_run: Option<bool>,
+}
+
+// Tells a missing field (None) apart from an explicit null (Some(None))
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct PatchProcessResponse {
+    success: bool,
+    message: String,
+    apply: ApplyMode,
+    changes: Vec<ConfigChange>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ProcessInputRequest {
+    input: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PaginationParams {
+    page: Option<usize>,
+    page_size: Option<usize>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ApiResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct LogsPage {
+    success: bool,
+    page: usize,
+    page_size: usize,
+    total_lines: usize,
+    logs: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct InfoResponse {
+    name: String,
+    version: String,
+    author: String,
+    url: String,
+    rate_limiting: ratelimit::RateLimitStats,
+    endpoints: Vec<String>,
+}
+
+pub fn generate_api_key() -> String {
+    use rand::RngCore;
+
+    // 256 bits from the OS CSPRNG
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    format!("exeio_philo{}", hex)
+}
+
+pub fn get_api_key_path() -> PathBuf {
+    get_config_path().with_file_name("api_key")
+}
+
+// Where the master key came from, so startup can tell the operator without echoing it
+enum ApiKeySource {
+    CommandLine,
+    KeyFile(PathBuf),
+    Persisted(PathBuf),
+    Generated(PathBuf),
+}
+
+// Resolve the master key: --api-key / EXEIO_API_KEY first, then --api-key-file,
+// then the persisted key from a previous run, otherwise generate and persist a new one
+fn resolve_api_key(cli_key: Option<String>, key_file: Option<&PathBuf>) -> Result<(String, ApiKeySource), String> {
+    if let Some(key) = cli_key.filter(|k| !k.trim().is_empty()) {
+        return Ok((key, ApiKeySource::CommandLine));
+    }
+
+    if let Some(path) = key_file {
+        let key = read_api_key_file(path)?;
+        return Ok((key, ApiKeySource::KeyFile(path.clone())));
+    }
+
+    let persisted_path = get_api_key_path();
+    if persisted_path.exists() {
+        let key = read_api_key_file(&persisted_path)?;
+        return Ok((key, ApiKeySource::Persisted(persisted_path)));
+    }
+
+    let key = generate_api_key();
+    persist_api_key(&persisted_path, &key)?;
+    Ok((key, ApiKeySource::Generated(persisted_path)))
+}
+
+pub fn read_api_key_file(path: &PathBuf) -> Result<String, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read API key file {}: {}", path.display(), e))?;
+    let key = content.trim().to_string();
+    if key.is_empty() {
+        return Err(format!("API key file {} is empty", path.display()));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = fs::metadata(path)
+            && metadata.permissions().mode() & 0o077 != 0 {
+                eprintln!("Warning: API key file {} is accessible by other users, consider chmod 600", path.display());
+        }
+    }
+
+    Ok(key)
+}
+
+fn persist_api_key(path: &PathBuf, key: &str) -> Result<(), String> {
+    let temp_path = path.with_extension("tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(&temp_path)
+        .and_then(|mut file| file.write_all(key.as_bytes()))
+        .and_then(|_| fs::rename(&temp_path, path))
+        .map_err(|e| format!("Failed to save API key to {}: {}", path.display(), e))
+}
+
+// Credentials of a client connected over the Unix socket, attached to each of its requests
+#[derive(Debug, Clone)]
+pub struct LocalPeer {
+    pub uid: u32,
+    pub trusted: bool,
+}
+
+fn with_caller(keys: Arc<KeyStore>, scope: Scope) -> impl Filter<Extract = (Caller,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("exeio-api-key")
+        .and(warp::ext::optional::<LocalPeer>())
+        .and(network::with_client_ip())
+        .and(warp::any().map(move || keys.clone()))
+        .and_then(move |provided_key, peer, client, keys| {
+            validate_api_key(provided_key, peer, client, keys, scope)
+        })
+}
+
+fn with_auth(keys: Arc<KeyStore>, scope: Scope) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    with_caller(keys, scope).map(|_caller: Caller| ()).untuple_one()
+}
+
+// Authenticates the caller and checks that its key may act on the process id in the path
+fn with_process_auth(keys: Arc<KeyStore>, scope: Scope) -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+    warp::path::param::<String>()
+        .and(with_caller(keys, scope))
+        .and_then(authorize_process)
+}
+
+// For routes that matched the process id themselves, e.g. /v1/processes/:id
+async fn authorize_process(id: String, caller: Caller) -> Result<String, warp::Rejection> {
+    if caller.can_access(&id) {
+        Ok(id)
+    } else {
+        Err(warp::reject::custom(ApiError::Forbidden(format!(
+            "Key '{}' is not allowed to access process '{}'", caller.name, id
+        ))))
+    }
+}
+
+pub async fn validate_api_key(provided_key: Option<String>, peer: Option<LocalPeer>, client: Option<std::net::IpAddr>, keys: Arc<KeyStore>, scope: Scope) -> Result<Caller, warp::Rejection> {
+    let group = RouteGroup::for_scope(scope);
+    network::check_access(group, client)?;
+
+    let source = ratelimit::client_source(client, peer.as_ref());
+    let trusted_peer = peer.filter(|p| p.trusted);
+    // Trusted peers never fail authentication, so they can't be locked out by other clients' failures
+    if trusted_peer.is_none() {
+        RATE_LIMITER.check_lockout(&source).map_err(warp::reject::custom)?;
+    }
+    RATE_LIMITER.check_ip(group, &source).map_err(warp::reject::custom)?;
+
+    // Trusted local peers on the Unix socket don't need a key
+    let caller = if let Some(peer) = trusted_peer {
+        Caller::admin(format!("peer:uid={}", peer.uid))
+    } else {
+        match provided_key.and_then(|key| keys.authenticate(&key)) {
+            Some(caller) => {
+                RATE_LIMITER.record_success(&source);
+                caller
+            }
+            None => {
+                RATE_LIMITER.record_failure(&source);
+                return Err(warp::reject::custom(ApiError::Unauthorized));
+            }
+        }
+    };
+
+    RATE_LIMITER.check_key(group, &caller.name).map_err(warp::reject::custom)?;
+
+    if caller.has_scope(scope) {
+        Ok(caller)
+    } else {
+        Err(warp::reject::custom(ApiError::Forbidden(format!(
+            "Key '{}' lacks the '{}' scope required for this endpoint",
+            caller.name,
+            serde_json::to_value(scope).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default()
+        ))))
+    }
+}
+
+// The server's settings, shared by every route; the supervisor itself keeps no globals
+lazy_static::lazy_static! {
+    pub static ref RATE_LIMITER: RateLimiter = RateLimiter::new();
+    pub static ref NETWORK_POLICY: RwLock<NetworkPolicy> = RwLock::new(NetworkPolicy::default());
+    // The rate limit and network settings in effect, for export
+    pub static ref SETTINGS: RwLock<config_file::Settings> = RwLock::new(config_file::Settings::default());
+}
+
+// The exeio server: everything the binary does once the command line is parsed
+pub async fn run(cli: Cli) {
+    #[cfg(unix)]
+    upgrade::init();
+
+    let cli = match cli.command {
+        None => cli.serve,
+        Some(CliCommand::Serve(serve)) => *serve,
+        Some(CliCommand::Import { file, format, name, save }) => {
+            if let Err(e) = import::run_cli(&file, format, name, save) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(CliCommand::Client(command)) => {
+            if let Err(e) = client::run(command).await {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(CliCommand::Tui { connection }) => {
+            if let Err(e) = tui::run(&connection).await {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+    };
+
+    // Check for single instance before doing anything else
+    if let Err(e) = ensure_single_instance() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    // Use the provided API key, or load/generate the persisted one
+    let (api_key, api_key_source) = resolve_api_key(cli.api_key.clone(), cli.api_key_file.as_ref())
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+    let api_key = Arc::new(api_key);
+    let key_store = Arc::new(KeyStore::load(
+        cli.keys_file.clone().unwrap_or_else(keys::get_keys_path),
+        &api_key,
+    ).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }));
+    let settings = config_file::Settings {
+        rate_limits: cli.rate_limits.clone(),
+        allow: cli.allow.clone(),
+        deny: cli.deny.clone(),
+        trusted_proxies: cli.trusted_proxies.clone(),
+        auth_max_failures: cli.auth_max_failures,
+        auth_lockout_seconds: cli.auth_lockout_seconds,
+    };
+    settings.apply();
+    // The config file is validated before anything starts, so a bad file never half-applies
+    let config_file = cli.config.clone().map(|path| Arc::new(config_file::ConfigFileManager::new(path, settings)));
+    let desired_state = config_file.as_ref().map(|config_file| {
+        config_file.load().unwrap_or_else(|errors| {
+            eprintln!("Error: invalid config file {}:", config_file.path().display());
+            for error in errors {
+                eprintln!("  {}: {}", error.field, error.message);
+            }
+            std::process::exit(1);
+        })
+    });
+    let cors = network::build_cors(&network::CorsOptions {
+        origins: cli.cors_origins.clone(),
+        methods: cli.cors_methods.clone(),
+        headers: cli.cors_headers.clone(),
+    })
+    .unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    let audit_log = Arc::new(AuditLog::new(
+        cli.audit_log.clone().unwrap_or_else(|| get_logs_dir().join("audit.log")),
+        cli.audit_hash_input,
+    ));
+
+    // The HTTP API is a front-end to the same supervisor library callers get
+    let builder = Supervisor::builder().address(cli.host.clone(), cli.port);
+    #[cfg(unix)]
+    let builder = builder.runtime_dir(runtime::default_dir());
+    let supervisor = builder.build();
+    setup_cleanup_handler(get_lock_file_path(), supervisor.clone());
+    webhooks::start(&supervisor);
+    email::start(&supervisor);
+    
+    let _exeio_log_path = init_exeio_log(&supervisor);
+
+    let supervisor_for_log = supervisor.clone();
+    let log_filter = warp::log::custom(move |info| {
+        let log_entry = format!(
+            "{} {} -> {}\n",
+            info.method(),
+            info.path(),
+            info.status()
+        );
+        
+        println!(
+            "exeio: [{}] {} {} -> {}",
+            supervisor_for_log.now().format("%Y-%m-%d %H:%M:%S"),
+            info.method(),
+            info.path(),
+            info.status()
+        );
+        
+        log_exeio_event(&supervisor_for_log, &log_entry); 
+    });
+    
+    
+    #[cfg(unix)]
+    {
+        let adopted = runtime::adopt(&supervisor);
+        if !adopted.is_empty() {
+            log_exeio_event(&supervisor, &format!("Reattached to {} process(es) left running by the previous supervisor\n", adopted.len()));
+        }
+        upgrade::restore_handoff(&supervisor).await;
+    }
+    supervisor.load_saved_except(|id| desired_state.as_ref().is_some_and(|desired| desired.declares(id))).await;
+    if let (Some(config_file), Some(desired_state)) = (&config_file, desired_state) {
+        let applied = config_file.apply(desired_state, &supervisor).await;
+        println!("{}", applied.message);
+    }
+
+    // SIGUSR2 upgrades in place to the binary exeio was started from, like POST /upgrade
+    #[cfg(unix)]
+    {
+        let supervisor = supervisor.clone();
+        tokio::spawn(async move {
+            let mut signals = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined2()) {
+                Ok(signals) => signals,
+                Err(e) => {
+                    eprintln!("Failed to listen for SIGUSR2: {}", e);
+                    return;
+                }
+            };
+            while signals.recv().await.is_some() {
+                if let Err(error) = upgrade::upgrade(None, supervisor.clone()).await {
+                    let message = format!("Upgrade on SIGUSR2 failed: {}\n", error.message());
+                    eprint!("{}", message);
+                    log_exeio_event(&supervisor, &message);
+                }
+            }
+        });
+    }
+
+    // SIGHUP reloads the config file, like POST /reload
+    #[cfg(unix)]
+    if let Some(config_file) = config_file.clone() {
+        let supervisor = supervisor.clone();
+        tokio::spawn(async move {
+            let mut hangups = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+                Ok(hangups) => hangups,
+                Err(e) => {
+                    eprintln!("Failed to listen for SIGHUP: {}", e);
+                    return;
+                }
+            };
+            while hangups.recv().await.is_some() {
+                match config_file.reload(&supervisor, false, "on SIGHUP").await {
+                    Ok(response) => println!("{}", response.message),
+                    Err(errors) => {
+                        let fields: Vec<String> = errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+                        let message = format!("Config reload on SIGHUP rejected, nothing changed: {}\n", fields.join("; "));
+                        eprint!("{}", message);
+                        log_exeio_event(&supervisor, &message);
+                    }
+                }
+            }
+        });
+    }
+    
+    // Ensure config directory exists
+    let config_path = get_config_path();
+    println!("Using config file: {}", config_path.display());
+    
+    // Setup API routes
+    let supervisor_filter = warp::any().map({
+        let supervisor = supervisor.clone();
+        move || supervisor.clone()
+    });
+    let keys_filter = warp::any().map({
+        let key_store = key_store.clone();
+        move || key_store.clone()
+    });
+    
+    let add_process = warp::path("add")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(handle_add_process);
+    
+    let restart_process = warp::path("restart")
+        .and(warp::post())
+        .and(audit::with_process_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(supervisor_filter.clone())
+        .and_then(handle_restart_process);
+    
+    let stop_process = warp::path("stop")
+        .and(warp::post())
+        .and(audit::with_process_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(supervisor_filter.clone())
+        .and_then(handle_stop_process);
+    
+    let remove_process = warp::path("remove")
+        .and(warp::post())
+        .and(audit::with_process_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(supervisor_filter.clone())
+        .and_then(handle_remove_process);
+    
+    let restart_all = warp::path("restart-all")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(supervisor_filter.clone())
+        .and_then(handle_restart_all);
+    
+    let stop_all = warp::path("stop-all")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(supervisor_filter.clone())
+        .and_then(handle_stop_all);
+    
+    let send_input = warp::path("input")
+        .and(warp::post())
+        .and(audit::with_process_audit(key_store.clone(), audit_log.clone(), Scope::Input))
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(handle_send_input);
+    
+    let clear_log = warp::path("clear-log")
+        .and(warp::post())
+        .and(audit::with_process_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(supervisor_filter.clone())
+        .and_then(handle_clear_log);
+    
+    let list_processes = warp::path("list")
+        .and(warp::get())
+        .and(with_caller(key_store.clone(), Scope::Read))
+        .and(supervisor_filter.clone())
+        .and_then(handle_list_processes);
+
+    // Stays unprotected for health checks
+    let exeio_info = warp::path("info")
+        .and(warp::get())
+        .and(ratelimit::with_rate_limit(RouteGroup::Public))
+        .and(supervisor_filter.clone())
+        .and_then(handle_exeio_info);
+    
+    let logs_route = warp::path("logs")
+        .and(warp::get())
+        .and(with_process_auth(key_store.clone(), Scope::Logs))
+        .and(warp::query::<PaginationParams>())
+        .and(supervisor_filter.clone())
+        .and_then(handle_process_logs);
+    
+    let shutdown_route = warp::path("shutdown")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(supervisor_filter.clone())
+        .and_then(handle_shutdown);
+    
+    let list_keys = warp::path("keys")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(keys_filter.clone())
+        .and_then(keys::handle_list_keys);
+
+    let create_key = warp::path("keys")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(warp::body::json())
+        .and(keys_filter.clone())
+        .and_then(keys::handle_create_key);
+
+    let revoke_key = warp::path!("keys" / String / "revoke")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(keys_filter.clone())
+        .and_then(keys::handle_revoke_key);
+
+    let rotate_key = warp::path!("keys" / String / "rotate")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(keys_filter.clone())
+        .and_then(keys::handle_rotate_key);
+
+    let audit_route = warp::path("audit")
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(warp::query::<audit::AuditQuery>())
+        .and(warp::any().map({
+            let audit_log = audit_log.clone();
+            move || audit_log.clone()
+        }))
+        .and_then(audit::handle_audit_log);
+
+    let patch_process = warp::path!("process" / String)
+        .and(warp::patch())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and_then(audit::authorize_process)
+        .untuple_one()
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(handle_patch_process);
+
+    let config_file_filter = warp::any().map(move || config_file.clone());
+
+    let export_route = warp::path("export")
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(warp::query::<backup::ExportParams>())
+        .and(supervisor_filter.clone())
+        .and_then(backup::handle_export);
+
+    let restore_route = warp::path("restore")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(backup::handle_restore);
+
+    let import_route = warp::path("import")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(import::handle_import);
+
+    let reload_route = warp::path("reload")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(warp::query::<config_file::ReloadParams>())
+        .and(config_file_filter.clone())
+        .and(supervisor_filter.clone())
+        .and_then(config_file::handle_reload);
+
+    let config_history = warp::path!("config" / "history")
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(warp::query::<history::HistoryQuery>())
+        .and(supervisor_filter.clone())
+        .and_then(history::handle_history);
+
+    let config_revision = warp::path!("config" / "history" / u64)
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(supervisor_filter.clone())
+        .and_then(history::handle_revision);
+
+    let config_rollback = warp::path!("config" / "rollback" / u64)
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(warp::query::<history::RollbackParams>())
+        .and(supervisor_filter.clone())
+        .and_then(history::handle_rollback);
+
+    // Versioned, resource-oriented API; the paths above are kept for existing clients
+    let v1_list = warp::path!("v1" / "processes")
+        .and(warp::get())
+        .and(with_caller(key_store.clone(), Scope::Read))
+        .and(supervisor_filter.clone())
+        .and_then(handle_list_processes);
+
+    let v1_create = warp::path!("v1" / "processes")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(handle_add_process);
+
+    let v1_get = warp::path!("v1" / "processes" / String)
+        .and(warp::get())
+        .and(with_caller(key_store.clone(), Scope::Read))
+        .and_then(authorize_process)
+        .and(supervisor_filter.clone())
+        .and_then(handle_get_process);
+
+    let v1_put = warp::path!("v1" / "processes" / String)
+        .and(warp::put())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and_then(audit::authorize_process)
+        .untuple_one()
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(handle_put_process);
+
+    let v1_patch = warp::path!("v1" / "processes" / String)
+        .and(warp::patch())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and_then(audit::authorize_process)
+        .untuple_one()
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(handle_patch_process);
+
+    let v1_delete = warp::path!("v1" / "processes" / String)
+        .and(warp::delete())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and_then(audit::authorize_process)
+        .untuple_one()
+        .and(supervisor_filter.clone())
+        .and_then(handle_remove_process);
+
+    let v1_restart = warp::path!("v1" / "processes" / String / "actions" / "restart")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and_then(audit::authorize_process)
+        .untuple_one()
+        .and(supervisor_filter.clone())
+        .and_then(handle_restart_process);
+
+    let v1_stop = warp::path!("v1" / "processes" / String / "actions" / "stop")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and_then(audit::authorize_process)
+        .untuple_one()
+        .and(supervisor_filter.clone())
+        .and_then(handle_stop_process);
+
+    let v1_input = warp::path!("v1" / "processes" / String / "input")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Input))
+        .and_then(audit::authorize_process)
+        .untuple_one()
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(handle_send_input);
+
+    let v1_logs = warp::path!("v1" / "processes" / String / "logs")
+        .and(warp::get())
+        .and(with_caller(key_store.clone(), Scope::Logs))
+        .and_then(authorize_process)
+        .and(warp::query::<PaginationParams>())
+        .and(supervisor_filter.clone())
+        .and_then(handle_process_logs);
+
+    let v1_clear_logs = warp::path!("v1" / "processes" / String / "logs")
+        .and(warp::delete())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and_then(audit::authorize_process)
+        .untuple_one()
+        .and(supervisor_filter.clone())
+        .and_then(handle_clear_log);
+
+    let v1_restart_all = warp::path!("v1" / "actions" / "restart-all")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(supervisor_filter.clone())
+        .and_then(handle_restart_all);
+
+    let v1_stop_all = warp::path!("v1" / "actions" / "stop-all")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(supervisor_filter.clone())
+        .and_then(handle_stop_all);
+
+    let v1_shutdown = warp::path!("v1" / "actions" / "shutdown")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(supervisor_filter.clone())
+        .and_then(handle_shutdown);
+
+    let v1_reload = warp::path!("v1" / "actions" / "reload")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(warp::query::<config_file::ReloadParams>())
+        .and(config_file_filter.clone())
+        .and(supervisor_filter.clone())
+        .and_then(config_file::handle_reload);
+
+    let v1_import = warp::path!("v1" / "actions" / "import")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Control))
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(import::handle_import);
+
+    let v1_export = warp::path!("v1" / "export")
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(warp::query::<backup::ExportParams>())
+        .and(supervisor_filter.clone())
+        .and_then(backup::handle_export);
+
+    let v1_restore = warp::path!("v1" / "actions" / "restore")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(warp::body::json())
+        .and(supervisor_filter.clone())
+        .and_then(backup::handle_restore);
+
+    let v1_config_history = warp::path!("v1" / "config" / "history")
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(warp::query::<history::HistoryQuery>())
+        .and(supervisor_filter.clone())
+        .and_then(history::handle_history);
+
+    let v1_config_revision = warp::path!("v1" / "config" / "history" / u64)
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(supervisor_filter.clone())
+        .and_then(history::handle_revision);
+
+    let v1_config_rollback = warp::path!("v1" / "config" / "history" / u64 / "actions" / "rollback")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(warp::query::<history::RollbackParams>())
+        .and(supervisor_filter.clone())
+        .and_then(history::handle_rollback);
+
+    let v1_info = warp::path!("v1" / "info")
+        .and(warp::get())
+        .and(ratelimit::with_rate_limit(RouteGroup::Public))
+        .and(supervisor_filter.clone())
+        .and_then(handle_exeio_info);
+
+    let v1_openapi = warp::path!("v1" / "openapi.json")
+        .and(warp::get())
+        .and(ratelimit::with_rate_limit(RouteGroup::Public))
+        .and_then(openapi::handle_openapi);
+
+    let v1_list_keys = warp::path!("v1" / "keys")
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(keys_filter.clone())
+        .and_then(keys::handle_list_keys);
+
+    let v1_create_key = warp::path!("v1" / "keys")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(warp::body::json())
+        .and(keys_filter.clone())
+        .and_then(keys::handle_create_key);
+
+    let v1_revoke_key = warp::path!("v1" / "keys" / String)
+        .and(warp::delete())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(keys_filter.clone())
+        .and_then(keys::handle_revoke_key);
+
+    let v1_rotate_key = warp::path!("v1" / "keys" / String / "actions" / "rotate")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(keys_filter.clone())
+        .and_then(keys::handle_rotate_key);
+
+    let v1_audit = warp::path!("v1" / "audit")
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(warp::query::<audit::AuditQuery>())
+        .and(warp::any().map({
+            let audit_log = audit_log.clone();
+            move || audit_log.clone()
+        }))
+        .and_then(audit::handle_audit_log);
+
+    // In-place upgrades need Unix, elsewhere these paths are not found
+    #[cfg(unix)]
+    let upgrade_routes = warp::path!("v1" / "actions" / "upgrade")
+        .or(warp::path("upgrade"))
+        .unify()
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(warp::query::<upgrade::UpgradeParams>())
+        .and(supervisor_filter.clone())
+        .and_then(upgrade::handle_upgrade);
+    #[cfg(not(unix))]
+    let upgrade_routes = warp::any().and_then(|| async { Err::<warp::reply::Json, _>(warp::reject::not_found()) });
+
+    // A WebSocket when the client asks for an upgrade, server-sent events otherwise. The caller is
+    // authenticated once, before the choice, so a failed attempt isn't counted twice
+    let events_routes = warp::path!("v1" / "events")
+        .or(warp::path!("events"))
+        .unify()
+        .and(warp::get())
+        .and(with_caller(key_store.clone(), Scope::Read))
+        .and(warp::query::<events::EventsQuery>())
+        .and(warp::ws().map(Some).or(warp::any().map(|| None)).unify())
+        .and(warp::header::optional::<u64>("last-event-id"))
+        .and(supervisor_filter.clone())
+        .and_then(events::handle_events);
+
+    let webhook_test = warp::path!("v1" / "webhooks" / String / "actions" / "test")
+        .or(warp::path!("webhooks" / String / "test"))
+        .unify()
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(supervisor_filter.clone())
+        .and_then(webhooks::handle_test_webhook);
+
+    let webhook_dead_letters = warp::path!("v1" / "webhooks" / "dead-letters")
+        .or(warp::path!("webhooks" / "dead-letters"))
+        .unify()
+        .and(warp::get())
+        .and(with_auth(key_store.clone(), Scope::Admin))
+        .and(supervisor_filter.clone())
+        .and_then(webhooks::handle_dead_letters);
+
+    let webhook_redeliver = warp::path!("v1" / "webhooks" / "dead-letters" / "actions" / "redeliver")
+        .or(warp::path!("webhooks" / "dead-letters" / "redeliver"))
+        .unify()
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(supervisor_filter.clone())
+        .and_then(webhooks::handle_redeliver);
+
+    let email_test = warp::path!("v1" / "email" / "actions" / "test")
+        .and(warp::post())
+        .and(audit::with_audit(key_store.clone(), audit_log.clone(), Scope::Admin))
+        .and(supervisor_filter.clone())
+        .and_then(email::handle_test_email);
+
+    let notifier_routes = webhook_test
+        .or(webhook_dead_letters)
+        .or(webhook_redeliver)
+        .or(email_test)
+        .boxed();
+
+    let v1_routes = v1_list
+        .or(v1_create)
+        .or(v1_get)
+        .or(v1_put)
+        .or(v1_patch)
+        .or(v1_delete)
+        .or(v1_restart)
+        .or(v1_stop)
+        .or(v1_input)
+        .or(v1_logs)
+        .or(v1_clear_logs)
+        .or(v1_restart_all)
+        .or(v1_stop_all)
+        .or(v1_shutdown)
+        .or(v1_reload)
+        .or(v1_import)
+        .or(v1_export)
+        .or(v1_restore)
+        .or(v1_config_history)
+        .or(v1_config_revision)
+        .or(v1_config_rollback)
+        .or(upgrade_routes)
+        .or(events_routes)
+        .or(notifier_routes)
+        .or(v1_info)
+        .or(v1_openapi)
+        .or(v1_list_keys)
+        .or(v1_create_key)
+        .or(v1_revoke_key)
+        .or(v1_rotate_key)
+        .or(v1_audit)
+        .boxed();
+
+    let legacy_routes = add_process
+        .or(patch_process)
+        .or(restart_process)
+        .or(stop_process)
+        .or(remove_process)
+        .or(restart_all)
+        .or(stop_all)
+        .or(send_input)
+        .or(clear_log)
+        .or(list_processes)
+        .or(exeio_info)
+        .or(logs_route)
+        .or(shutdown_route)
+        .or(reload_route)
+        .or(import_route)
+        .or(export_route)
+        .or(restore_route)
+        .or(config_history)
+        .or(config_revision)
+        .or(config_rollback)
+        .or(list_keys)
+        .or(create_key)
+        .or(revoke_key)
+        .or(rotate_key)
+        .or(audit_route)
+        .boxed();
+
+    let routes = v1_routes
+        .or(legacy_routes)
+        .or(ui::routes())
+        .recover(error::handle_rejection)
+        .with(log_filter);
+    // Without --cors-origin no CORS headers are sent, so browsers refuse cross-origin calls
+    let routes = match cors {
+        Some(cors) => routes.with(cors).map(warp::Reply::into_response).boxed(),
+        None => routes.map(warp::Reply::into_response).boxed(),
+    };
+   
+    println!("Process Supervisor starting on port {} at {}", cli.port, cli.host);
+    match api_key_source {
+        ApiKeySource::CommandLine => println!("API Key: provided via --api-key/EXEIO_API_KEY"),
+        ApiKeySource::KeyFile(path) => println!("API Key: read from {}", path.display()),
+        ApiKeySource::Persisted(path) => println!("API Key: loaded from {}", path.display()),
+        ApiKeySource::Generated(path) => {
+            println!("API Key: {}", api_key);
+            println!("  (generated and saved to {} with mode 600, it will be reused on restart)", path.display());
+        }
+    }
+    println!("Dashboard: http://{}:{}/ui", cli.host, cli.port);
+    println!("NOTE: All endpoints except /info and /v1/openapi.json require the 'exeio-api-key' header with the above key or a scoped key");
+    println!("Key store: {}", key_store.path().display());
+    println!("Audit log: {}", audit_log.path().display());
+    println!("Logs directory: {}", get_logs_dir().display());
+    println!("Config file: {}", get_config_path().display());
+    if let Some(path) = &cli.config {
+        println!("Declarative config: {} (reload with SIGHUP or POST /v1/actions/reload)", path.display());
+    }
+    println!("  Available endpoints (the legacy paths without /v1 keep working, full spec at GET /v1/openapi.json):");
+    for endpoint in openapi::ENDPOINTS.iter().filter(|endpoint| !endpoint.legacy) {
+        println!("  {}", endpoint.describe());
+    }
+    
+    let addr: std::net::IpAddr = cli.host.parse()
+    .unwrap_or_else(|_| {
+        eprintln!("Invalid host address: {}", cli.host);
+        std::process::exit(1);
+    });
+
+    #[cfg(unix)]
+    if let Some(ref socket_path) = cli.unix_socket {
+        let options = unix_socket::UnixSocketOptions {
+            path: socket_path.clone(),
+            mode: cli.unix_socket_mode,
+            owner: cli.unix_socket_owner.clone(),
+        };
+        let policy = if cli.peer_cred_auth {
+            match unix_socket::PeerCredPolicy::new(&cli.peer_cred_users, &cli.peer_cred_groups) {
+                Ok(policy) => Some(Arc::new(policy)),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            None
+        };
+        let listener = unix_socket::bind(&options).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+
+        println!("Unix socket: {} (mode {:o}{})", socket_path.display(), cli.unix_socket_mode,
+            if cli.peer_cred_auth { ", peer credential auth enabled" } else { "" });
+
+        let server = unix_socket::serve(listener, warp::service(routes.clone()), policy);
+        if cli.no_tcp {
+            server.await;
+            return;
+        }
+        tokio::spawn(server);
+    }
+
+    // Our own listener, so it can be handed to a new binary on upgrade
+    #[cfg(unix)]
+    {
+        let listener = upgrade::listen((addr, cli.port).into()).unwrap_or_else(|e| {
+            eprintln!("Error: failed to listen on {}:{}: {}", addr, cli.port, e);
+            std::process::exit(1);
+        });
+        upgrade::serve(listener, warp::service(routes)).await;
+    }
+    #[cfg(not(unix))]
+    warp::serve(routes)
+        .run((addr, cli.port))
+        .await;
+}
+
+async fn handle_add_process(
+    audit: AuditContext,
+    req: AddProcessRequest,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // The full request goes to the audit log, with secret-looking arguments, hook commands and rule input masked
+    let audit_details = serde_json::json!({
+        "id": req.id,
+        "command": req.command,
+        "args": redact::redact_args(&req.args),
+        "working_dir": req.working_dir,
+        "auto_restart": req.auto_restart,
+        "save_for_next_run": req.save_for_next_run,
+        "periodic": req.periodic,
+        "period_seconds": req.period_seconds,
+        "hooks": redact::redact_hooks(&req.hooks),
+        "log_rules": redact::redact_log_rules(&req.log_rules)
+    });
+    let id = req.id.clone();
+
+    if !audit.caller.can_access(&id) {
+        let error = ApiError::Forbidden(format!("Key '{}' is not allowed to add process '{}'", audit.caller.name, id));
+        audit.record_error(Some(&id), Some(audit_details), &error);
+        return Err(warp::reject::custom(error));
+    }
+
+    let config = match supervisor.add_as(req, &audit.caller.name).await {
+        Ok(config) => config,
+        Err(error) => {
+            audit.record_error(Some(&id), Some(audit_details), &error);
+            return Err(warp::reject::custom(error));
+        }
+    };
+    
+    let process_type = if config.periodic {
+        format!("periodic ({}s)", config.period_seconds.unwrap_or(0))
+    } else {
+        "regular".to_string()
+    };
+    
+    let auto_restart_info = if config.auto_restart {
+        " with auto-restart enabled"
+    } else {
+        ""
+    };
+    
+    let response = ApiResponse {
+        success: true,
+        message: format!("Process {} added successfully as {}{}", id, process_type, auto_restart_info),
+    };
+    
+    audit.record(Some(&id), Some(audit_details), &response);
+    
+    Ok(warp::reply::json(&response))
+}
+
+async fn handle_restart_process(
+    id: String,
+    audit: AuditContext,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let reason = format!("Process '{}' restarted via API by '{}'", id, audit.caller.name);
+    match supervisor.restart_because(&id, &reason).await {
+        Ok(()) => {
+            let response = ApiResponse {
+                success: true,
+                message: format!("Process {} restarted successfully", id),
+            };
+            audit.record(Some(&id), None, &response);
+            Ok(warp::reply::json(&response))
+        }
+        Err(error) => {
+            audit.record_error(Some(&id), None, &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+async fn handle_stop_process(
+    id: String,
+    audit: AuditContext,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match supervisor.stop_because(&id, "Process stopped manually via API") {
+        Ok(()) => {
+            let response = ApiResponse {
+                success: true,
+                message: format!("Process {} stopped successfully", id),
+            };
+            audit.record(Some(&id), None, &response);
+            Ok(warp::reply::json(&response))
+        }
+        Err(error) => {
+            audit.record_error(Some(&id), None, &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+async fn handle_remove_process(
+    id: String,
+    audit: AuditContext,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match supervisor.remove_as(&id, &audit.caller.name) {
+        Ok(_) => {
+            let response = ApiResponse {
+                success: true,
+                message: format!("Process {} removed successfully", id),
+            };
+            audit.record(Some(&id), None, &response);
+            Ok(warp::reply::json(&response))
+        }
+        Err(error) => {
+            audit.record_error(Some(&id), None, &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+async fn handle_restart_all(audit: AuditContext, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    // Keys restricted to some processes only restart those
+    supervisor.restart_where(|id| audit.caller.can_access(id)).await;
+    
+    let response = ApiResponse {
+        success: true,
+        message: "All processes restarted successfully".to_string(),
+    };
+    audit.record(None, None, &response);
+    Ok(warp::reply::json(&response))
+}
+
+async fn handle_stop_all(audit: AuditContext, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    // Keys restricted to some processes only stop those
+    supervisor.stop_where(|id| audit.caller.can_access(id));
+    
+    let response = ApiResponse {
+        success: true,
+        message: "All processes stopped successfully".to_string(),
+    };
+    audit.record(None, None, &response);
+    Ok(warp::reply::json(&response))
+}
+
+async fn handle_send_input(
+    id: String,
+    audit: AuditContext,
+    req: ProcessInputRequest,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let audit_details = audit.input_details(&req.input);
+    match supervisor.send_input(&id, &req.input) {
+        Ok(()) => {
+            let response = ApiResponse {
+                success: true,
+                message: format!("Input sent to process {}", id),
+            };
+            audit.record(Some(&id), Some(audit_details), &response);
+            Ok(warp::reply::json(&response))
+        }
+        Err(error) => {
+            audit.record_error(Some(&id), Some(audit_details), &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+async fn handle_clear_log(
+    id: String,
+    audit: AuditContext,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match supervisor.clear_log(&id).await {
+        Ok(()) => {
+            let response = ApiResponse {
+                success: true,
+                message: format!("Log cleared for process {}", id),
+            };
+            audit.record(Some(&id), None, &response);
+            Ok(warp::reply::json(&response))
+        }
+        Err(error) => {
+            audit.record_error(Some(&id), None, &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+pub fn change_summary(changes: &[ConfigChange]) -> String {
+    if changes.is_empty() {
+        return "no changes".to_string();
+    }
+    changes.iter()
+        .map(|c| format!("{}: {} -> {}", c.field, c.old, c.new))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+async fn handle_patch_process(
+    id: String,
+    audit: AuditContext,
+    req: PatchProcessRequest,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let current = match supervisor.config(&id) {
+        Ok(current) => current,
+        Err(error) => {
+            audit.record_error(Some(&id), Some(serde_json::json!({ "apply": req.apply })), &error);
+            return Err(warp::reject::custom(error));
+        }
+    };
+
+    let mut updated = current.clone();
+    if let Some(command) = req.command {
+        updated.command = command;
+    }
+    if let Some(args) = req.args {
+        updated.args = args;
+    }
+    if let Some(working_dir) = req.working_dir {
+        updated.working_dir = working_dir;
+    }
+    if let Some(auto_restart) = req.auto_restart {
+        updated.auto_restart = auto_restart;
+    }
+    if let Some(periodic) = req.periodic {
+        updated.periodic = periodic;
+    }
+    if let Some(period_seconds) = req.period_seconds {
+        updated.period_seconds = period_seconds;
+    }
+    if let Some(hooks) = req.hooks {
+        updated.hooks = hooks;
+    }
+    if let Some(log_rules) = req.log_rules {
+        updated.log_rules = log_rules;
+    }
+
+    let changes = config_diff(&current, &updated);
+    let audit_details = serde_json::json!({ "apply": req.apply, "changes": changes });
+
+    let invalid_fields = validate_process_fields(&id, &updated.command, updated.periodic, updated.period_seconds, &updated.hooks, &updated.log_rules);
+    if !invalid_fields.is_empty() {
+        let error = ApiError::Validation(invalid_fields);
+        audit.record_error(Some(&id), Some(audit_details), &error);
+        return Err(warp::reject::custom(error));
+    }
+
+    // save_only always writes the saved config; otherwise it follows save_for_next_run,
+    // and a process that is already saved stays saved
+    let persist = match (req.apply, req.save_for_next_run) {
+        (ApplyMode::SaveOnly, _) | (_, Some(true)) => Some(true),
+        (_, Some(false)) => Some(false),
+        (_, None) => supervisor.saved().is_saved(&id).then_some(true),
+    };
+    let saved = match persist {
+        Some(true) => supervisor.saved().save_process_config(&updated, &audit.caller.name).map_err(|e| e.to_string()),
+        Some(false) => supervisor.saved().remove_process_config(&id, &audit.caller.name).map_err(|e| e.to_string()),
+        None => Ok(()),
+    };
+    if let Err(e) = saved {
+        let error = ApiError::Io(format!("Failed to save config for process {}: {}", id, e));
+        audit.record_error(Some(&id), Some(audit_details), &error);
+        return Err(warp::reject::custom(error));
+    }
+
+    let summary = change_summary(&changes);
+
+    let note = format!("Configuration updated via API (apply={}): {}", req.apply.as_str(), summary);
+    let outcome = supervisor.reconfigure(&id, updated, req.apply, &note).await.map_err(warp::reject::custom)?;
+
+    let response = ApiResponse {
+        success: true,
+        message: format!("Process {} updated ({} changed), {}", id, changes.len(), outcome),
+    };
+    audit.record(Some(&id), Some(audit_details), &response);
+    Ok(warp::reply::json(&PatchProcessResponse {
+        success: true,
+        message: response.message,
+        apply: req.apply,
+        changes,
+    }))
+}
+
+async fn handle_list_processes(caller: Caller, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    let process_list: Vec<ProcessInfo> = supervisor
+        .list()
+        .into_iter()
+        .filter(|info| caller.can_access(&info.id))
+        .collect();
+    
+    Ok(warp::reply::json(&process_list))
+}
+
+async fn handle_get_process(id: String, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    let info = supervisor.get(&id).map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&info))
+}
+
+async fn handle_put_process(
+    id: String,
+    audit: AuditContext,
+    spec: ProcessSpec,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let audit_details = serde_json::json!({
+        "command": spec.command,
+        "args": redact::redact_args(&spec.args),
+        "working_dir": spec.working_dir,
+        "auto_restart": spec.auto_restart,
+        "save_for_next_run": spec.save_for_next_run,
+        "periodic": spec.periodic,
+        "period_seconds": spec.period_seconds,
+        "hooks": redact::redact_hooks(&spec.hooks),
+        "log_rules": redact::redact_log_rules(&spec.log_rules)
+    });
+
+    let invalid_fields = validate_process_fields(&id, &spec.command, spec.periodic, spec.period_seconds, &spec.hooks, &spec.log_rules);
+    if !invalid_fields.is_empty() {
+        let error = ApiError::Validation(invalid_fields);
+        audit.record_error(Some(&id), Some(audit_details), &error);
+        return Err(warp::reject::custom(error));
+    }
+
+    let config = ProcessConfig {
+        id: id.clone(),
+        command: spec.command,
+        args: spec.args,
+        working_dir: spec.working_dir,
+        auto_restart: spec.auto_restart,
+        log_file: get_process_log_path(&id).to_string_lossy().into_owned(),
+        periodic: spec.periodic,
+        period_seconds: spec.period_seconds,
+        hooks: spec.hooks,
+        log_rules: spec.log_rules,
+    };
+
+    // Saved only when asked, like POST /add; PUT replaces everything, so otherwise drop any saved copy
+    let saved = if spec.save_for_next_run {
+        supervisor.saved().save_process_config(&config, &audit.caller.name).map_err(|e| e.to_string())
+    } else {
+        supervisor.saved().remove_process_config(&id, &audit.caller.name).map_err(|e| e.to_string())
+    };
+    if let Err(e) = saved {
+        let error = ApiError::Io(format!("Failed to save config for process {}: {}", id, e));
+        audit.record_error(Some(&id), Some(audit_details), &error);
+        return Err(warp::reject::custom(error));
+    }
+
+    // Stops the current instance, keeping its run count
+    let replaced = supervisor.replace(config, "Process configuration replaced via API").await;
+
+    let response = ApiResponse {
+        success: true,
+        message: if replaced {
+            format!("Process {} replaced and restarted", id)
+        } else {
+            format!("Process {} created", id)
+        },
+    };
+    audit.record(Some(&id), Some(audit_details), &response);
+    Ok(warp::reply::json(&response))
+}
+
+async fn handle_exeio_info(supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    let info = InfoResponse {
+        name: "exeio - Process Supervisor".to_string(),
+        version: EXEIO_VERSION.to_string(),
+        author: "made by philo".to_string(),
+        url: format!("http://{}:{}", supervisor.host(), supervisor.port()),
+        rate_limiting: RATE_LIMITER.stats(),
+        endpoints: openapi::ENDPOINTS.iter().map(|endpoint| endpoint.describe()).collect(),
+    };
+    
+    Ok(warp::reply::json(&info))
+}
+
+async fn handle_process_logs(
+    id: String,
+    params: PaginationParams,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let page = params.page.unwrap_or(1).max(1); 
+    let page_size = params.page_size.unwrap_or(50).max(1);
+
+    let (logs, total_lines) = supervisor.logs(&id, page, page_size).map_err(warp::reject::custom)?;
+    let response = LogsPage {
+        success: true,
+        page,
+        page_size,
+        total_lines,
+        logs,
+    };
+    Ok(warp::reply::json(&response))
+}
+
+async fn handle_shutdown(
+    audit: AuditContext,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    log_exeio_event(&supervisor, "Shutdown requested via API\n");
+    
+    // Stop all processes gracefully, including reattached ones that are only known by pid
+    for info in supervisor.list().iter().filter(|info| info.is_running) {
+        println!("Stopping process: {}", info.id);
+    }
+    // The kills are sent by each process's actor, so wait for them before exiting
+    let _ = tokio::time::timeout(Duration::from_secs(5), supervisor.shutdown()).await;
+    
+    log_exeio_event(&supervisor, "exeio supervisor shutting down\n");
+    
+    println!("Shutting down exeio supervisor...");
+    
+    let response = ApiResponse {
+        success: true,
+        message: "Supervisor shutdown initiated".to_string(),
+    };
+    
+    // Schedule shutdown after a brief delay to allow response to be sent
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        supervisor.logger().flush_process_logs();
+        cleanup_lock_file(&get_lock_file_path());
+        #[cfg(unix)]
+        unix_socket::cleanup_unix_socket();
+        std::process::exit(0);
+    });
+    
+    audit.record(None, None, &response);
+    
+    Ok(warp::reply::json(&response))
+}
+
+fn init_exeio_log(supervisor: &Supervisor) -> PathBuf {
+    let mut log_path = get_logs_dir();
+    log_path.push("exeio.log");
+    
+    // Log startup information
+    let start_log = format!("[{}] SYSTEM {}:{}: exeio process supervisor started\n", 
+        supervisor.now().format("%Y-%m-%d %H:%M:%S"), 
+        supervisor.host(), supervisor.port()
+    );
+    
+    let _ = supervisor.logger().safe_append_log(&log_path.to_string_lossy(), &start_log);
+    log_path
+}
+
+pub fn log_exeio_event(supervisor: &Supervisor, event: &str) {
+    let log_path = get_logs_dir().join("exeio.log");
+    let log_entry = format!("[{}] SYSTEM {}:{}: {}", 
+        supervisor.now().format("%Y-%m-%d %H:%M:%S"), 
+        supervisor.host(), supervisor.port(), event);
+    
+    let _ = supervisor.logger().safe_append_log(&log_path.to_string_lossy(), &log_entry);
+}
+
+fn ensure_single_instance() -> Result<(), String> {
+    let lock_path = get_lock_file_path();
+    
+    // Check if lock file exists and if the process is still running
+    if lock_path.exists()
+        && let Ok(pid_content) = fs::read_to_string(&lock_path)
+        && let Ok(pid) = pid_content.trim().parse::<u32>() {
+            // Our own pid when an upgrade exec'd this binary in place of the previous one
+            if pid != std::process::id() && is_process_running(pid) {
+                return Err(format!(
+                    "Another instance of exeio is already running (PID: {}). Lock file: {}",
+                    pid, lock_path.display()
+                ));
+            } else {
+                // Process not running, remove stale lock file
+                let _ = fs::remove_file(&lock_path);
+            }
+    }
+    
+    // Create lock file with current PID atomically using temp file + rename
+    let current_pid = std::process::id();
+    let temp_lock_path = lock_path.with_extension("lock.tmp");
+    
+    if let Err(e) = fs::write(&temp_lock_path, current_pid.to_string()) {
+        return Err(format!("Failed to create temp lock file: {}", e));
+    }
+    
+    if let Err(e) = fs::rename(&temp_lock_path, &lock_path) {
+        let _ = fs::remove_file(&temp_lock_path); // Clean up temp file
+        return Err(format!("Failed to create lock file atomically: {}", e));
+    }
+    
+    println!("Single instance lock acquired (PID: {}, Lock: {})", current_pid, lock_path.display());
+    Ok(())
+}
+
+fn is_process_running(pid: u32) -> bool {
+ 
+        use std::process::Command;
+        
+        // Use kill -0 to check if process exists without actually killing it
+        match Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .output()
+        {
+            Ok(output) => {
+                let result = output.status.success();
+                eprintln!("DEBUG: kill -0 {} -> exit_code: {:?}, success: {}", 
+                         pid, output.status.code(), result);
+                result
+            },
+            Err(e) => {
+                eprintln!("DEBUG: kill -0 {} -> error: {}", pid, e);
+                false
+            },
+        }
+
+}
+
+fn setup_cleanup_handler(lock_path: PathBuf, supervisor: Supervisor) {
+    // Set up signal handlers for graceful cleanup
+    #[cfg(unix)]
+    {
+        use signal_hook::{consts::SIGTERM, consts::SIGINT, iterator::Signals};
+        
+        let lock_path_clone = lock_path.clone();
+        thread::spawn(move || {
+            let mut signals = Signals::new([SIGINT, SIGTERM]).expect("Failed to create signal handler");
+            if signals.forever().next().is_some() {
+                supervisor.logger().flush_process_logs();
+                cleanup_lock_file(&lock_path_clone);
+                unix_socket::cleanup_unix_socket();
+                std::process::exit(0);
+            }
+        });
+    }
+    
+    // Also clean up on normal program termination
+    let lock_path_clone = lock_path;
+    std::panic::set_hook(Box::new(move |_| {
+        cleanup_lock_file(&lock_path_clone);
+    }));
+}
+
+fn cleanup_lock_file(lock_path: &PathBuf) {
+    if lock_path.exists() {
+        if let Err(e) = fs::remove_file(lock_path) {
+            eprintln!("Failed to remove lock file: {}", e);
+        } else {
+            println!("Lock file removed: {}", lock_path.display());
+        }
+    }
+}
+
@@ -0,0 +1,769 @@
+// exeio as a library: a Supervisor with the add/start/stop/restart/list/logs/events operations the HTTP API is built on
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+#[cfg(feature = "server")]
+use crate::email::EmailConfig;
+use crate::actor::Relays;
+use crate::error::ApiError;
+use crate::events::{Event, EventBus, EventKind};
+use crate::hooks::{self, Hook, HookContext, Hooks};
+use crate::log_rules::RuleCounters;
+#[cfg(feature = "server")]
+use crate::webhooks::Webhooks;
+use crate::{
+    AddProcessRequest, ManagedProcess, ProcessConfig, ProcessInfo, ProcessMap, ProcessStatus, RestartRequest,
+    SafeConfigManager, SafeLogger,
+};
+
+/// Where the saved process configs live.
+pub trait ConfigStore: Send + Sync {
+    /// The saved configs, empty if nothing has been saved yet.
+    fn load(&self) -> std::io::Result<Vec<ProcessConfig>>;
+
+    /// Replaces the saved configs.
+    fn save(&self, configs: &[ProcessConfig]) -> std::io::Result<()>;
+
+    /// File the config history is appended to; without one no history is kept.
+    fn history_path(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Where process output and exeio's lines about a process go, keyed by the process's `log_file`.
+pub trait LogSink: Send + Sync {
+    /// Appends `content`, one or more newline-terminated lines.
+    fn append(&self, log_file: &str, content: &str) -> std::io::Result<()>;
+
+    /// Lines newest first, `page` counting from 1, with the total number of lines.
+    fn read(&self, log_file: &str, page: usize, page_size: usize) -> std::io::Result<(Vec<String>, usize)>;
+
+    /// Drops everything logged so far.
+    fn clear(&self, log_file: &str) -> std::io::Result<()>;
+
+    /// Called before a process starts; an error keeps it from starting.
+    fn open(&self, _log_file: &str) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Time source for timestamps and restart backoff.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> chrono::DateTime<chrono::Utc>;
+}
+
+/// The JSON file exeio uses by default, `~/.config/exeio/processes.json`.
+pub struct FileConfigStore {
+    path: PathBuf,
+}
+
+impl FileConfigStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Default for FileConfigStore {
+    fn default() -> Self {
+        Self::new(crate::get_config_path())
+    }
+}
+
+impl ConfigStore for FileConfigStore {
+    fn load(&self) -> std::io::Result<Vec<ProcessConfig>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => Ok(serde_json::from_str(&content).unwrap_or_default()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn save(&self, configs: &[ProcessConfig]) -> std::io::Result<()> {
+        // Use atomic write: write to temp file, then rename
+        let temp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(configs)?)?;
+        std::fs::rename(&temp_path, &self.path)
+    }
+
+    fn history_path(&self) -> Option<PathBuf> {
+        Some(self.path.with_file_name("processes.history.jsonl"))
+    }
+}
+
+/// Keeps the saved configs in memory, for embedding exeio without touching the disk.
+#[derive(Default)]
+pub struct MemoryConfigStore {
+    configs: Mutex<Vec<ProcessConfig>>,
+}
+
+impl MemoryConfigStore {
+    pub fn new(configs: Vec<ProcessConfig>) -> Self {
+        Self { configs: Mutex::new(configs) }
+    }
+}
+
+impl ConfigStore for MemoryConfigStore {
+    fn load(&self) -> std::io::Result<Vec<ProcessConfig>> {
+        Ok(self.configs.lock().unwrap().clone())
+    }
+
+    fn save(&self, configs: &[ProcessConfig]) -> std::io::Result<()> {
+        *self.configs.lock().unwrap() = configs.to_vec();
+        Ok(())
+    }
+}
+
+/// Appends to the log files on disk, the default.
+#[derive(Debug, Default)]
+pub struct FileLogSink {
+    log_locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl FileLogSink {
+    fn get_log_lock(&self, log_path: &str) -> Arc<Mutex<()>> {
+        let mut locks = self.log_locks.lock().unwrap();
+        locks.entry(log_path.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .clone()
+    }
+}
+
+impl LogSink for FileLogSink {
+    fn append(&self, log_file: &str, content: &str) -> std::io::Result<()> {
+        let lock = self.get_log_lock(log_file);
+        let _guard = lock.lock().unwrap();
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)?;
+        file.write_all(content.as_bytes())?;
+        file.flush()
+    }
+
+    fn read(&self, log_file: &str, page: usize, page_size: usize) -> std::io::Result<(Vec<String>, usize)> {
+        crate::read_logs_reverse_paginated(log_file, page, page_size)
+    }
+
+    fn clear(&self, log_file: &str) -> std::io::Result<()> {
+        std::fs::write(log_file, "")
+    }
+
+    fn open(&self, log_file: &str) -> std::io::Result<()> {
+        OpenOptions::new().create(true).append(true).open(log_file).map(|_| ())
+    }
+}
+
+/// The system clock, the default.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc::now()
+    }
+}
+
+/// Configures and starts a [`Supervisor`].
+pub struct SupervisorBuilder {
+    config_store: Option<Arc<dyn ConfigStore>>,
+    log_sink: Option<Arc<dyn LogSink>>,
+    clock: Option<Arc<dyn Clock>>,
+    #[cfg(unix)]
+    runtime_dir: Option<PathBuf>,
+    host: String,
+    port: u16,
+}
+
+impl SupervisorBuilder {
+    /// Defaults to [`FileConfigStore`].
+    pub fn config_store(mut self, store: impl ConfigStore + 'static) -> Self {
+        self.config_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Defaults to [`FileLogSink`].
+    pub fn log_sink(mut self, sink: impl LogSink + 'static) -> Self {
+        self.log_sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Defaults to [`SystemClock`].
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Directory for the named pipes and state that let a supervisor started later reattach to the processes
+    /// this one leaves running. Without one, processes get ordinary pipes and nothing is written.
+    #[cfg(unix)]
+    pub fn runtime_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.runtime_dir = Some(dir.into());
+        self
+    }
+
+    /// Address named in the SYSTEM lines of the process logs.
+    pub fn address(mut self, host: impl Into<String>, port: u16) -> Self {
+        self.host = host.into();
+        self.port = port;
+        self
+    }
+
+    /// Must be called inside a tokio runtime.
+    pub fn build(self) -> Supervisor {
+        let clock = self.clock.unwrap_or_else(|| Arc::new(SystemClock));
+        let store = self.config_store.unwrap_or_else(|| Arc::new(FileConfigStore::default()));
+        let sink = self.log_sink.unwrap_or_else(|| Arc::new(FileLogSink::default()));
+        let (restarts, restart_requests) = tokio::sync::mpsc::unbounded_channel();
+
+        let supervisor = Supervisor {
+            inner: Arc::new(Inner {
+                processes: Arc::new(Mutex::new(HashMap::new())),
+                configs: SafeConfigManager::new(store, clock.clone()),
+                logger: SafeLogger::new(sink),
+                events: EventBus::new(),
+                clock,
+                restarts,
+                global_hooks: RwLock::new(Arc::new(Hooks::default())),
+                rule_counters: Mutex::new(HashMap::new()),
+                relays: Relays::new(),
+                #[cfg(feature = "server")]
+                webhooks: Webhooks::new(crate::get_logs_dir().join("webhook-dead-letters.jsonl")),
+                #[cfg(feature = "server")]
+                email: RwLock::new(None),
+                #[cfg(unix)]
+                state_lock: Mutex::new(()),
+                #[cfg(unix)]
+                runtime_dir: self.runtime_dir,
+                host: self.host,
+                port: self.port,
+            }),
+        };
+        supervisor.spawn_restart_handler(restart_requests);
+        supervisor
+    }
+}
+
+/// Runs and watches processes; what the exeio HTTP API drives. Clones share the same processes.
+#[derive(Clone)]
+pub struct Supervisor {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    processes: ProcessMap,
+    configs: SafeConfigManager,
+    logger: SafeLogger,
+    events: EventBus,
+    clock: Arc<dyn Clock>,
+    restarts: UnboundedSender<RestartRequest>,
+    // The config file's [hooks] section, run for every process before its own hooks
+    global_hooks: RwLock<Arc<Hooks>>,
+    // Process id -> rule name -> counters; outlives restarts, so a restart action doesn't reset them
+    rule_counters: Mutex<HashMap<String, HashMap<String, RuleCounters>>>,
+    relays: Relays,
+    #[cfg(feature = "server")]
+    webhooks: Webhooks,
+    // The config file's [email] section, None when email is off
+    #[cfg(feature = "server")]
+    email: RwLock<Option<Arc<EmailConfig>>>,
+    // Serializes rewrites of the runtime state file between processes starting at the same time
+    #[cfg(unix)]
+    state_lock: Mutex<()>,
+    #[cfg(unix)]
+    runtime_dir: Option<PathBuf>,
+    host: String,
+    port: u16,
+}
+
+impl Supervisor {
+    pub fn builder() -> SupervisorBuilder {
+        SupervisorBuilder {
+            config_store: None,
+            log_sink: None,
+            clock: None,
+            #[cfg(unix)]
+            runtime_dir: None,
+            host: "localhost".to_string(),
+            port: 0,
+        }
+    }
+
+    // The process entries, for the lifecycle code in lib.rs, actor.rs, log_rules.rs and runtime.rs; everything
+    // else goes through the methods below
+    pub(crate) fn process_map(&self) -> &ProcessMap {
+        &self.inner.processes
+    }
+
+    pub(crate) fn logger(&self) -> &SafeLogger {
+        &self.inner.logger
+    }
+
+    // The config store, with its history
+    #[cfg(feature = "server")]
+    pub(crate) fn saved(&self) -> &SafeConfigManager {
+        &self.inner.configs
+    }
+
+    pub(crate) fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        self.inner.clock.now()
+    }
+
+    pub(crate) fn global_hooks(&self) -> &RwLock<Arc<Hooks>> {
+        &self.inner.global_hooks
+    }
+
+    pub(crate) fn rule_counters(&self) -> &Mutex<HashMap<String, HashMap<String, RuleCounters>>> {
+        &self.inner.rule_counters
+    }
+
+    pub(crate) fn relays(&self) -> &Relays {
+        &self.inner.relays
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn webhooks(&self) -> &Webhooks {
+        &self.inner.webhooks
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn email(&self) -> &RwLock<Option<Arc<EmailConfig>>> {
+        &self.inner.email
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn state_lock(&self) -> &Mutex<()> {
+        &self.inner.state_lock
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn runtime_dir(&self) -> Option<&std::path::Path> {
+        self.inner.runtime_dir.as_deref()
+    }
+
+    pub(crate) fn emit(&self, process_id: &str, kind: EventKind) {
+        self.inner.events.emit(process_id, kind, self.now());
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn host(&self) -> &str {
+        &self.inner.host
+    }
+
+    #[cfg(feature = "server")]
+    pub(crate) fn port(&self) -> u16 {
+        self.inner.port
+    }
+
+    // Appends a SYSTEM line to a process log
+    pub(crate) fn log_system(&self, log_file: &str, message: &str) {
+        let entry = format!("[{}] SYSTEM {}:{}: {}\n",
+            self.now().format("%Y-%m-%d %H:%M:%S"), self.inner.host, self.inner.port, message);
+        let _ = self.inner.logger.append_process_log(log_file, &entry);
+    }
+
+    // Carried out by the restart handler after the request's delay
+    pub(crate) fn request_restart(&self, request: RestartRequest) {
+        let _ = self.inner.restarts.send(request);
+    }
+
+    // Auto-restarts are requested by the monitors and carried out here after their delay. Holds the supervisor
+    // weakly, so it ends once the last clone is dropped
+    fn spawn_restart_handler(&self, mut requests: UnboundedReceiver<RestartRequest>) {
+        let inner = Arc::downgrade(&self.inner);
+        tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                tokio::time::sleep(Duration::from_secs(request.delay_seconds)).await;
+                let Some(inner) = inner.upgrade() else {
+                    break;
+                };
+                let supervisor = Supervisor { inner };
+
+                // Get the config for this process
+                let config = {
+                    let processes_lock = supervisor.inner.processes.lock().unwrap();
+                    processes_lock.get(&request.process_id).map(|p| p.config.clone())
+                };
+
+                if let Some(config) = config {
+                    supervisor.log_system(&config.log_file, &request.reason);
+                    crate::start_process(&supervisor, config).await;
+                }
+            }
+        });
+    }
+
+    /// Starts the processes in the config store that aren't supervised yet.
+    pub async fn load_saved(&self) {
+        self.load_saved_except(|_| false).await;
+    }
+
+    // Processes reattached from the previous supervisor are already in the map; `skip` leaves out the ones
+    // the config file declares, as it wins for those
+    pub(crate) async fn load_saved_except(&self, skip: impl Fn(&str) -> bool) {
+        let configs = self.inner.configs.load_configs();
+        for config in configs.into_iter().filter(|config| !skip(&config.id)) {
+            if self.inner.processes.lock().unwrap().contains_key(&config.id) {
+                continue;
+            }
+            crate::start_process(self, config).await;
+        }
+    }
+
+    /// Adds a process and starts it, saving it to the config store if `save_for_next_run` is set.
+    pub async fn add(&self, req: AddProcessRequest) -> Result<ProcessInfo, ApiError> {
+        let id = req.id.clone();
+        self.add_as(req, "library").await?;
+        self.get(&id)
+    }
+
+    // Validates, saves and starts a new process; `actor` is recorded in the config history
+    pub(crate) async fn add_as(&self, req: AddProcessRequest, actor: &str) -> Result<ProcessConfig, ApiError> {
+        let invalid_fields = crate::validate_process_fields(&req.id, &req.command, req.periodic.unwrap_or(false), req.period_seconds, &req.hooks, &req.log_rules);
+        if !invalid_fields.is_empty() {
+            return Err(ApiError::Validation(invalid_fields));
+        }
+
+        let log_path = crate::get_process_log_path(&req.id);
+        let config = ProcessConfig {
+            id: req.id,
+            command: req.command,
+            args: req.args,
+            working_dir: req.working_dir,
+            auto_restart: req.auto_restart,
+            log_file: log_path.to_string_lossy().into_owned(),
+            periodic: req.periodic.unwrap_or(false),
+            period_seconds: req.period_seconds,
+            hooks: req.hooks,
+            log_rules: req.log_rules,
+        };
+
+        // Reserve the id under the lock, so a concurrent add of the same id fails while the pre_start hook runs
+        {
+            let mut processes_lock = self.inner.processes.lock().unwrap();
+            if processes_lock.contains_key(&config.id) {
+                return Err(ApiError::ProcessExists(config.id));
+            }
+            processes_lock.insert(config.id.clone(), ManagedProcess {
+                config: config.clone(),
+                pid: None,
+                actor: None,
+                run_count: 1,
+                last_run: None,
+                periodic_handle: None,
+                status: ProcessStatus::Starting,
+                last_exit_time: None,
+                pending_restart: false,
+            });
+        }
+        self.emit(&config.id, EventKind::Added);
+
+        // Save to configuration file if requested
+        if req.save_for_next_run
+            && let Err(e) = self.inner.configs.save_process_config(&config, actor) {
+                eprintln!("Failed to save process config: {}", e);
+        }
+
+        crate::start_process(self, config.clone()).await;
+        Ok(config)
+    }
+
+    /// Starts a process that isn't running; does nothing if it is.
+    pub async fn start(&self, id: &str) -> Result<(), ApiError> {
+        let info = self.get(id)?;
+        if info.is_running {
+            return Ok(());
+        }
+        self.restart(id).await
+    }
+
+    /// Stops a process; it stays supervised and isn't auto-restarted.
+    pub fn stop(&self, id: &str) -> Result<(), ApiError> {
+        self.stop_because(id, "Process stopped manually")
+    }
+
+    // Stops a process without removing it, noting `reason` in its log
+    pub(crate) fn stop_because(&self, id: &str, reason: &str) -> Result<(), ApiError> {
+        let mut processes_lock = self.inner.processes.lock().unwrap();
+        let managed_process = processes_lock.get_mut(id).ok_or_else(|| ApiError::ProcessNotFound(id.to_string()))?;
+        // Nothing replaces it, so the actor is left to reap the child on its own
+        let _ = crate::halt_process(self, managed_process, ProcessStatus::ManuallyStopped); // Mark as manually stopped
+        self.log_system(&managed_process.config.log_file, reason);
+        Ok(())
+    }
+
+    pub async fn restart(&self, id: &str) -> Result<(), ApiError> {
+        self.restart_because(id, &format!("Process '{}' restarted manually", id)).await
+    }
+
+    // Kills the process if it is running and starts it again with its live config; `reason` goes to the
+    // on_restart hook
+    pub(crate) async fn restart_because(&self, id: &str, reason: &str) -> Result<(), ApiError> {
+        let (config, was_running, reaped, run_count) = {
+            let mut processes_lock = self.inner.processes.lock().unwrap();
+            let managed_process = processes_lock.get_mut(id).ok_or_else(|| ApiError::ProcessNotFound(id.to_string()))?;
+            let was_running = managed_process.actor.is_some() || managed_process.periodic_handle.is_some();
+            // Also kills reattached processes and ones whose child handle the auto-restart monitor holds
+            let reaped = crate::halt_process(self, managed_process, ProcessStatus::Stopped);
+            // Increment run count for manual restart
+            managed_process.run_count += 1;
+            (managed_process.config.clone(), was_running, reaped, managed_process.run_count)
+        };
+        // Lets the pre_stop hook finish and the old instance exit before the new one starts
+        if let Some(reaped) = reaped {
+            let _ = reaped.await;
+        }
+        if was_running {
+            let context = HookContext { reason: Some(reason.to_string()), ..HookContext::run(run_count) };
+            hooks::spawn(self, vec![Hook::OnRestart], config.clone(), context);
+        }
+
+        crate::start_process(self, config).await;
+        Ok(())
+    }
+
+    /// Stops a process and drops it from the supervisor and the config store.
+    pub fn remove(&self, id: &str) -> Result<(), ApiError> {
+        self.remove_as(id, "library").map(|_| ())
+    }
+
+    // `actor` is recorded in the config history. The returned task finishes once the process has exited
+    pub(crate) fn remove_as(&self, id: &str, actor: &str) -> Result<Option<tokio::task::JoinHandle<()>>, ApiError> {
+        let mut managed_process = self.inner.processes.lock().unwrap().remove(id).ok_or_else(|| ApiError::ProcessNotFound(id.to_string()))?;
+        let reaped = crate::halt_process(self, &mut managed_process, ProcessStatus::Stopped);
+        self.log_system(&managed_process.config.log_file, "Process removed from supervisor");
+
+        // Remove from saved configuration
+        if self.inner.configs.is_saved(id)
+            && let Err(e) = self.inner.configs.remove_process_config(id, actor) {
+                eprintln!("Failed to remove process config: {}", e);
+        }
+        self.emit(id, EventKind::Removed);
+        crate::log_rules::forget(self, id);
+        Ok(reaped)
+    }
+
+    pub fn list(&self) -> Vec<ProcessInfo> {
+        let processes_lock = self.inner.processes.lock().unwrap();
+        let mut processes: Vec<ProcessInfo> = processes_lock
+            .iter()
+            .map(|(id, managed_process)| crate::process_info(self, id, managed_process))
+            .collect();
+        processes.sort_by(|a, b| a.id.cmp(&b.id));
+        processes
+    }
+
+    pub fn get(&self, id: &str) -> Result<ProcessInfo, ApiError> {
+        let processes_lock = self.inner.processes.lock().unwrap();
+        processes_lock
+            .get(id)
+            .map(|managed_process| crate::process_info(self, id, managed_process))
+            .ok_or_else(|| ApiError::ProcessNotFound(id.to_string()))
+    }
+
+    /// Log lines newest first, `page` counting from 1, with the total number of lines.
+    pub fn logs(&self, id: &str, page: usize, page_size: usize) -> Result<(Vec<String>, usize), ApiError> {
+        let log_file = self.log_file(id)?;
+        self.inner.logger
+            .process_sink()
+            .read(&log_file, page, page_size)
+            .map_err(|e| ApiError::Io(format!("Failed to read logs for process {}: {}", id, e)))
+    }
+
+    pub(crate) fn log_file(&self, id: &str) -> Result<String, ApiError> {
+        self.inner.processes
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|managed_process| managed_process.config.log_file.clone())
+            .ok_or_else(|| ApiError::ProcessNotFound(id.to_string()))
+    }
+
+    /// Writes a line to the process's stdin.
+    pub fn send_input(&self, id: &str, input: &str) -> Result<(), ApiError> {
+        let processes_lock = self.inner.processes.lock().unwrap();
+        let managed_process = processes_lock.get(id).ok_or_else(|| ApiError::ProcessNotFound(id.to_string()))?;
+        if managed_process.config.periodic || managed_process.actor.as_ref().is_some_and(|actor| !actor.is_interactive()) {
+            return Err(ApiError::ProcessNotInteractive(id.to_string()));
+        }
+        // The actor is gone once the process has exited
+        managed_process
+            .actor
+            .as_ref()
+            .ok_or_else(|| ApiError::ProcessNotRunning(id.to_string()))?
+            .send(crate::actor::ProcessCommand::Input(input.to_string()))
+            .map_err(|_| ApiError::ProcessNotRunning(id.to_string()))
+    }
+
+    /// Process lifecycle events from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.inner.events.subscribe()
+    }
+
+    /// Like [`Supervisor::subscribe`], first returning the recent events with an id above `since`; the
+    /// supervisor keeps the last 1000.
+    pub fn subscribe_since(&self, since: u64) -> (Vec<Event>, broadcast::Receiver<Event>) {
+        self.inner.events.subscribe_since(since)
+    }
+
+    /// Stops every process, leaving them supervised, and waits for them to exit.
+    pub async fn shutdown(&self) {
+        let reaped: Vec<tokio::task::JoinHandle<()>> = {
+            let mut processes_lock = self.inner.processes.lock().unwrap();
+            processes_lock
+                .values_mut()
+                .filter_map(|managed_process| {
+                    let reaped = crate::halt_process(self, managed_process, ProcessStatus::ManuallyStopped);
+                    self.log_system(&managed_process.config.log_file, "Process stopped due to supervisor shutdown");
+                    reaped
+                })
+                .collect()
+        };
+        for handle in reaped {
+            let _ = handle.await;
+        }
+    }
+}
+
+// What only the HTTP API needs
+#[cfg(feature = "server")]
+impl Supervisor {
+    pub(crate) fn contains(&self, id: &str) -> bool {
+        self.inner.processes.lock().unwrap().contains_key(id)
+    }
+
+    pub(crate) fn config(&self, id: &str) -> Result<ProcessConfig, ApiError> {
+        self.inner.processes
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|managed_process| managed_process.config.clone())
+            .ok_or_else(|| ApiError::ProcessNotFound(id.to_string()))
+    }
+
+    // The live config of every supervised process, and whether it is running or waiting for its next period
+    pub(crate) fn live_configs(&self) -> HashMap<String, (ProcessConfig, bool)> {
+        self.inner.processes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, managed_process)| {
+                let is_active = matches!(managed_process.status, ProcessStatus::Running | ProcessStatus::WaitingForPeriod);
+                (id.clone(), (managed_process.config.clone(), is_active))
+            })
+            .collect()
+    }
+
+    // Starts a process that isn't supervised yet, or starts one that is again with `config`
+    pub(crate) async fn launch(&self, config: ProcessConfig) {
+        crate::start_process(self, config).await;
+    }
+
+    // Stops the process with the same id if there is one, noting `note` in its log, and starts `config` in its
+    // place, keeping the run count. True if a process was replaced
+    pub(crate) async fn replace(&self, config: ProcessConfig, note: &str) -> bool {
+        let (replaced, reaped) = {
+            let mut processes_lock = self.inner.processes.lock().unwrap();
+            match processes_lock.get_mut(&config.id) {
+                Some(managed_process) => {
+                    let reaped = crate::halt_process(self, managed_process, ProcessStatus::Stopped);
+                    managed_process.run_count += 1;
+                    self.log_system(&managed_process.config.log_file, note);
+                    (true, reaped)
+                }
+                None => (false, None),
+            }
+        };
+        if let Some(reaped) = reaped {
+            let _ = reaped.await;
+        }
+        crate::start_process(self, config).await;
+        replaced
+    }
+
+    // Puts a changed config in place of a process's live one, noting `note` in its log. Returns what was done
+    pub(crate) async fn reconfigure(&self, id: &str, config: ProcessConfig, apply: crate::ApplyMode, note: &str) -> Result<&'static str, ApiError> {
+        let mut restart = false;
+        let mut reaped = None;
+        let outcome = {
+            let mut processes_lock = self.inner.processes.lock().unwrap();
+            let managed_process = processes_lock.get_mut(id).ok_or_else(|| ApiError::ProcessNotFound(id.to_string()))?;
+            self.log_system(&managed_process.config.log_file, note);
+
+            // A failed process already has an auto-restart queued, which picks up the new config
+            let is_active = matches!(managed_process.status, ProcessStatus::Running | ProcessStatus::WaitingForPeriod);
+            match apply {
+                crate::ApplyMode::SaveOnly => "saved for the next supervisor start",
+                _ if crate::config_diff(&managed_process.config, &config).is_empty() => "nothing to apply",
+                crate::ApplyMode::RestartNow if is_active => {
+                    reaped = crate::halt_process(self, managed_process, ProcessStatus::Stopped);
+                    managed_process.config = config.clone();
+                    managed_process.run_count += 1;
+                    restart = true;
+                    "restarted with the new config"
+                }
+                crate::ApplyMode::RestartNow | crate::ApplyMode::OnNextRestart => {
+                    managed_process.config = config.clone();
+                    managed_process.pending_restart = is_active;
+                    "applied on the next restart"
+                }
+            }
+        };
+
+        if let Some(reaped) = reaped {
+            let _ = reaped.await;
+        }
+        if restart {
+            crate::start_process(self, config).await;
+        }
+        Ok(outcome)
+    }
+
+    // Restarts the processes whose id passes `filter`
+    pub(crate) async fn restart_where(&self, filter: impl Fn(&str) -> bool) {
+        let (configs, reaped): (Vec<ProcessConfig>, Vec<tokio::task::JoinHandle<()>>) = {
+            let mut processes_lock = self.inner.processes.lock().unwrap();
+            let mut configs = Vec::new();
+            let mut reaped = Vec::new();
+            for (_, managed_process) in processes_lock.iter_mut().filter(|(id, _)| filter(id)) {
+                reaped.extend(crate::halt_process(self, managed_process, ProcessStatus::Stopped));
+                configs.push(managed_process.config.clone());
+            }
+            (configs, reaped)
+        };
+        // The old instances have to exit before their replacements start
+        for handle in reaped {
+            let _ = handle.await;
+        }
+        for config in configs {
+            crate::start_process(self, config).await;
+        }
+    }
+
+    // Stops the processes whose id passes `filter`, without waiting for them to exit
+    pub(crate) fn stop_where(&self, filter: impl Fn(&str) -> bool) {
+        let mut processes_lock = self.inner.processes.lock().unwrap();
+        for (_, managed_process) in processes_lock.iter_mut().filter(|(id, _)| filter(id)) {
+            let _ = crate::halt_process(self, managed_process, ProcessStatus::ManuallyStopped); // Mark as manually stopped
+        }
+    }
+
+    pub(crate) async fn clear_log(&self, id: &str) -> Result<(), ApiError> {
+        let log_file = self.log_file(id)?;
+        // Lines still queued would otherwise land after the clear
+        let supervisor = self.clone();
+        tokio::task::spawn_blocking(move || {
+            supervisor.inner.logger.flush_process_logs();
+            supervisor.inner.logger.process_sink().clear(&log_file)
+        })
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+        .map_err(|e| ApiError::Io(format!("Failed to clear log for process {}: {}", id, e)))
+    }
+}
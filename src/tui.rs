@@ -0,0 +1,640 @@
+// `exeio tui`: a top-like terminal view of a running supervisor for SSH sessions. The process list follows
+// GET /v1/events and is polled as well, for CPU use and for when the event stream is down; the log of the
+// selected process is polled, as the API has no log stream
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+use crate::client::{Client, Connection};
+
+const TICK: Duration = Duration::from_secs(1);
+// The list is also refreshed on every event, the poll keeps CPU and uptime current
+const LIST_POLL_TICKS: u64 = 2;
+const LOG_LINES: usize = 1000;
+const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+struct ProcessRow {
+    id: String,
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    status: String,
+    periodic: bool,
+    run_count: u64,
+    last_run: Option<chrono::DateTime<chrono::Utc>>,
+    pid: Option<u32>,
+    resources: Option<Resources>,
+}
+
+#[derive(Deserialize)]
+struct Resources {
+    cpu_seconds: f64,
+    memory_bytes: u64,
+}
+
+#[derive(Deserialize)]
+struct LogsPage {
+    total_lines: u64,
+    logs: Vec<String>,
+}
+
+// Results of the background requests and terminal input, handled one at a time by the main loop
+enum Update {
+    Processes(Result<Vec<ProcessRow>, String>),
+    Logs { id: String, result: Result<LogsPage, String> },
+    Stream(bool),
+    Event(String),
+    Done(Result<String, String>),
+    Key(KeyEvent),
+    Resize,
+}
+
+enum Mode {
+    Normal,
+    Input(String),
+    ConfirmRemove(String),
+}
+
+// A CPU time reading, so the next one can be turned into a percentage
+struct CpuSample {
+    pid: u32,
+    cpu_seconds: f64,
+    at: Instant,
+}
+
+struct App {
+    client: Client,
+    sender: UnboundedSender<Update>,
+    processes: Vec<ProcessRow>,
+    selected: Option<String>,
+    cpu_samples: HashMap<String, CpuSample>,
+    cpu_percent: HashMap<String, f64>,
+    logs: Vec<String>,
+    logs_of: Option<String>,
+    log_total: u64,
+    // Lines between the bottom of the log and the last line shown, 0 while following
+    scroll: usize,
+    follow: bool,
+    log_height: usize,
+    mode: Mode,
+    message: Option<(String, bool, Instant)>,
+    live: bool,
+    last_event: Option<String>,
+    list_error: Option<String>,
+    list_in_flight: bool,
+    logs_in_flight: bool,
+    ticks: u64,
+    quit: bool,
+}
+
+pub async fn run(connection: &Connection) -> Result<(), String> {
+    let client = Client::new(connection)?;
+    // Fail before taking over the terminal if the supervisor can't be reached
+    client.get("/v1/processes").await?;
+
+    let (sender, mut receiver) = unbounded_channel();
+    let input_sender = sender.clone();
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            let update = match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press => Update::Key(key),
+                Event::Resize(_, _) => Update::Resize,
+                _ => continue,
+            };
+            if input_sender.send(update).is_err() {
+                break;
+            }
+        }
+    });
+    tokio::spawn(watch_events(client.clone(), sender.clone()));
+
+    let mut terminal = ratatui::try_init().map_err(|e| format!("Failed to set up the terminal: {}", e))?;
+    let mut app = App::new(client, sender);
+    let result = app.run(&mut terminal, &mut receiver).await;
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn new(client: Client, sender: UnboundedSender<Update>) -> Self {
+        Self {
+            client,
+            sender,
+            processes: Vec::new(),
+            selected: None,
+            cpu_samples: HashMap::new(),
+            cpu_percent: HashMap::new(),
+            logs: Vec::new(),
+            logs_of: None,
+            log_total: 0,
+            scroll: 0,
+            follow: true,
+            log_height: 0,
+            mode: Mode::Normal,
+            message: None,
+            live: false,
+            last_event: None,
+            list_error: None,
+            list_in_flight: false,
+            logs_in_flight: false,
+            ticks: 0,
+            quit: false,
+        }
+    }
+
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        receiver: &mut tokio::sync::mpsc::UnboundedReceiver<Update>,
+    ) -> Result<(), String> {
+        let mut tick = tokio::time::interval(TICK);
+        self.refresh_list();
+        while !self.quit {
+            terminal
+                .draw(|frame| self.draw(frame))
+                .map_err(|e| format!("Failed to draw: {}", e))?;
+            tokio::select! {
+                Some(update) = receiver.recv() => self.update(update),
+                _ = tick.tick() => self.tick(),
+            }
+        }
+        Ok(())
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(LIST_POLL_TICKS) {
+            self.refresh_list();
+        }
+        self.refresh_logs();
+        if self.message.as_ref().is_some_and(|(_, _, at)| at.elapsed() > MESSAGE_DURATION) {
+            self.message = None;
+        }
+    }
+
+    fn refresh_list(&mut self) {
+        if self.list_in_flight {
+            return;
+        }
+        self.list_in_flight = true;
+        let client = self.client.clone();
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let result = client.get("/v1/processes").await.and_then(|value| {
+                serde_json::from_value(value).map_err(|e| format!("Unexpected process list: {}", e))
+            });
+            let _ = sender.send(Update::Processes(result));
+        });
+    }
+
+    fn refresh_logs(&mut self) {
+        let Some(id) = self.selected.clone() else {
+            return;
+        };
+        if self.logs_in_flight {
+            return;
+        }
+        self.logs_in_flight = true;
+        let client = self.client.clone();
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let path = format!("/v1/processes/{}/logs?page=1&page_size={}", id, LOG_LINES);
+            let result = client.get(&path).await.and_then(|value| {
+                serde_json::from_value(value).map_err(|e| format!("Unexpected log page: {}", e))
+            });
+            let _ = sender.send(Update::Logs { id, result });
+        });
+    }
+
+    fn update(&mut self, update: Update) {
+        match update {
+            Update::Processes(result) => {
+                self.list_in_flight = false;
+                match result {
+                    Ok(mut processes) => {
+                        processes.sort_by(|a, b| a.id.cmp(&b.id));
+                        self.sample_cpu(&processes);
+                        self.processes = processes;
+                        self.list_error = None;
+                        let selected_exists = self.selected.as_ref().is_some_and(|id| self.index_of(id).is_some());
+                        if !selected_exists {
+                            self.select(self.processes.first().map(|process| process.id.clone()));
+                        }
+                    }
+                    Err(e) => self.list_error = Some(e),
+                }
+            }
+            Update::Logs { id, result } => {
+                self.logs_in_flight = false;
+                // The selection may have moved on while the request ran
+                if self.selected.as_ref() != Some(&id) {
+                    return;
+                }
+                match result {
+                    Ok(page) => {
+                        // Keep the view still while scrolled back, a cleared log starts over
+                        if !self.follow && self.logs_of.as_ref() == Some(&id) && page.total_lines >= self.log_total {
+                            self.scroll += (page.total_lines - self.log_total) as usize;
+                        }
+                        self.logs = page.logs.into_iter().rev().collect();
+                        self.scroll = self.scroll.min(self.max_scroll());
+                        self.log_total = page.total_lines;
+                        self.logs_of = Some(id);
+                    }
+                    Err(e) => self.show(e, true),
+                }
+            }
+            Update::Stream(live) => self.live = live,
+            Update::Event(description) => {
+                self.last_event = Some(description);
+                self.refresh_list();
+            }
+            Update::Done(result) => {
+                match result {
+                    Ok(message) => self.show(message, false),
+                    Err(e) => self.show(e, true),
+                }
+                self.refresh_list();
+                self.refresh_logs();
+            }
+            Update::Key(key) => self.key(key),
+            Update::Resize => {}
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return;
+        }
+        match &mut self.mode {
+            Mode::Input(line) => match key.code {
+                KeyCode::Enter => {
+                    let input = std::mem::take(line);
+                    self.mode = Mode::Normal;
+                    if let Some(id) = self.selected.clone() {
+                        self.action(Method::POST, format!("/v1/processes/{}/input", id), Some(json!({ "input": input })));
+                    }
+                }
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Backspace => {
+                    line.pop();
+                }
+                KeyCode::Char(c) => line.push(c),
+                _ => {}
+            },
+            Mode::ConfirmRemove(id) => {
+                let id = id.clone();
+                self.mode = Mode::Normal;
+                if let KeyCode::Char('y') | KeyCode::Char('Y') = key.code {
+                    self.action(Method::DELETE, format!("/v1/processes/{}", id), None);
+                }
+            }
+            Mode::Normal => self.normal_key(key),
+        }
+    }
+
+    fn normal_key(&mut self, key: KeyEvent) {
+        let page = self.log_height.max(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.scroll_logs(page as isize),
+            KeyCode::PageDown => self.scroll_logs(-(page as isize)),
+            KeyCode::Char('g') | KeyCode::Home => self.scroll_logs(isize::MAX),
+            KeyCode::Char('G') | KeyCode::End => self.scroll_logs(isize::MIN),
+            KeyCode::Char('f') => {
+                self.follow = !self.follow;
+                if self.follow {
+                    self.scroll = 0;
+                }
+            }
+            KeyCode::Char('s') => self.on_selected(|id| (Method::POST, format!("/v1/processes/{}/actions/stop", id))),
+            KeyCode::Char('r') => self.on_selected(|id| (Method::POST, format!("/v1/processes/{}/actions/restart", id))),
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(id) = self.selected.clone() {
+                    self.mode = Mode::ConfirmRemove(id);
+                }
+            }
+            KeyCode::Char('i') if self.selected.is_some() => self.mode = Mode::Input(String::new()),
+            _ => {}
+        }
+    }
+
+    fn on_selected(&mut self, request: impl FnOnce(&str) -> (Method, String)) {
+        if let Some(id) = self.selected.clone() {
+            let (method, path) = request(&id);
+            self.action(method, path, None);
+        }
+    }
+
+    fn action(&mut self, method: Method, path: String, body: Option<Value>) {
+        let client = self.client.clone();
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let result = client.request(method, &path, body).await.map(|response| {
+                response.get("message").and_then(Value::as_str).unwrap_or("Done").to_string()
+            });
+            let _ = sender.send(Update::Done(result));
+        });
+    }
+
+    fn show(&mut self, message: String, error: bool) {
+        self.message = Some((message, error, Instant::now()));
+    }
+
+    fn index_of(&self, id: &str) -> Option<usize> {
+        self.processes.iter().position(|process| process.id == id)
+    }
+
+    fn select(&mut self, id: Option<String>) {
+        if self.selected == id {
+            return;
+        }
+        self.selected = id;
+        self.logs.clear();
+        self.logs_of = None;
+        self.log_total = 0;
+        self.scroll = 0;
+        self.follow = true;
+        self.refresh_logs();
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.processes.is_empty() {
+            return;
+        }
+        let current = self.selected.as_ref().and_then(|id| self.index_of(id)).unwrap_or(0);
+        let next = current.saturating_add_signed(delta).min(self.processes.len() - 1);
+        self.select(Some(self.processes[next].id.clone()));
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.logs.len().saturating_sub(self.log_height)
+    }
+
+    // Positive scrolls back towards older lines; back at the bottom the log follows again
+    fn scroll_logs(&mut self, delta: isize) {
+        self.scroll = self.scroll.saturating_add_signed(delta).min(self.max_scroll());
+        self.follow = self.scroll == 0;
+    }
+
+    fn sample_cpu(&mut self, processes: &[ProcessRow]) {
+        let now = Instant::now();
+        let mut samples = HashMap::new();
+        self.cpu_percent.clear();
+        for process in processes {
+            let (Some(pid), Some(resources)) = (process.pid, &process.resources) else {
+                continue;
+            };
+            if let Some(previous) = self.cpu_samples.get(&process.id)
+                && previous.pid == pid
+            {
+                let elapsed = now.duration_since(previous.at).as_secs_f64();
+                if elapsed > 0.0 {
+                    let percent = (resources.cpu_seconds - previous.cpu_seconds).max(0.0) / elapsed * 100.0;
+                    self.cpu_percent.insert(process.id.clone(), percent);
+                }
+            }
+            samples.insert(process.id.clone(), CpuSample { pid, cpu_seconds: resources.cpu_seconds, at: now });
+        }
+        self.cpu_samples = samples;
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let area = frame.area();
+        let table_height = (self.processes.len() as u16 + 3).clamp(4, (area.height * 2 / 5).max(4));
+        let [header, table, logs, message, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(table_height),
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+        self.draw_header(frame, header);
+        self.draw_table(frame, table);
+        self.draw_logs(frame, logs);
+        self.draw_message(frame, message);
+        self.draw_footer(frame, footer);
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let mut spans = vec![
+            Span::from(" exeio ").bold().reversed(),
+            Span::raw(format!("  {}  ", self.client.base_url())),
+            if self.live { Span::from("● live").green() } else { Span::from("● polling").yellow() },
+            Span::raw(format!("  {} processes", self.processes.len())),
+        ];
+        if let Some(event) = &self.last_event {
+            spans.push(Span::raw("  last event: ").dark_gray());
+            spans.push(Span::raw(event.clone()));
+        }
+        frame.render_widget(Line::from(spans), area);
+    }
+
+    fn draw_table(&self, frame: &mut Frame, area: Rect) {
+        let now = chrono::Utc::now();
+        let rows = self.processes.iter().map(|process| {
+            let running = process.status == "running";
+            let uptime = match process.last_run {
+                Some(started) if running && !process.periodic => format_duration((now - started).num_seconds()),
+                _ => "-".to_string(),
+            };
+            let restarts = if process.periodic {
+                format!("{} runs", process.run_count)
+            } else {
+                process.run_count.saturating_sub(1).to_string()
+            };
+            let cpu = self.cpu_percent.get(&process.id).map(|percent| format!("{:.1}", percent));
+            let memory = process.resources.as_ref().map(|resources| format_bytes(resources.memory_bytes));
+            let mut command = process.command.clone();
+            for arg in crate::redact::redact_args(&process.args) {
+                command.push(' ');
+                command.push_str(&arg);
+            }
+            Row::new(vec![
+                Span::raw(process.id.clone()),
+                Span::styled(process.status.clone(), status_style(&process.status)),
+                Span::raw(process.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string())),
+                Span::raw(uptime),
+                Span::raw(restarts),
+                Span::raw(cpu.unwrap_or_else(|| "-".to_string())),
+                Span::raw(memory.unwrap_or_else(|| "-".to_string())),
+                Span::raw(command),
+            ])
+        });
+        let widths = [
+            Constraint::Length(16),
+            Constraint::Length(17),
+            Constraint::Length(8),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ];
+        let header = Row::new(["ID", "STATUS", "PID", "UPTIME", "RESTARTS", "CPU%", "MEM", "COMMAND"])
+            .style(Style::new().add_modifier(Modifier::BOLD));
+        let title = match &self.list_error {
+            Some(e) => Line::from(format!(" Processes: {} ", e)).red(),
+            None => Line::from(" Processes "),
+        };
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(title))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = TableState::default().with_selected(self.selected.as_ref().and_then(|id| self.index_of(id)));
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn draw_logs(&mut self, frame: &mut Frame, area: Rect) {
+        self.log_height = area.height.saturating_sub(2) as usize;
+        self.scroll = self.scroll.min(self.max_scroll());
+        let end = self.logs.len() - self.scroll;
+        let start = end.saturating_sub(self.log_height);
+        let lines: Vec<Line> = self.logs[start..end].iter().map(|line| Line::styled(line.clone(), log_style(line))).collect();
+
+        let title = match &self.selected {
+            Some(id) => format!(" Logs: {} ", id),
+            None => " Logs ".to_string(),
+        };
+        let position = if self.follow {
+            " following ".to_string()
+        } else {
+            format!(" {} lines up, f to follow ", self.scroll)
+        };
+        let block = Block::bordered().title(title).title_bottom(Line::from(position).right_aligned());
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_message(&self, frame: &mut Frame, area: Rect) {
+        if let Some((message, error, _)) = &self.message {
+            let style = if *error { Style::new().red() } else { Style::new().green() };
+            frame.render_widget(Line::styled(format!(" {}", message), style), area);
+        }
+    }
+
+    fn draw_footer(&self, frame: &mut Frame, area: Rect) {
+        let line = match &self.mode {
+            Mode::Input(text) => {
+                let id = self.selected.as_deref().unwrap_or_default();
+                frame.set_cursor_position((area.x + (id.len() + text.chars().count() + 9) as u16, area.y));
+                Line::from(vec![Span::from(format!(" input {}> ", id)).bold(), Span::raw(text.clone())])
+            }
+            Mode::ConfirmRemove(id) => Line::from(format!(" Remove {}? y to confirm, any other key to cancel", id)).bold(),
+            Mode::Normal => Line::from(
+                " ↑↓ select  PgUp/PgDn scroll  f follow  s stop  r restart  d remove  i input  q quit",
+            )
+            .dark_gray(),
+        };
+        frame.render_widget(line, area);
+    }
+}
+
+// Reads GET /v1/events, reconnecting with a growing delay; while it is down the list is only polled
+async fn watch_events(client: Client, sender: UnboundedSender<Update>) {
+    let mut delay = TICK;
+    loop {
+        if let Ok(mut response) = client.stream("/v1/events").await {
+            if sender.send(Update::Stream(true)).is_err() {
+                return;
+            }
+            delay = TICK;
+            let mut buffer: Vec<u8> = Vec::new();
+            while let Ok(Some(chunk)) = response.chunk().await {
+                buffer.extend_from_slice(&chunk);
+                // Events are separated by a blank line; chunks may end anywhere
+                while let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+                    let block: Vec<u8> = buffer.drain(..end + 2).collect();
+                    for line in String::from_utf8_lossy(&block).lines() {
+                        if let Some(data) = line.strip_prefix("data:")
+                            && let Ok(event) = serde_json::from_str::<Value>(data.trim())
+                            && sender.send(Update::Event(describe(&event))).is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+        if sender.send(Update::Stream(false)).is_err() {
+            return;
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+fn describe(event: &Value) -> String {
+    let text = |field: &str| event.get(field).and_then(Value::as_str).unwrap_or_default().to_string();
+    let mut description = format!("{} {}", text("process_id"), text("event"));
+    if let Some(code) = event.get("exit_code").and_then(Value::as_i64) {
+        description.push_str(&format!(" (code {})", code));
+    }
+    if let Some(reason) = event.get("reason").and_then(Value::as_str) {
+        description.push_str(&format!(": {}", reason));
+    }
+    description
+}
+
+fn status_style(status: &str) -> Style {
+    match status {
+        "running" => Style::new().fg(Color::Green),
+        "waiting" | "starting" => Style::new().fg(Color::Cyan),
+        "failed" | "pre_start_failed" => Style::new().fg(Color::Red),
+        _ => Style::new().fg(Color::DarkGray),
+    }
+}
+
+// Same colors as the dashboard's log viewer
+fn log_style(line: &str) -> Style {
+    if line.contains(" STDERR: ") {
+        Style::new().fg(Color::LightRed)
+    } else if line.contains("] HOOK ") {
+        Style::new().fg(Color::Magenta)
+    } else if line.contains("] SYSTEM ") {
+        Style::new().fg(Color::LightBlue)
+    } else {
+        Style::new()
+    }
+}
+
+fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
@@ -0,0 +1,41 @@
+// The web dashboard at /ui: a static page compiled into the binary that drives the v1 API from the browser,
+// with the API key the user enters kept in sessionStorage. Nothing is loaded from other origins
+use warp::Filter;
+use warp::http::Response;
+
+use crate::ratelimit::{self, RouteGroup};
+
+const INDEX_HTML: &str = include_str!("ui/index.html");
+const APP_JS: &str = include_str!("ui/app.js");
+const APP_CSS: &str = include_str!("ui/app.css");
+
+// Scripts and styles only from /ui itself, API calls only to this server
+const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; script-src 'self'; style-src 'self'; connect-src 'self'; \
+    img-src 'self' data:; base-uri 'none'; form-action 'none'; frame-ancestors 'none'";
+
+pub fn routes() -> warp::filters::BoxedFilter<(warp::reply::Response,)> {
+    let index = warp::path("ui")
+        .and(warp::path::end())
+        .map(|| asset(INDEX_HTML, "text/html; charset=utf-8"));
+    let app_js = warp::path!("ui" / "app.js")
+        .map(|| asset(APP_JS, "text/javascript; charset=utf-8"));
+    let app_css = warp::path!("ui" / "app.css")
+        .map(|| asset(APP_CSS, "text/css; charset=utf-8"));
+
+    warp::get()
+        .and(ratelimit::with_rate_limit(RouteGroup::Public))
+        .and(index.or(app_js).unify().or(app_css).unify())
+        .boxed()
+}
+
+fn asset(body: &'static str, content_type: &str) -> warp::reply::Response {
+    Response::builder()
+        .header("content-type", content_type)
+        // Served from the binary, so a new version is only picked up after revalidating
+        .header("cache-control", "no-cache")
+        .header("content-security-policy", CONTENT_SECURITY_POLICY)
+        .header("x-content-type-options", "nosniff")
+        .header("referrer-policy", "no-referrer")
+        .body(body.into())
+        .unwrap()
+}
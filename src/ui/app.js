@@ -0,0 +1,585 @@
+// exeio dashboard: talks to the v1 API of the server that served it, with the API key kept in sessionStorage
+"use strict";
+
+const KEY_STORAGE = "exeio-api-key";
+const LOG_PAGE_SIZE = 1000;
+const LOG_POLL_MS = 2000;
+const LIST_POLL_MS = 10000;
+
+const state = {
+  processes: [],
+  selected: null,
+  logLines: [],
+  logTimer: null,
+  listTimer: null,
+  refreshTimer: null,
+  events: null,
+  editing: null,
+};
+
+const $ = (id) => document.getElementById(id);
+
+class ApiError extends Error {
+  constructor(status, body) {
+    let message = (body && body.message) || `HTTP ${status}`;
+    if (body && Array.isArray(body.errors) && body.errors.length > 1) {
+      message += ": " + body.errors.map((error) => `${error.field}: ${error.message}`).join("; ");
+    }
+    super(message);
+    this.status = status;
+  }
+}
+
+function apiKey() {
+  return sessionStorage.getItem(KEY_STORAGE);
+}
+
+async function api(method, path, body) {
+  const headers = { "exeio-api-key": apiKey() || "" };
+  if (body !== undefined) {
+    headers["content-type"] = "application/json";
+  }
+  const response = await fetch(path, {
+    method,
+    headers,
+    body: body === undefined ? undefined : JSON.stringify(body),
+    cache: "no-store",
+  });
+  const text = await response.text();
+  let parsed = null;
+  try {
+    parsed = text ? JSON.parse(text) : null;
+  } catch (_) {
+    parsed = null;
+  }
+  if (!response.ok) {
+    if (response.status === 401) {
+      signOut("The API key was rejected.");
+    }
+    throw new ApiError(response.status, parsed);
+  }
+  return parsed;
+}
+
+function processPath(id, suffix) {
+  return `/v1/processes/${encodeURIComponent(id)}${suffix || ""}`;
+}
+
+// Banner
+
+function showBanner(message, info) {
+  $("banner-text").textContent = message;
+  $("banner").classList.toggle("info", !!info);
+  $("banner").hidden = false;
+  if (info) {
+    setTimeout(() => {
+      if ($("banner-text").textContent === message) {
+        $("banner").hidden = true;
+      }
+    }, 3000);
+  }
+}
+
+function hideBanner() {
+  $("banner").hidden = true;
+}
+
+// Sign in and out
+
+function signIn(key) {
+  sessionStorage.setItem(KEY_STORAGE, key);
+  start();
+}
+
+function signOut(reason) {
+  sessionStorage.removeItem(KEY_STORAGE);
+  stopEvents();
+  clearInterval(state.listTimer);
+  clearInterval(state.logTimer);
+  state.selected = null;
+  $("dashboard").hidden = true;
+  $("add-button").hidden = true;
+  $("logout-button").hidden = true;
+  $("connection").hidden = true;
+  $("login").hidden = false;
+  $("login-key").value = "";
+  $("login-key").focus();
+  if (reason) {
+    showBanner(reason);
+  }
+}
+
+async function start() {
+  try {
+    await refreshList();
+  } catch (error) {
+    if (error.status !== 401) {
+      showBanner(error.message);
+    }
+    return;
+  }
+  hideBanner();
+  $("login").hidden = true;
+  $("dashboard").hidden = false;
+  $("add-button").hidden = false;
+  $("logout-button").hidden = false;
+  $("connection").hidden = false;
+  clearInterval(state.listTimer);
+  state.listTimer = setInterval(() => refreshList().catch(() => {}), LIST_POLL_MS);
+  startEvents();
+}
+
+// Live updates: GET /v1/events read with fetch, since EventSource can't send the key header
+
+function setConnection(live) {
+  $("connection").className = "connection " + (live ? "live" : "polling");
+  $("connection").textContent = live ? "Live" : "Polling";
+}
+
+function stopEvents() {
+  if (state.events) {
+    state.events.abort();
+    state.events = null;
+  }
+}
+
+async function startEvents(retryDelay) {
+  stopEvents();
+  const controller = new AbortController();
+  state.events = controller;
+  const delay = retryDelay || 1000;
+  try {
+    const response = await fetch("/v1/events", {
+      headers: { "exeio-api-key": apiKey() || "", accept: "text/event-stream" },
+      signal: controller.signal,
+      cache: "no-store",
+    });
+    if (!response.ok || !response.body) {
+      throw new Error(`HTTP ${response.status}`);
+    }
+    setConnection(true);
+    const reader = response.body.getReader();
+    const decoder = new TextDecoder();
+    let buffer = "";
+    for (;;) {
+      const { value, done } = await reader.read();
+      if (done) {
+        break;
+      }
+      buffer += decoder.decode(value, { stream: true });
+      let end;
+      while ((end = buffer.indexOf("\n\n")) >= 0) {
+        const message = buffer.slice(0, end);
+        buffer = buffer.slice(end + 2);
+        if (message.split("\n").some((line) => line.startsWith("data:"))) {
+          scheduleRefresh();
+        }
+      }
+    }
+  } catch (_) {
+    if (controller.signal.aborted) {
+      return;
+    }
+  }
+  if (state.events !== controller || !apiKey()) {
+    return;
+  }
+  // Dropped or refused: keep polling and try again, backing off up to a minute
+  setConnection(false);
+  setTimeout(() => {
+    if (state.events === controller && apiKey()) {
+      startEvents(Math.min(delay * 2, 60000));
+    }
+  }, delay);
+}
+
+function scheduleRefresh() {
+  clearTimeout(state.refreshTimer);
+  state.refreshTimer = setTimeout(() => refreshList().catch(() => {}), 250);
+}
+
+// Process table
+
+async function refreshList() {
+  const processes = await api("GET", "/v1/processes");
+  state.processes = (processes || []).sort((a, b) => a.id.localeCompare(b.id));
+  renderList();
+}
+
+function findProcess(id) {
+  return state.processes.find((process) => process.id === id);
+}
+
+function button(label, className, onClick, disabled) {
+  const element = document.createElement("button");
+  element.type = "button";
+  element.textContent = label;
+  if (className) {
+    element.className = className;
+  }
+  element.disabled = !!disabled;
+  element.addEventListener("click", (event) => {
+    event.stopPropagation();
+    onClick();
+  });
+  return element;
+}
+
+function cell(row, text, className) {
+  const element = document.createElement("td");
+  if (text !== undefined) {
+    element.textContent = text;
+  }
+  if (className) {
+    element.className = className;
+  }
+  row.appendChild(element);
+  return element;
+}
+
+function formatTime(value) {
+  return value ? new Date(value).toLocaleString() : "";
+}
+
+function renderList() {
+  const rows = $("process-rows");
+  rows.replaceChildren();
+  $("empty").hidden = state.processes.length > 0;
+  for (const process of state.processes) {
+    const row = document.createElement("tr");
+    row.classList.toggle("selected", process.id === state.selected);
+    row.addEventListener("click", () => openLogs(process.id));
+
+    const name = cell(row, process.id);
+    if (process.periodic) {
+      const tag = document.createElement("span");
+      tag.className = "tag";
+      tag.textContent = `every ${process.period_seconds}s`;
+      name.appendChild(tag);
+    }
+    if (process.pending_restart) {
+      const tag = document.createElement("span");
+      tag.className = "tag";
+      tag.textContent = "pending restart";
+      name.appendChild(tag);
+    }
+
+    const status = document.createElement("span");
+    status.className = "status " + process.status;
+    status.textContent = process.status.replace(/_/g, " ");
+    cell(row).appendChild(status);
+
+    const command = [process.command, ...process.args].join(" ");
+    cell(row, command, "command").title = command;
+    cell(row, String(process.run_count));
+    cell(row, formatTime(process.last_run));
+
+    const buttons = cell(row, undefined, "buttons");
+    if (process.is_running) {
+      buttons.appendChild(button("Stop", "secondary", () => act(process.id, "stop")));
+    } else {
+      buttons.appendChild(button("Start", "", () => act(process.id, "start")));
+    }
+    buttons.appendChild(button("Restart", "secondary", () => act(process.id, "restart")));
+    buttons.appendChild(button("Logs", "secondary", () => openLogs(process.id)));
+    buttons.appendChild(button("Edit", "secondary", () => openForm(process)));
+    buttons.appendChild(button("Remove", "danger", () => act(process.id, "remove")));
+    rows.appendChild(row);
+  }
+  updateInputState();
+}
+
+async function act(id, action) {
+  try {
+    switch (action) {
+      case "stop":
+        await api("POST", processPath(id, "/actions/stop"));
+        break;
+      // A stopped process is started by restarting it
+      case "start":
+      case "restart":
+        await api("POST", processPath(id, "/actions/restart"));
+        break;
+      case "remove":
+        if (!confirm(`Stop and remove process '${id}'?`)) {
+          return;
+        }
+        await api("DELETE", processPath(id));
+        if (state.selected === id) {
+          closeLogs();
+        }
+        break;
+    }
+    showBanner(`Process '${id}': ${action} done`, true);
+  } catch (error) {
+    showBanner(`Could not ${action} '${id}': ${error.message}`);
+  }
+  await refreshList().catch(() => {});
+}
+
+// Log viewer
+
+function openLogs(id) {
+  if (state.selected !== id) {
+    state.selected = id;
+    state.logLines = [];
+    $("log-lines").replaceChildren();
+    $("log-search").value = "";
+    $("input-line").value = "";
+  }
+  $("log-process").textContent = id;
+  $("log-panel").hidden = false;
+  renderList();
+  refreshLogs();
+  clearInterval(state.logTimer);
+  state.logTimer = setInterval(() => {
+    if (!$("log-pause").checked) {
+      refreshLogs();
+    }
+  }, LOG_POLL_MS);
+}
+
+function closeLogs() {
+  clearInterval(state.logTimer);
+  state.selected = null;
+  $("log-panel").hidden = true;
+  renderList();
+}
+
+async function refreshLogs() {
+  const id = state.selected;
+  if (!id) {
+    return;
+  }
+  try {
+    const page = await api("GET", processPath(id, `/logs?page=1&page_size=${LOG_PAGE_SIZE}`));
+    if (state.selected !== id) {
+      return;
+    }
+    // Newest first from the API
+    state.logLines = page.logs.slice().reverse();
+    renderLogs();
+  } catch (error) {
+    if (error.status === 404) {
+      closeLogs();
+    }
+    showBanner(`Could not read logs of '${id}': ${error.message}`);
+  }
+}
+
+function lineClass(line) {
+  if (/\] (RUN#\d+ )?STDERR: /.test(line)) {
+    return "stderr";
+  }
+  if (/\] HOOK /.test(line)) {
+    return "hook";
+  }
+  if (/\] SYSTEM /.test(line)) {
+    return "system";
+  }
+  return "";
+}
+
+// Appends `text` to `parent`, wrapping each case-insensitive occurrence of `query` in <mark>
+function appendHighlighted(parent, text, query) {
+  if (!query) {
+    parent.appendChild(document.createTextNode(text));
+    return;
+  }
+  const lower = text.toLowerCase();
+  let position = 0;
+  let found;
+  while ((found = lower.indexOf(query, position)) >= 0) {
+    parent.appendChild(document.createTextNode(text.slice(position, found)));
+    const mark = document.createElement("mark");
+    mark.textContent = text.slice(found, found + query.length);
+    parent.appendChild(mark);
+    position = found + query.length;
+  }
+  parent.appendChild(document.createTextNode(text.slice(position)));
+}
+
+function renderLogs() {
+  const container = $("log-lines");
+  const query = $("log-search").value.trim().toLowerCase();
+  const lines = query ? state.logLines.filter((line) => line.toLowerCase().includes(query)) : state.logLines;
+  const atBottom = container.scrollHeight - container.scrollTop - container.clientHeight < 24;
+
+  const fragment = document.createDocumentFragment();
+  for (const line of lines) {
+    const element = document.createElement("div");
+    const className = lineClass(line);
+    if (className) {
+      element.className = className;
+    }
+    appendHighlighted(element, line, query);
+    fragment.appendChild(element);
+  }
+  container.replaceChildren(fragment);
+  $("log-count").textContent = query
+    ? `${lines.length} of ${state.logLines.length} lines`
+    : `${state.logLines.length} lines`;
+  if ($("log-follow").checked && (atBottom || !query)) {
+    container.scrollTop = container.scrollHeight;
+  }
+}
+
+function updateInputState() {
+  const process = state.selected && findProcess(state.selected);
+  const running = !!(process && process.is_running && !process.periodic);
+  $("input-line").disabled = !running;
+  $("input-form").querySelector("button").disabled = !running;
+  $("input-line").placeholder = running ? "Line for the process's stdin" : "The process isn't running";
+}
+
+async function sendInput(event) {
+  event.preventDefault();
+  const id = state.selected;
+  const input = $("input-line").value;
+  if (!id) {
+    return;
+  }
+  try {
+    await api("POST", processPath(id, "/input"), { input });
+    $("input-line").value = "";
+    setTimeout(refreshLogs, 300);
+  } catch (error) {
+    showBanner(`Could not send input to '${id}': ${error.message}`);
+  }
+}
+
+async function clearLog() {
+  const id = state.selected;
+  if (!id || !confirm(`Clear the log of '${id}'?`)) {
+    return;
+  }
+  try {
+    await api("DELETE", processPath(id, "/logs"));
+    refreshLogs();
+  } catch (error) {
+    showBanner(`Could not clear the log of '${id}': ${error.message}`);
+  }
+}
+
+// Add and edit form
+
+function openForm(process) {
+  const form = $("process-form");
+  form.reset();
+  state.editing = process ? process.id : null;
+  $("process-form-title").textContent = process ? `Edit ${process.id}` : "Add process";
+  $("process-form-error").hidden = true;
+  form.elements.id.readOnly = !!process;
+  $("advanced-hint").textContent = process
+    ? "Leave empty to keep the current hooks and log rules; anything entered replaces them. Save for next run only changes the saved config when ticked."
+    : "Optional, see the README for the fields.";
+  if (process) {
+    form.elements.id.value = process.id;
+    form.elements.command.value = process.command;
+    form.elements.args.value = process.args.join("\n");
+    form.elements.working_dir.value = process.working_dir || "";
+    form.elements.auto_restart.checked = process.auto_restart;
+    form.elements.periodic.checked = process.periodic;
+    form.elements.period_seconds.value = process.period_seconds || "";
+  }
+  $("process-dialog").showModal();
+  (process ? form.elements.command : form.elements.id).focus();
+}
+
+function parseJsonField(form, name) {
+  const text = form.elements[name].value.trim();
+  if (!text) {
+    return undefined;
+  }
+  try {
+    return JSON.parse(text);
+  } catch (error) {
+    throw new Error(`${name} is not valid JSON: ${error.message}`);
+  }
+}
+
+async function submitForm(event) {
+  event.preventDefault();
+  const form = $("process-form");
+  const error = $("process-form-error");
+  error.hidden = true;
+
+  const args = form.elements.args.value.split("\n").filter((arg) => arg !== "");
+  const workingDir = form.elements.working_dir.value.trim();
+  const period = form.elements.period_seconds.value ? Number(form.elements.period_seconds.value) : null;
+  let hooks;
+  let logRules;
+  try {
+    hooks = parseJsonField(form, "hooks");
+    logRules = parseJsonField(form, "log_rules");
+  } catch (parseError) {
+    error.textContent = parseError.message;
+    error.hidden = false;
+    return;
+  }
+
+  const body = {
+    command: form.elements.command.value.trim(),
+    args,
+    working_dir: workingDir || null,
+    auto_restart: form.elements.auto_restart.checked,
+    periodic: form.elements.periodic.checked,
+    period_seconds: period,
+  };
+  if (hooks !== undefined) {
+    body.hooks = hooks;
+  }
+  if (logRules !== undefined) {
+    body.log_rules = logRules;
+  }
+
+  const id = state.editing || form.elements.id.value.trim();
+  try {
+    if (state.editing) {
+      // PATCH keeps the fields that aren't sent
+      if (form.elements.save_for_next_run.checked) {
+        body.save_for_next_run = true;
+      }
+      await api("PATCH", processPath(id), body);
+    } else {
+      body.id = id;
+      body.save_for_next_run = form.elements.save_for_next_run.checked;
+      await api("POST", "/v1/processes", body);
+    }
+  } catch (apiError) {
+    error.textContent = apiError.message;
+    error.hidden = false;
+    return;
+  }
+  $("process-dialog").close();
+  showBanner(state.editing ? `Process '${id}' updated` : `Process '${id}' added`, true);
+  await refreshList().catch(() => {});
+  openLogs(id);
+}
+
+// Wiring
+
+document.addEventListener("DOMContentLoaded", () => {
+  $("login-form").addEventListener("submit", (event) => {
+    event.preventDefault();
+    const key = $("login-key").value.trim();
+    if (key) {
+      signIn(key);
+    }
+  });
+  $("logout-button").addEventListener("click", () => signOut());
+  $("add-button").addEventListener("click", () => openForm(null));
+  $("banner-close").addEventListener("click", hideBanner);
+  $("log-close").addEventListener("click", closeLogs);
+  $("log-clear").addEventListener("click", clearLog);
+  $("log-search").addEventListener("input", renderLogs);
+  $("input-form").addEventListener("submit", sendInput);
+  $("process-form").addEventListener("submit", submitForm);
+  $("process-form-cancel").addEventListener("click", () => $("process-dialog").close());
+
+  if (apiKey()) {
+    start();
+  } else {
+    signOut();
+  }
+});
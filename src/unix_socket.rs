@@ -0,0 +1,221 @@
+// Serving the API over a Unix domain socket for on-box tooling, with optional
+// SO_PEERCRED based authorization for local root/admin users.
+use std::convert::Infallible;
+use std::ffi::CString;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use hyper::service::Service;
+use hyper::{Body, Request, Response};
+use tokio::net::UnixListener;
+
+use crate::LocalPeer;
+
+lazy_static::lazy_static! {
+    static ref BOUND_SOCKET_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+pub struct UnixSocketOptions {
+    pub path: PathBuf,
+    pub mode: u32,
+    pub owner: Option<String>,
+}
+
+// Which local users may use the socket without an API key
+#[derive(Debug, Clone, Default)]
+pub struct PeerCredPolicy {
+    uids: Vec<u32>,
+    gids: Vec<u32>,
+}
+
+impl PeerCredPolicy {
+    // Root and the user exeio runs as are always trusted, plus the given users and groups
+    pub fn new(users: &[String], groups: &[String]) -> Result<Self, String> {
+        let mut uids = vec![0, unsafe { libc::getuid() }];
+        for user in users {
+            uids.push(resolve_uid(user)?);
+        }
+        let mut gids = Vec::new();
+        for group in groups {
+            gids.push(resolve_gid(group)?);
+        }
+        Ok(Self { uids, gids })
+    }
+
+    fn is_trusted(&self, uid: u32, gid: u32, pid: Option<i32>) -> bool {
+        if self.uids.contains(&uid) || self.gids.contains(&gid) {
+            return true;
+        }
+        // SO_PEERCRED only carries the primary group, so look up supplementary groups as well
+        match pid {
+            Some(pid) if !self.gids.is_empty() => supplementary_groups(pid)
+                .iter()
+                .any(|g| self.gids.contains(g)),
+            _ => false,
+        }
+    }
+}
+
+fn supplementary_groups(pid: i32) -> Vec<u32> {
+    let status = match std::fs::read_to_string(format!("/proc/{}/status", pid)) {
+        Ok(status) => status,
+        Err(_) => return Vec::new(),
+    };
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Groups:"))
+        .map(|groups| groups.split_whitespace().filter_map(|g| g.parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+fn resolve_uid(user: &str) -> Result<u32, String> {
+    if let Ok(uid) = user.parse::<u32>() {
+        return Ok(uid);
+    }
+    let name = CString::new(user).map_err(|_| format!("Invalid user name: {}", user))?;
+    let passwd = unsafe { libc::getpwnam(name.as_ptr()) };
+    if passwd.is_null() {
+        return Err(format!("Unknown user: {}", user));
+    }
+    Ok(unsafe { (*passwd).pw_uid })
+}
+
+fn resolve_gid(group: &str) -> Result<u32, String> {
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(gid);
+    }
+    let name = CString::new(group).map_err(|_| format!("Invalid group name: {}", group))?;
+    let grp = unsafe { libc::getgrnam(name.as_ptr()) };
+    if grp.is_null() {
+        return Err(format!("Unknown group: {}", group));
+    }
+    Ok(unsafe { (*grp).gr_gid })
+}
+
+pub fn parse_octal_mode(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|m| *m <= 0o777)
+        .ok_or_else(|| format!("Invalid file mode '{}', expected octal such as 660", mode))
+}
+
+// Bind the socket, replacing a stale socket file, and apply the requested mode and owner
+pub fn bind(options: &UnixSocketOptions) -> Result<UnixListener, String> {
+    let path = &options.path;
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(format!("Unix socket {} is already in use", path.display()));
+        }
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove stale socket {}: {}", path.display(), e))?;
+    }
+
+    // Created owner-only, so nobody else can connect before the mode and owner are set. The umask is
+    // process-wide, so it is only changed for the bind itself
+    let previous_umask = unsafe { libc::umask(0o177) };
+    let bound = UnixListener::bind(path);
+    unsafe { libc::umask(previous_umask) };
+    let listener = bound.map_err(|e| format!("Failed to bind Unix socket {}: {}", path.display(), e))?;
+    *BOUND_SOCKET_PATH.lock().unwrap() = Some(path.clone());
+
+    if let Some(ref owner) = options.owner {
+        set_owner(path, owner)?;
+    }
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(options.mode))
+        .map_err(|e| format!("Failed to set mode on {}: {}", path.display(), e))?;
+
+    Ok(listener)
+}
+
+fn set_owner(path: &Path, owner: &str) -> Result<(), String> {
+    let (user, group) = match owner.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (owner, None),
+    };
+    let uid = if user.is_empty() { None } else { Some(resolve_uid(user)?) };
+    let gid = match group {
+        Some(group) if !group.is_empty() => Some(resolve_gid(group)?),
+        _ => None,
+    };
+    std::os::unix::fs::chown(path, uid, gid)
+        .map_err(|e| format!("Failed to change owner of {}: {}", path.display(), e))
+}
+
+// Serve an already-built warp service on the socket, tagging each request with the peer credentials
+pub async fn serve<S>(listener: UnixListener, service: S, policy: Option<Arc<PeerCredPolicy>>)
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    let incoming = hyper::server::accept::from_stream(futures_util::stream::unfold(listener, |listener| async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => return Some((Ok::<_, Infallible>(stream), listener)),
+                Err(e) => back_off_after_accept_error(&e, "Unix socket").await,
+            }
+        }
+    }));
+
+    let make_service = hyper::service::make_service_fn(move |stream: &tokio::net::UnixStream| {
+        let peer = stream.peer_cred().ok().map(|cred| {
+            let trusted = policy
+                .as_ref()
+                .is_some_and(|p| p.is_trusted(cred.uid(), cred.gid(), cred.pid()));
+            LocalPeer { uid: cred.uid(), trusted }
+        });
+        let service = service.clone();
+
+        async move {
+            Ok::<_, Infallible>(hyper::service::service_fn(move |mut req: Request<Body>| {
+                if let Some(ref peer) = peer {
+                    req.extensions_mut().insert(peer.clone());
+                }
+                let mut service = service.clone();
+                async move { service.call(req).await }
+            }))
+        }
+    });
+
+    if let Err(e) = hyper::Server::builder(incoming).serve(make_service).await {
+        eprintln!("Unix socket server error: {}", e);
+    }
+}
+
+// An accept error such as EMFILE mustn't end the server: it is logged, and accepting resumes after a pause
+// that gives connections time to close. Errors about a single connection are retried at once
+pub async fn back_off_after_accept_error(error: &std::io::Error, listener: &str) {
+    use std::io::ErrorKind;
+
+    if matches!(error.kind(), ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset | ErrorKind::ConnectionRefused) {
+        return;
+    }
+    eprintln!("{} accept error: {}, retrying in 1s", listener, error);
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+}
+
+pub fn cleanup_unix_socket() {
+    if let Some(path) = BOUND_SOCKET_PATH.lock().unwrap().take() {
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn binds_with_the_requested_mode_and_restores_the_umask() {
+        let path = std::env::temp_dir().join(format!("exeio-socket-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let umask = unsafe { libc::umask(0o022) };
+
+        let listener = bind(&UnixSocketOptions { path: path.clone(), mode: 0o660, owner: None }).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o660);
+        assert_eq!(unsafe { libc::umask(umask) }, 0o022);
+
+        drop(listener);
+        let _ = std::fs::remove_file(&path);
+    }
+}
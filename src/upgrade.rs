@@ -0,0 +1,480 @@
+// In-place upgrade: the running supervisor execs a new binary, which keeps the listening socket and reattaches
+// to the supervised processes (see runtime.rs), so neither the API nor the processes go down
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use hyper::service::Service;
+use hyper::{Body, Request, Response};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::actor;
+use crate::audit::AuditContext;
+use crate::error::{ApiError, FieldError};
+use crate::network::RemoteAddr;
+use crate::{ApiResponse, ManagedProcess, ProcessConfig, ProcessMap, ProcessStatus, Supervisor};
+
+// Set by the old binary for the new one
+const LISTEN_FD_ENV: &str = "EXEIO_LISTEN_FD";
+const HANDOFF_ENV: &str = "EXEIO_HANDOFF";
+
+// The binary this supervisor was started from, read before it can be replaced on disk
+static CURRENT_EXE: OnceLock<Option<PathBuf>> = OnceLock::new();
+// What the previous binary passed in the environment: the listener fd and the handoff file
+static INHERITED: OnceLock<(Option<RawFd>, Option<PathBuf>)> = OnceLock::new();
+static LISTENER_FD: Mutex<Option<RawFd>> = Mutex::new(None);
+// Only one upgrade at a time
+static UPGRADING: Mutex<bool> = Mutex::new(false);
+// How long a periodic run in progress may take to finish before an upgrade kills it
+const PERIODIC_RUN_GRACE: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UpgradeParams {
+    /// Path of the new exeio binary, defaults to the one exeio was started from
+    binary: Option<String>,
+}
+
+// Processes the new binary can't reattach to, e.g. stopped or periodic ones, so their state isn't lost
+#[derive(Serialize, Deserialize)]
+struct HandoffEntry {
+    config: ProcessConfig,
+    status: ProcessStatus,
+    run_count: u64,
+    last_run: Option<chrono::DateTime<chrono::Utc>>,
+    last_exit_time: Option<chrono::DateTime<chrono::Utc>>,
+    pending_restart: bool,
+}
+
+// Called first thing in main, before anything else reads the environment
+pub fn init() {
+    CURRENT_EXE.get_or_init(|| std::env::current_exe().ok());
+    INHERITED.get_or_init(|| {
+        let fd = std::env::var(LISTEN_FD_ENV).ok().and_then(|fd| fd.parse::<RawFd>().ok());
+        let handoff = std::env::var_os(HANDOFF_ENV).map(PathBuf::from);
+        // So supervised processes don't inherit them
+        unsafe {
+            std::env::remove_var(LISTEN_FD_ENV);
+            std::env::remove_var(HANDOFF_ENV);
+        }
+        (fd, handoff)
+    });
+}
+
+// The listening socket handed over by the previous binary, or a new one
+pub fn listen(addr: SocketAddr) -> std::io::Result<tokio::net::TcpListener> {
+    let listener = match INHERITED.get().and_then(|(fd, _)| *fd) {
+        Some(fd) => {
+            let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+            set_cloexec(fd, true);
+            println!("Listening socket inherited from the previous exeio ({})", listener.local_addr()?);
+            listener
+        }
+        None => std::net::TcpListener::bind(addr)?,
+    };
+    listener.set_nonblocking(true)?;
+    *LISTENER_FD.lock().unwrap() = Some(listener.as_raw_fd());
+    tokio::net::TcpListener::from_std(listener)
+}
+
+fn set_cloexec(fd: RawFd, cloexec: bool) {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        let flags = if cloexec { flags | libc::FD_CLOEXEC } else { flags & !libc::FD_CLOEXEC };
+        libc::fcntl(fd, libc::F_SETFD, flags);
+    }
+}
+
+// Serve an already-built warp service on the listener. Like the Unix socket server, it passes the client
+// address along as a request extension, since warp's own server can't serve a listener it didn't bind
+pub async fn serve<S>(listener: tokio::net::TcpListener, service: S)
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    let incoming = hyper::server::accept::from_stream(futures_util::stream::unfold(listener, |listener| async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => return Some((Ok::<_, Infallible>(stream), listener)),
+                Err(e) => crate::unix_socket::back_off_after_accept_error(&e, "Listener").await,
+            }
+        }
+    }));
+
+    let make_service = hyper::service::make_service_fn(move |stream: &tokio::net::TcpStream| {
+        let remote = stream.peer_addr().ok();
+        let service = service.clone();
+
+        async move {
+            Ok::<_, Infallible>(hyper::service::service_fn(move |mut req: Request<Body>| {
+                if let Some(remote) = remote {
+                    req.extensions_mut().insert(RemoteAddr(remote));
+                }
+                let mut service = service.clone();
+                async move { service.call(req).await }
+            }))
+        }
+    });
+
+    if let Err(e) = hyper::Server::builder(incoming).serve(make_service).await {
+        eprintln!("Server error: {}", e);
+    }
+}
+
+fn handoff_path(supervisor: &Supervisor) -> PathBuf {
+    crate::runtime::runtime_dir(supervisor).unwrap_or_else(crate::runtime::default_dir).join("handoff.json")
+}
+
+// Called at startup after runtime::adopt: restores the processes the previous binary couldn't hand over running
+pub async fn restore_handoff(supervisor: &Supervisor) {
+    if let Some(path) = INHERITED.get().and_then(|(_, handoff)| handoff.clone()) {
+        restore_from(supervisor, &path).await;
+    }
+}
+
+async fn restore_from(supervisor: &Supervisor, path: &Path) {
+    let entries: Vec<HandoffEntry> = match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Failed to read upgrade handoff {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let _ = std::fs::remove_file(path);
+
+    let mut restarted = Vec::new();
+    for entry in entries {
+        let is_active = matches!(entry.status, ProcessStatus::Running | ProcessStatus::WaitingForPeriod | ProcessStatus::Failed | ProcessStatus::Starting);
+        {
+            let mut processes_lock = supervisor.process_map().lock().unwrap();
+            if let Some(managed_process) = processes_lock.get_mut(&entry.config.id) {
+                // Reattached, only the bookkeeping the state file doesn't have is missing
+                managed_process.pending_restart = entry.pending_restart;
+                managed_process.last_exit_time = entry.last_exit_time;
+                continue;
+            }
+            // Periodic processes, and ones waiting to be auto-restarted, are started again below
+            if !(is_active && (entry.config.periodic || entry.config.auto_restart)) {
+                if let Err(e) = supervisor.logger().open_process_log(&entry.config.log_file) {
+                    eprintln!("Failed to open log file {}: {}", entry.config.log_file, e);
+                    continue;
+                }
+                processes_lock.insert(entry.config.id.clone(), ManagedProcess {
+                    config: entry.config,
+                    pid: None,
+                    actor: None,
+                    run_count: entry.run_count,
+                    last_run: entry.last_run,
+                    periodic_handle: None,
+                    // A regular process that was running but couldn't be reattached has exited in the meantime
+                    status: if is_active { ProcessStatus::Stopped } else { entry.status },
+                    last_exit_time: entry.last_exit_time,
+                    pending_restart: entry.pending_restart,
+                });
+                continue;
+            }
+        }
+        restarted.push(entry.config);
+    }
+    for config in restarted {
+        crate::start_process(supervisor, config).await;
+    }
+}
+
+async fn check_binary(binary: &PathBuf) -> Result<(), String> {
+    let metadata = std::fs::metadata(binary).map_err(|e| format!("Cannot use {}: {}", binary.display(), e))?;
+    if !metadata.is_file() || std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o111 == 0 {
+        return Err(format!("{} is not an executable file", binary.display()));
+    }
+    // Make sure it runs and is an exeio before replacing ourselves with it
+    let output = tokio::time::timeout(Duration::from_secs(10), tokio::process::Command::new(binary).arg("--help").output())
+        .await
+        .map_err(|_| format!("{} --help did not finish within 10s", binary.display()))?
+        .map_err(|e| format!("Failed to run {}: {}", binary.display(), e))?;
+    if !output.status.success() || !String::from_utf8_lossy(&output.stdout).contains("exeio") {
+        return Err(format!("{} does not look like an exeio binary ('--help' failed or printed something else)", binary.display()));
+    }
+    Ok(())
+}
+
+// Validates the binary. Returns the one to exec
+async fn prepare(binary: Option<String>) -> Result<PathBuf, ApiError> {
+    let binary = match binary {
+        Some(binary) => PathBuf::from(binary),
+        None => CURRENT_EXE
+            .get()
+            .cloned()
+            .flatten()
+            .ok_or_else(|| ApiError::Internal("Could not determine the path of the exeio binary, pass ?binary=".to_string()))?,
+    };
+    check_binary(&binary)
+        .await
+        .map_err(|message| ApiError::Validation(vec![FieldError::new("binary", "invalid", message)]))?;
+    Ok(binary)
+}
+
+// Running regular processes: those the new binary reattaches to, and those started without named pipes, whose
+// output goes to pipes that close with the exec. The new binary still watches those but can't read them
+fn handed_over(supervisor: &Supervisor) -> (Vec<String>, Vec<String>) {
+    let running: Vec<String> = supervisor
+        .process_map()
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, managed_process)| managed_process.pid.is_some() && !managed_process.config.periodic)
+        .map(|(id, _)| id.clone())
+        .collect();
+    running.into_iter().partition(|id| crate::runtime::Fifos::exist(supervisor, id))
+}
+
+// Writes the state the new binary needs. Called with the relays paused right before the exec, so it has every
+// start and exit up to then
+fn write_handoff(supervisor: &Supervisor) -> Result<(), String> {
+    crate::runtime::save(supervisor);
+    let entries: Vec<HandoffEntry> = supervisor
+        .process_map()
+        .lock()
+        .unwrap()
+        .values()
+        .map(|managed_process| HandoffEntry {
+            config: managed_process.config.clone(),
+            status: managed_process.status.clone(),
+            run_count: managed_process.run_count,
+            last_run: managed_process.last_run,
+            last_exit_time: managed_process.last_exit_time,
+            pending_restart: managed_process.pending_restart,
+        })
+        .collect();
+    let path = handoff_path(supervisor);
+    serde_json::to_string_pretty(&entries)
+        .map_err(std::io::Error::other)
+        .and_then(|json| crate::runtime::write_private(&path, &json))
+        .map_err(|e| format!("failed to write upgrade handoff {}: {}", path.display(), e))
+}
+
+// Periodic processes aren't handed over, the new binary starts them again from the handoff. A run in progress
+// gets PERIODIC_RUN_GRACE to finish, then it's killed and reaped so it isn't left behind with nobody waiting on it.
+// Returns the processes that were stopped, to start again if the exec fails
+async fn stop_periodic_processes(processes: &ProcessMap) -> Vec<ProcessConfig> {
+    let deadline = tokio::time::Instant::now() + PERIODIC_RUN_GRACE;
+    while tokio::time::Instant::now() < deadline && processes.lock().unwrap().values().any(|p| p.config.periodic && p.pid.is_some()) {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let mut stopped = Vec::new();
+    let mut pids = Vec::new();
+    let mut handles = Vec::new();
+    for managed_process in processes.lock().unwrap().values_mut() {
+        let Some(handle) = managed_process.periodic_handle.take() else {
+            continue;
+        };
+        handle.abort();
+        handles.push(handle);
+        pids.extend(managed_process.pid.take());
+        stopped.push(managed_process.config.clone());
+    }
+    // Dropping an aborted task kills its run (kill_on_drop)
+    for handle in handles {
+        let _ = handle.await;
+    }
+    for pid in pids {
+        for _ in 0..20 {
+            // Reaped, or already reaped by tokio
+            if unsafe { libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), libc::WNOHANG) } != 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+    stopped
+}
+
+// Replaces this process with `binary`, with the same pid and arguments. Only returns if the exec failed
+fn exec(binary: &PathBuf, supervisor: &Supervisor) -> String {
+    let listener_fd = *LISTENER_FD.lock().unwrap();
+    let mut command = std::process::Command::new(binary);
+    command.args(std::env::args_os().skip(1)).env(HANDOFF_ENV, handoff_path(supervisor));
+    if let Some(fd) = listener_fd {
+        set_cloexec(fd, false);
+        command.env(LISTEN_FD_ENV, fd.to_string());
+    }
+    // The Unix socket closes with the exec and the new binary binds it again
+
+    let (_, unpiped) = handed_over(supervisor);
+    if !unpiped.is_empty() {
+        crate::log_exeio_event(supervisor,
+            &format!("Upgrading: process(es) {} have no named pipes, their output is lost after the exec\n", unpiped.join(", ")));
+    }
+    crate::log_exeio_event(supervisor, &format!("Upgrading: exec {}\n", binary.display()));
+    supervisor.logger().flush_process_logs();
+    let error = command.exec();
+
+    if let Some(fd) = listener_fd {
+        set_cloexec(fd, true);
+    }
+    let _ = std::fs::remove_file(handoff_path(supervisor));
+    let message = format!("Upgrade failed, exec {}: {}\n", binary.display(), error);
+    crate::log_exeio_event(supervisor, &message);
+    message
+}
+
+// Shared by the API and SIGUSR2; the exec happens shortly after returning so the response can be sent
+pub async fn upgrade(binary: Option<String>, supervisor: Supervisor) -> Result<String, ApiError> {
+    {
+        let mut upgrading = UPGRADING.lock().unwrap();
+        if *upgrading {
+            return Err(ApiError::UpgradeInProgress);
+        }
+        *upgrading = true;
+    }
+    let binary = match prepare(binary).await {
+        Ok(binary) => binary,
+        Err(error) => {
+            *UPGRADING.lock().unwrap() = false;
+            return Err(error);
+        }
+    };
+
+    let (piped, unpiped) = handed_over(&supervisor);
+    let mut message = format!("Upgrading to {}, {} running process(es) are handed over", binary.display(), piped.len());
+    if !unpiped.is_empty() {
+        message.push_str(&format!("; {} have no named pipes and lose their output: {}", unpiped.len(), unpiped.join(", ")));
+    }
+    println!("{}", message);
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let periodic = stop_periodic_processes(supervisor.process_map()).await;
+        actor::pause_relays(&supervisor).await;
+        let error = match write_handoff(&supervisor) {
+            Ok(()) => exec(&binary, &supervisor),
+            Err(e) => {
+                let message = format!("Upgrade failed, {}\n", e);
+                crate::log_exeio_event(&supervisor, &message);
+                message
+            }
+        };
+        eprint!("{}", error);
+        actor::resume_relays(&supervisor);
+        for config in periodic {
+            crate::start_process(&supervisor, config).await;
+        }
+        *UPGRADING.lock().unwrap() = false;
+    });
+    Ok(message)
+}
+
+pub async fn handle_upgrade(
+    audit: AuditContext,
+    params: UpgradeParams,
+    supervisor: Supervisor,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let audit_details = serde_json::json!({ "binary": params.binary });
+    match upgrade(params.binary, supervisor).await {
+        Ok(message) => {
+            let response = ApiResponse { success: true, message };
+            audit.record(None, Some(audit_details), &response);
+            Ok(warp::reply::json(&response))
+        }
+        Err(error) => {
+            audit.record_error(None, Some(audit_details), &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supervisor(runtime_dir: &Path) -> Supervisor {
+        Supervisor::builder().config_store(crate::MemoryConfigStore::default()).runtime_dir(runtime_dir).build()
+    }
+
+    fn managed(config: ProcessConfig, status: ProcessStatus, run_count: u64) -> ManagedProcess {
+        ManagedProcess {
+            config,
+            pid: None,
+            actor: None,
+            run_count,
+            last_run: None,
+            periodic_handle: None,
+            status,
+            last_exit_time: Some(chrono::Utc::now()),
+            pending_restart: true,
+        }
+    }
+
+    fn config(id: &str, dir: &Path) -> ProcessConfig {
+        ProcessConfig {
+            id: id.to_string(),
+            command: "true".to_string(),
+            args: Vec::new(),
+            working_dir: None,
+            auto_restart: false,
+            log_file: dir.join(format!("{}.log", id)).to_string_lossy().into_owned(),
+            periodic: false,
+            period_seconds: None,
+            hooks: Default::default(),
+            log_rules: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn the_handoff_restores_stopped_processes_and_reattached_bookkeeping() {
+        let dir = std::env::temp_dir().join(format!("exeio-handoff-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let old = supervisor(&dir);
+        let stopped = config("stopped", &dir);
+        let reattached = config("reattached", &dir);
+        old.process_map().lock().unwrap().insert(stopped.id.clone(), managed(stopped.clone(), ProcessStatus::Stopped, 3));
+        old.process_map().lock().unwrap().insert(reattached.id.clone(), managed(reattached.clone(), ProcessStatus::Running, 1));
+        write_handoff(&old).unwrap();
+
+        // The new binary has reattached to one process already, from the state file
+        let new = supervisor(&dir);
+        let mut adopted = managed(reattached.clone(), ProcessStatus::Running, 1);
+        adopted.pending_restart = false;
+        adopted.last_exit_time = None;
+        new.process_map().lock().unwrap().insert(reattached.id.clone(), adopted);
+        restore_from(&new, &handoff_path(&new)).await;
+
+        let processes = new.process_map().lock().unwrap();
+        let restored = &processes[&stopped.id];
+        assert!(matches!(restored.status, ProcessStatus::Stopped));
+        assert_eq!(restored.run_count, 3);
+        assert!(restored.pending_restart && restored.pid.is_none());
+        let reattached = &processes[&reattached.id];
+        assert!(reattached.pending_restart && reattached.last_exit_time.is_some());
+        // Read once
+        assert!(!handoff_path(&new).exists());
+        drop(processes);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn a_running_process_that_was_not_reattached_is_stopped() {
+        let dir = std::env::temp_dir().join(format!("exeio-handoff-gone-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let old = supervisor(&dir);
+        let gone = config("gone", &dir);
+        old.process_map().lock().unwrap().insert(gone.id.clone(), managed(gone.clone(), ProcessStatus::Running, 2));
+        write_handoff(&old).unwrap();
+
+        let new = supervisor(&dir);
+        restore_from(&new, &handoff_path(&new)).await;
+        let processes = new.process_map().lock().unwrap();
+        assert!(matches!(processes[&gone.id].status, ProcessStatus::Stopped));
+        assert_eq!(processes[&gone.id].run_count, 2);
+        drop(processes);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,610 @@
+// Outgoing webhooks: lifecycle events matching a webhook declared in the config file are POSTed to it, signed,
+// retried with backoff and kept in a dead-letter file once the retries run out
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::broadcast;
+
+use crate::{ApiResponse, Supervisor};
+use crate::audit::AuditContext;
+use crate::error::{ApiError, FieldError};
+use crate::events::{Event, TRIGGER_NAMES};
+
+// Longest wait between two attempts
+const MAX_BACKOFF_SECONDS: u64 = 60;
+
+// A `[webhooks.<name>]` section of the config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    // Event types and derived triggers such as crash_loop and periodic_failed
+    pub events: Vec<String>,
+    // Process id globs, empty means all processes
+    #[serde(default)]
+    pub processes: Vec<String>,
+    // Key for the X-Exeio-Signature header, unsigned without one
+    pub secret: Option<String>,
+    // JSON body with {{field}} placeholders, the event itself without one
+    pub template: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_timeout_seconds() -> u64 {
+    10
+}
+
+fn default_max_retries() -> u32 {
+    5
+}
+
+impl WebhookConfig {
+    // Every invalid field, named `webhooks.<name>.<field>`
+    pub fn validate(&self, name: &str) -> Vec<FieldError> {
+        let field = |field: &str| format!("webhooks.{}.{}", name, field);
+        let mut errors = Vec::new();
+        match reqwest::Url::parse(&self.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            Ok(_) => errors.push(FieldError::new(&field("url"), "invalid", "Webhook URLs must be http or https")),
+            Err(e) => errors.push(FieldError::new(&field("url"), "invalid", format!("Invalid URL {}: {}", self.url, e))),
+        }
+        if self.events.is_empty() {
+            errors.push(FieldError::new(&field("events"), "empty", "List at least one event the webhook fires on"));
+        }
+        for (index, event) in self.events.iter().enumerate() {
+            if !TRIGGER_NAMES.contains(&event.as_str()) {
+                errors.push(FieldError::new(&format!("{}[{}]", field("events"), index), "unknown",
+                    format!("Unknown event '{}', expected one of: {}", event, TRIGGER_NAMES.join(", "))));
+            }
+        }
+        if let Some(template) = &self.template
+            && let Err(e) = serde_json::from_str::<serde_json::Value>(&render(template, &sample_payload(name))) {
+                errors.push(FieldError::new(&field("template"), "invalid", format!("Template doesn't render to JSON: {}", e)));
+        }
+        for (header, value) in &self.headers {
+            if reqwest::header::HeaderName::from_bytes(header.as_bytes()).is_err() {
+                errors.push(FieldError::new(&field("headers"), "invalid", format!("Invalid header name '{}'", header)));
+            }
+            // The value isn't repeated, it may be a credential
+            if reqwest::header::HeaderValue::from_str(value).is_err() {
+                errors.push(FieldError::new(&field("headers"), "invalid", format!("Invalid value for header '{}'", header)));
+            }
+        }
+        if self.timeout_seconds == 0 {
+            errors.push(FieldError::new(&field("timeout_seconds"), "out_of_range", "timeout_seconds must be greater than zero"));
+        }
+        errors
+    }
+
+    fn fires_on(&self, event: &Event) -> bool {
+        (self.processes.is_empty() || self.processes.iter().any(|pattern| crate::keys::glob_match(pattern, &event.process_id)))
+            && event.triggers().iter().any(|trigger| self.events.iter().any(|name| name == trigger))
+    }
+}
+
+// A delivery that ran out of retries
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeadLetter {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub webhook: String,
+    pub trigger: String,
+    pub process_id: String,
+    /// The body that was sent
+    pub payload: String,
+    pub attempts: u32,
+    pub error: String,
+}
+
+pub struct Webhooks {
+    hooks: RwLock<Arc<BTreeMap<String, WebhookConfig>>>,
+    client: reqwest::Client,
+    dead_letter_path: PathBuf,
+    // Serializes appends and rewrites of the dead-letter file
+    dead_letter_lock: Mutex<()>,
+    // Held for a whole redelivery, so two at once can't both send the same dead letter
+    redelivery_lock: tokio::sync::Mutex<()>,
+    // The first retry waits this long, each later one twice as long as the one before
+    backoff_unit: Duration,
+}
+
+impl Webhooks {
+    pub fn new(dead_letter_path: PathBuf) -> Self {
+        Self {
+            hooks: RwLock::new(Arc::new(BTreeMap::new())),
+            client: reqwest::Client::new(),
+            dead_letter_path,
+            dead_letter_lock: Mutex::new(()),
+            redelivery_lock: tokio::sync::Mutex::new(()),
+            backoff_unit: Duration::from_secs(1),
+        }
+    }
+
+    pub fn configure(&self, hooks: BTreeMap<String, WebhookConfig>) {
+        *self.hooks.write().unwrap() = Arc::new(hooks);
+    }
+
+    fn hooks(&self) -> Arc<BTreeMap<String, WebhookConfig>> {
+        self.hooks.read().unwrap().clone()
+    }
+
+    // One POST; only a 2xx status counts as delivered. The error says whether retrying can help
+    async fn send(&self, hook: &WebhookConfig, trigger: &str, body: &str) -> Result<u16, (String, bool)> {
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let mut request = self.client
+            .post(&hook.url)
+            .timeout(Duration::from_secs(hook.timeout_seconds))
+            .header("content-type", "application/json")
+            .header("user-agent", concat!("exeio/", env!("CARGO_PKG_VERSION")))
+            .header("x-exeio-event", trigger)
+            .header("x-exeio-timestamp", &timestamp);
+        if let Some(secret) = &hook.secret {
+            request = request.header("x-exeio-signature", format!("sha256={}", sign(secret, &timestamp, body)));
+        }
+        for (name, value) in &hook.headers {
+            request = request.header(name, value);
+        }
+
+        // A request that can't even be built fails the same way every time
+        let response = request.body(body.to_string()).send().await.map_err(|e| (e.to_string(), !e.is_builder()))?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(status.as_u16())
+        } else {
+            // Other client errors won't go away by themselves
+            let retryable = status.is_server_error() || status.as_u16() == 408 || status.as_u16() == 429;
+            Err((format!("HTTP {}", status), retryable))
+        }
+    }
+
+    async fn deliver(&self, name: String, hook: WebhookConfig, trigger: &'static str, event: Event) {
+        let body = payload(&name, &hook, trigger, &serde_json::to_value(&event).unwrap_or_default(), event.describe());
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            match self.send(&hook, trigger, &body).await {
+                Ok(_) => return,
+                Err((error, retryable)) if !retryable || attempts > hook.max_retries => break error,
+                Err(_) => {
+                    let backoff = 1u32.checked_shl(attempts - 1).unwrap_or(u32::MAX).min(MAX_BACKOFF_SECONDS as u32);
+                    tokio::time::sleep(self.backoff_unit * backoff).await;
+                }
+            }
+        };
+
+        eprintln!("Webhook '{}' failed for {} of process {} after {} attempts: {}", name, trigger, event.process_id, attempts, error);
+        self.append_dead_letter(&DeadLetter {
+            timestamp: chrono::Utc::now(),
+            webhook: name,
+            trigger: trigger.to_string(),
+            process_id: event.process_id,
+            payload: body,
+            attempts,
+            error,
+        });
+    }
+
+    fn append_dead_letter(&self, dead_letter: &DeadLetter) {
+        let _guard = self.dead_letter_lock.lock().unwrap();
+        let appended = serde_json::to_string(dead_letter).map_err(std::io::Error::other).and_then(|line| {
+            use std::io::Write;
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.dead_letter_path)?;
+            writeln!(file, "{}", line)
+        });
+        if let Err(e) = appended {
+            eprintln!("Failed to write webhook dead letter to {}: {}", self.dead_letter_path.display(), e);
+        }
+    }
+
+    fn read_dead_letters(&self) -> std::io::Result<Vec<DeadLetter>> {
+        match std::fs::read_to_string(&self.dead_letter_path) {
+            Ok(content) => Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Sends every dead letter once more to its webhook's current URL and drops the ones that get through.
+    // Returns how many were delivered and the ones still failing
+    async fn redeliver(&self) -> Result<(usize, Vec<DeadLetter>), String> {
+        let _redelivering = self.redelivery_lock.lock().await;
+        let dead_letters = self.read_dead_letters().map_err(|e| format!("Failed to read webhook dead letters: {}", e))?;
+
+        let processed = dead_letters.len();
+        let hooks = self.hooks();
+        let mut delivered = 0;
+        let mut remaining = Vec::new();
+        for mut dead_letter in dead_letters {
+            let result = match hooks.get(&dead_letter.webhook) {
+                Some(hook) => self.send(hook, &dead_letter.trigger, &dead_letter.payload).await.map_err(|(error, _)| error),
+                None => Err("Webhook is no longer configured".to_string()),
+            };
+            match result {
+                Ok(_) => delivered += 1,
+                Err(error) => {
+                    dead_letter.attempts += 1;
+                    dead_letter.error = error;
+                    remaining.push(dead_letter);
+                }
+            }
+        }
+
+        // Dead letters added while redelivering are kept too
+        let rewritten = {
+            let _guard = self.dead_letter_lock.lock().unwrap();
+            let added = self.read_dead_letters().unwrap_or_default().into_iter().skip(processed);
+            let lines: String = remaining.iter().cloned().chain(added)
+                .filter_map(|dead_letter| serde_json::to_string(&dead_letter).ok())
+                .map(|line| line + "\n")
+                .collect();
+            std::fs::write(&self.dead_letter_path, lines)
+        };
+        rewritten.map_err(|e| format!("Failed to rewrite webhook dead letters: {}", e))?;
+        Ok((delivered, remaining))
+    }
+}
+
+// Hex HMAC-SHA256 of "<timestamp>.<body>", so a captured request can't be replayed with a new timestamp
+fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// The event's fields plus `webhook`, `trigger` and a readable `message`
+fn payload_fields(name: &str, trigger: &str, event: &serde_json::Value, message: String) -> serde_json::Map<String, serde_json::Value> {
+    let mut fields = event.as_object().cloned().unwrap_or_default();
+    fields.insert("message".to_string(), message.into());
+    fields.insert("webhook".to_string(), name.into());
+    fields.insert("trigger".to_string(), trigger.into());
+    fields
+}
+
+fn payload(name: &str, hook: &WebhookConfig, trigger: &str, event: &serde_json::Value, message: String) -> String {
+    let fields = payload_fields(name, trigger, event, message);
+    match &hook.template {
+        Some(template) => render(template, &fields),
+        None => serde_json::Value::Object(fields).to_string(),
+    }
+}
+
+// Replaces each {{field}} with the field's value escaped for use inside a JSON string; unknown fields and
+// nulls become empty
+fn render(template: &str, fields: &serde_json::Map<String, serde_json::Value>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let value = match fields.get(rest[start + 2..start + end].trim()) {
+            Some(serde_json::Value::String(text)) => text.clone(),
+            Some(serde_json::Value::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        };
+        let escaped = serde_json::Value::String(value).to_string();
+        rendered.push_str(&escaped[1..escaped.len() - 1]);
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+// What templates are checked against
+fn sample_payload(name: &str) -> serde_json::Map<String, serde_json::Value> {
+    let event = serde_json::json!({
+        "id": 1, "timestamp": chrono::Utc::now(), "process_id": "sample", "event": "exited", "exit_code": 1,
+    });
+    payload_fields(name, "exited", &event, "Process 'sample' exited with code 1".to_string())
+}
+
+// Hands each event to the webhooks that fire on it, for as long as exeio runs
+pub fn start(supervisor: &Supervisor) {
+    let mut receiver = supervisor.subscribe();
+    let supervisor = supervisor.clone();
+    tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    eprintln!("Webhooks missed {} events", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            for (name, hook) in supervisor.webhooks().hooks().iter().filter(|(_, hook)| hook.fires_on(&event)) {
+                // The first matching trigger names the delivery
+                let Some(trigger) = event.triggers().into_iter().find(|trigger| hook.events.iter().any(|name| name == trigger)) else {
+                    continue;
+                };
+                let supervisor = supervisor.clone();
+                let (name, hook, event) = (name.clone(), hook.clone(), event.clone());
+                tokio::spawn(async move { supervisor.webhooks().deliver(name, hook, trigger, event).await });
+            }
+        }
+    });
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct WebhookTestResponse {
+    pub success: bool,
+    pub message: String,
+    /// HTTP status the webhook answered with
+    pub status: u16,
+    pub duration_ms: u64,
+}
+
+// Sends a `test` event once, without retries, and reports how the webhook answered
+pub async fn handle_test_webhook(name: String, audit: AuditContext, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    let audit_details = serde_json::json!({ "webhook": name });
+    let Some(hook) = supervisor.webhooks().hooks().get(&name).cloned() else {
+        let error = ApiError::WebhookNotFound(name);
+        audit.record_error(None, Some(audit_details), &error);
+        return Err(warp::reject::custom(error));
+    };
+
+    let event = serde_json::json!({ "id": 0, "timestamp": chrono::Utc::now(), "process_id": "", "event": "test" });
+    let body = payload(&name, &hook, "test", &event, "Test delivery from exeio".to_string());
+    let started = Instant::now();
+    match supervisor.webhooks().send(&hook, "test", &body).await {
+        Ok(status) => {
+            let message = format!("Webhook '{}' answered with HTTP {}", name, status);
+            audit.record(None, Some(audit_details), &ApiResponse { success: true, message: message.clone() });
+            Ok(warp::reply::json(&WebhookTestResponse {
+                success: true,
+                message,
+                status,
+                duration_ms: started.elapsed().as_millis() as u64,
+            }))
+        }
+        Err((error, _)) => {
+            let error = ApiError::WebhookFailed(format!("Webhook '{}' failed: {}", name, error));
+            audit.record_error(None, Some(audit_details), &error);
+            Err(warp::reject::custom(error))
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DeadLettersPage {
+    pub success: bool,
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+pub async fn handle_dead_letters(supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    match supervisor.webhooks().read_dead_letters() {
+        Ok(dead_letters) => Ok(warp::reply::json(&DeadLettersPage { success: true, dead_letters })),
+        Err(e) => Err(warp::reject::custom(ApiError::Io(format!("Failed to read webhook dead letters: {}", e)))),
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct RedeliverResponse {
+    pub success: bool,
+    pub message: String,
+    pub delivered: usize,
+    /// Still failing or for webhooks no longer configured; they stay in the queue
+    pub remaining: Vec<DeadLetter>,
+}
+
+pub async fn handle_redeliver(audit: AuditContext, supervisor: Supervisor) -> Result<impl warp::Reply, warp::Rejection> {
+    let (delivered, remaining) = match supervisor.webhooks().redeliver().await {
+        Ok(redelivered) => redelivered,
+        Err(message) => {
+            let error = ApiError::Io(message);
+            audit.record_error(None, None, &error);
+            return Err(warp::reject::custom(error));
+        }
+    };
+
+    let message = format!("Redelivered {} dead letters, {} still failing", delivered, remaining.len());
+    audit.record(None, None, &ApiResponse { success: true, message: message.clone() });
+    Ok(warp::reply::json(&RedeliverResponse { success: true, message, delivered, remaining }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicU16, Ordering};
+
+    use super::*;
+    use crate::events::EventKind;
+
+    struct Received {
+        at: Instant,
+        headers: warp::http::HeaderMap,
+        body: String,
+    }
+
+    // A local webhook receiver answering every POST with `status`, recording what it got
+    struct Receiver {
+        addr: SocketAddr,
+        status: Arc<AtomicU16>,
+        received: Arc<Mutex<Vec<Received>>>,
+    }
+
+    impl Receiver {
+        fn start(status: u16) -> Self {
+            use warp::Filter;
+
+            let status = Arc::new(AtomicU16::new(status));
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let route = {
+                let status = status.clone();
+                let received = received.clone();
+                warp::post()
+                    .and(warp::header::headers_cloned())
+                    .and(warp::body::bytes())
+                    .map(move |headers, body: hyper::body::Bytes| {
+                        received.lock().unwrap().push(Received {
+                            at: Instant::now(),
+                            headers,
+                            body: String::from_utf8_lossy(&body).into_owned(),
+                        });
+                        let status = warp::http::StatusCode::from_u16(status.load(Ordering::SeqCst)).unwrap();
+                        warp::reply::with_status("", status)
+                    })
+            };
+            let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+            tokio::spawn(server);
+            Self { addr, status, received }
+        }
+
+        fn url(&self) -> String {
+            format!("http://{}/hook", self.addr)
+        }
+
+        fn count(&self) -> usize {
+            self.received.lock().unwrap().len()
+        }
+    }
+
+    fn webhooks(test: &str) -> Webhooks {
+        let dir = std::env::temp_dir().join(format!("exeio-webhooks-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dead-letters.jsonl");
+        let _ = std::fs::remove_file(&path);
+        Webhooks { backoff_unit: Duration::from_millis(20), ..Webhooks::new(path) }
+    }
+
+    fn hook(url: String, secret: Option<&str>, max_retries: u32) -> WebhookConfig {
+        WebhookConfig {
+            url,
+            events: vec!["exited".to_string()],
+            processes: Vec::new(),
+            secret: secret.map(String::from),
+            template: None,
+            headers: BTreeMap::new(),
+            timeout_seconds: 5,
+            max_retries,
+        }
+    }
+
+    fn exited() -> Event {
+        Event { id: 7, timestamp: chrono::Utc::now(), process_id: "web".to_string(), kind: EventKind::Exited { exit_code: Some(1) } }
+    }
+
+    #[tokio::test]
+    async fn signs_the_timestamp_and_body() {
+        let receiver = Receiver::start(200);
+        let webhooks = webhooks("sign");
+        webhooks.deliver("ops".to_string(), hook(receiver.url(), Some("s3cret"), 0), "exited", exited()).await;
+
+        let received = receiver.received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let request = &received[0];
+        assert_eq!(request.headers["x-exeio-event"], "exited");
+        let timestamp = request.headers["x-exeio-timestamp"].to_str().unwrap();
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(format!("{}.{}", timestamp, request.body).as_bytes());
+        let expected: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(request.headers["x-exeio-signature"].to_str().unwrap(), format!("sha256={}", expected));
+
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["process_id"], "web");
+        assert_eq!(body["webhook"], "ops");
+        assert!(webhooks.read_dead_letters().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unsigned_without_a_secret() {
+        let receiver = Receiver::start(204);
+        webhooks("unsigned").deliver("ops".to_string(), hook(receiver.url(), None, 0), "exited", exited()).await;
+        assert!(!receiver.received.lock().unwrap()[0].headers.contains_key("x-exeio-signature"));
+    }
+
+    #[tokio::test]
+    async fn retries_with_doubling_backoff_then_dead_letters() {
+        let receiver = Receiver::start(503);
+        let webhooks = webhooks("retry");
+        webhooks.deliver("ops".to_string(), hook(receiver.url(), None, 3), "exited", exited()).await;
+
+        let received = receiver.received.lock().unwrap();
+        assert_eq!(received.len(), 4);
+        // 1, 2 and 4 backoff units between the attempts
+        for (gap, units) in received.windows(2).zip([1, 2, 4]) {
+            assert!(gap[1].at - gap[0].at >= webhooks.backoff_unit * units);
+        }
+
+        let dead_letters = webhooks.read_dead_letters().unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].webhook, "ops");
+        assert_eq!(dead_letters[0].attempts, 4);
+        assert_eq!(dead_letters[0].error, "HTTP 503 Service Unavailable");
+        assert_eq!(dead_letters[0].payload, received[0].body);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let receiver = Receiver::start(400);
+        let webhooks = webhooks("client-error");
+        webhooks.deliver("ops".to_string(), hook(receiver.url(), None, 3), "exited", exited()).await;
+
+        assert_eq!(receiver.count(), 1);
+        assert_eq!(webhooks.read_dead_letters().unwrap()[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn requests_that_cannot_be_built_are_not_retried() {
+        let receiver = Receiver::start(200);
+        let webhooks = webhooks("unbuildable");
+        let mut hook = hook(receiver.url(), None, 3);
+        hook.headers.insert("authorization".to_string(), "Bearer\nsecret".to_string());
+        assert_eq!(hook.validate("ops").len(), 1);
+        webhooks.deliver("ops".to_string(), hook, "exited", exited()).await;
+
+        assert_eq!(receiver.count(), 0);
+        let dead_letters = webhooks.read_dead_letters().unwrap();
+        assert_eq!(dead_letters[0].attempts, 1);
+        assert!(!dead_letters[0].error.contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn redelivers_dead_letters_once() {
+        let receiver = Receiver::start(500);
+        let webhooks = webhooks("redeliver");
+        let hook = hook(receiver.url(), None, 0);
+        webhooks.configure(BTreeMap::from([("ops".to_string(), hook.clone())]));
+        webhooks.deliver("ops".to_string(), hook, "exited", exited()).await;
+        assert_eq!(webhooks.read_dead_letters().unwrap().len(), 1);
+
+        // Still failing: kept, with the attempt counted
+        let (delivered, remaining) = webhooks.redeliver().await.unwrap();
+        assert_eq!((delivered, remaining.len()), (0, 1));
+        assert_eq!(webhooks.read_dead_letters().unwrap()[0].attempts, 2);
+
+        // Two redeliveries at once send it only once between them
+        receiver.status.store(200, Ordering::SeqCst);
+        let sent_before = receiver.count();
+        let (first, second) = tokio::join!(webhooks.redeliver(), webhooks.redeliver());
+        assert_eq!(first.unwrap().0 + second.unwrap().0, 1);
+        assert_eq!(receiver.count(), sent_before + 1);
+        assert!(webhooks.read_dead_letters().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn keeps_dead_letters_of_removed_webhooks() {
+        let receiver = Receiver::start(500);
+        let webhooks = webhooks("removed");
+        webhooks.deliver("gone".to_string(), hook(receiver.url(), None, 0), "exited", exited()).await;
+
+        let (delivered, remaining) = webhooks.redeliver().await.unwrap();
+        assert_eq!((delivered, remaining.len()), (0, 1));
+        assert_eq!(remaining[0].error, "Webhook is no longer configured");
+        assert_eq!(receiver.count(), 1);
+    }
+}